@@ -2,6 +2,7 @@ import { invoke } from '@tauri-apps/api/core';
 import { open as openDialog, save as saveDialog } from '@tauri-apps/plugin-dialog';
 import { open as openUrl } from '@tauri-apps/plugin-shell';
 import { getCurrentWindow } from '@tauri-apps/api/window';
+import { listen } from '@tauri-apps/api/event';
 import { writeTextFile } from '@tauri-apps/plugin-fs';
 import { initAnalytics, trackEvent, trackError, setAnalyticsEnabled, isAnalyticsEnabled } from './analytics.js';
 
@@ -19,6 +20,7 @@ document.addEventListener('DOMContentLoaded', async () => {
     await checkInstallation();
     setupEventListeners();
     setupCloseHandler();
+    setupFileWatchers();
     renderUI();
     loadInstallationInfo();
     updateReinstallBanner();
@@ -89,8 +91,12 @@ async function loadConfig() {
         // Config loaded successfully
     } catch (error) {
         console.error('Failed to load config:', error);
+        if (error && error.backup_path) {
+            showToast(`Could not load config (${describeConfigLoadError(error)}). Original backed up to ${error.backup_path}`, 'error');
+        }
         // Use default config if loading fails
         config = {
+            schema_version: 3,
             global_mode: true,
             global_settings: {
                 enabled: true,
@@ -119,7 +125,17 @@ async function loadConfig() {
                 voice_provider: 'system',
                 voice_id: null,
                 fish_audio_api_key: null,
-                respect_do_not_disturb: false
+                fish_audio_base_url: 'https://api.fish.audio',
+                openai_tts: { base_url: 'https://api.openai.com/v1', model: 'tts-1' },
+                openai_api_key: null,
+                system_tts: {},
+                voice_cache_max_mb: 100,
+                loudness: { target_lufs: -16, normalize_voices: true, normalize_uploads: false },
+                speak_summary: { stop: false, notification: false, max_words: 25, timeout_ms: 4000 },
+                respect_do_not_disturb: false,
+                schedules: [],
+                event_cooldowns: {},
+                event_priority: {}
             },
             projects: [],
             sound_library: [
@@ -143,53 +159,61 @@ async function loadConfig() {
     }
 }
 
-async function saveConfig() {
+function describeConfigLoadError(error) {
+    switch (error.kind) {
+        case 'unsupported_version':
+            return `written by a newer version: schema ${error.found}, this app supports ${error.supported}`;
+        case 'migration_failed':
+            return `migration from schema ${error.from_version} failed: ${error.message}`;
+        default:
+            return error.message;
+    }
+}
+
+function describeConfigSaveError(error) {
+    if (error?.kind === 'invalid') {
+        return error.findings
+            .filter(f => f.severity === 'error')
+            .map(f => `${f.field}: ${f.message}`)
+            .join('; ');
+    }
+    return error?.message ?? error;
+}
+
+async function saveConfig(force = false) {
     try {
         // Derive global enabled state from individual hook states (for backend compatibility)
-        const anyHookEnabled =
-            config.global_settings.event_enabled.notification ||
-            config.global_settings.event_enabled.stop ||
-            config.global_settings.event_enabled.pre_tool_use ||
-            config.global_settings.event_enabled.post_tool_use ||
-            config.global_settings.event_enabled.subagent_stop;
+        const anyHookEnabled = Object.values(config.global_settings.event_enabled).some(Boolean);
         config.global_settings.enabled = anyHookEnabled;
 
         // Check if we need to generate project-specific voice files
         // (only when "voice:project" is selected, not for simple voice)
+        const usesProjectVoice = sounds => Object.values(sounds).includes('voice:project');
+        // or when the template changed, since every clip says it
         const needsProjectVoice =
-            config.global_settings.event_sounds.notification === 'voice:project' ||
-            config.global_settings.event_sounds.stop === 'voice:project' ||
-            config.global_settings.event_sounds.pre_tool_use === 'voice:project' ||
-            config.global_settings.event_sounds.post_tool_use === 'voice:project' ||
-            config.global_settings.event_sounds.subagent_stop === 'voice:project' ||
-            config.projects.some(p =>
-                p.event_sounds.notification === 'voice:project' ||
-                p.event_sounds.stop === 'voice:project' ||
-                p.event_sounds.pre_tool_use === 'voice:project' ||
-                p.event_sounds.post_tool_use === 'voice:project' ||
-                p.event_sounds.subagent_stop === 'voice:project'
-            );
+            usesProjectVoice(config.global_settings.event_sounds) ||
+            config.projects.some(p => usesProjectVoice(p.event_sounds)) ||
+            config.global_settings.voice_template !== savedConfig?.global_settings?.voice_template;
 
         // Convert voice options to actual config before saving
         processVoiceSelections(config);
 
-        await invoke('save_config', { config });
+        await invoke('save_config', { config, strict: true, force });
+        refreshHistory();
 
         // Only generate voice files if project-specific voice is selected
         // Global "simple" voice files are generated once and reused
         if (needsProjectVoice) {
-            showToast('Generating voice notifications...');
             try {
-                const result = await invoke('generate_voice_notifications', {
-                    config,
-                    apiKey: config.global_settings.fish_audio_api_key || null
-                });
-                console.log(result);
-                showToast('Settings saved successfully');
+                // The backend reads the API key from the secret store the config points at
+                const report = await runVoiceGeneration('generate_voice_notifications', { config });
+                if (!reportVoiceGeneration(report)) {
+                    return; // Don't update savedConfig if generation didn't finish
+                }
             } catch (voiceError) {
                 console.error('Failed to generate voice notifications:', voiceError);
                 showToast('Saved, but voice generation failed: ' + voiceError, 'error');
-                return; // Don't update savedConfig if generation failed
+                return;
             }
         }
 
@@ -199,23 +223,105 @@ async function saveConfig() {
         trackEvent('settings_saved');
     } catch (error) {
         console.error('Failed to save config:', error);
-        showToast('Failed to save configuration', 'error');
+        if (error?.kind === 'conflict') {
+            showToast('Config file changed on disk since it was loaded', 'error', {
+                text: 'Overwrite',
+                onClick: () => saveConfig(true)
+            });
+            return;
+        }
+        showToast('Failed to save configuration: ' + describeConfigSaveError(error), 'error');
         trackError(error, { context: 'save_config' });
     }
 }
 
+// ===== Voice Generation =====
+
+// Run a voice generation command, showing its progress in one toast that
+// offers to cancel the run.
+async function runVoiceGeneration(command, args) {
+    const toast = document.createElement('div');
+    toast.className = 'toast toast-success visible';
+    const label = document.createElement('span');
+    label.textContent = 'Generating voice notifications...';
+    const cancelBtn = document.createElement('button');
+    cancelBtn.className = 'toast-action';
+    cancelBtn.textContent = 'Cancel';
+    cancelBtn.onclick = () => {
+        cancelBtn.disabled = true;
+        label.textContent = 'Cancelling after the clips in progress...';
+        invoke('cancel_voice_generation');
+    };
+    toast.append(label, cancelBtn);
+    document.body.appendChild(toast);
+
+    const unlisten = await listen('voice-generation-progress', (event) => {
+        const { total, finished, failed } = event.payload;
+        if (cancelBtn.disabled) return;
+        label.textContent = `Generating voice notifications... ${finished}/${total}` +
+            (failed ? ` (${failed} failed)` : '');
+    });
+    try {
+        return await invoke(command, args);
+    } finally {
+        unlisten();
+        toast.remove();
+    }
+}
+
+// Text for a voice provider error ({ code, message }); failures the user can
+// fix get a hint.
+function describeVoiceError(error) {
+    const hints = {
+        auth: 'check the API key',
+        quota_exhausted: 'add credit to the account',
+        invalid_voice: 'choose another voice'
+    };
+    const message = error?.message ?? error;
+    const hint = hints[error?.code];
+    return hint ? `${message} (${hint})` : message;
+}
+
+// Toast the outcome of a run; failed or cancelled clips can be resumed.
+// Returns whether every clip was written.
+function reportVoiceGeneration(report) {
+    console.log('Voice generation:', report);
+    if (report.failed === 0 && report.cancelled === 0) {
+        return true;
+    }
+    const failed = report.jobs.find(job => job.status.state === 'failed')?.status;
+    const firstError = failed && describeVoiceError({ code: failed.code, message: failed.error });
+    const message = report.failed > 0
+        ? `Saved, but ${report.failed} of ${report.total} voice clips failed: ${firstError}`
+        : `Saved; voice generation cancelled with ${report.cancelled} of ${report.total} clips left`;
+    showToast(message, 'error', {
+        text: 'Resume',
+        onClick: async () => {
+            try {
+                const resumed = await runVoiceGeneration('resume_voice_generation', {});
+                if (reportVoiceGeneration(resumed)) {
+                    showToast('Voice notifications generated');
+                }
+            } catch (error) {
+                showToast('Voice generation failed: ' + error, 'error');
+            }
+        }
+    });
+    return false;
+}
+
 function processVoiceSelections(config) {
     // Convert voice selections to voice_enabled flags and update templates
-    const events = ['notification', 'stop', 'pre_tool_use', 'post_tool_use', 'subagent_stop'];
+    // Event keys come from the backend's event registry
+    const events = Object.keys(config.global_settings.event_sounds);
 
     // Process global settings
     events.forEach(event => {
         const value = config.global_settings.event_sounds[event];
         if (value?.startsWith('voice:')) {
             config.global_settings.voice_enabled[event] = true;
-            if (value === 'voice:simple') {
-                config.global_settings.voice_template = '{event} event';
-            } else if (value === 'voice:project') {
+            // voice:simple speaks the template as typed in the Voice template field
+            if (value === 'voice:project') {
                 config.global_settings.voice_template = 'The {event} event was run for the {project} project';
             }
             // Keep the voice: prefix so select-sound.sh can detect it
@@ -255,6 +361,51 @@ function updateSaveButton() {
     }
 }
 
+// ===== External Changes =====
+
+async function setupFileWatchers() {
+    // Config edited outside the editor (text editor, dotfiles sync, another instance)
+    await listen('config-changed', async (event) => {
+        const { config: diskConfig, error } = event.payload;
+        // Profile switches from the tray arrive as config changes
+        refreshProfiles();
+        refreshHistory();
+        if (error) {
+            showToast('Config file changed on disk but could not be read: ' + error, 'error');
+            return;
+        }
+        if (!diskConfig) return;
+
+        if (hasUnsavedChanges()) {
+            showToast('Config file changed on disk', 'error', {
+                text: 'Reload',
+                onClick: async () => {
+                    await loadConfig();
+                    renderUI();
+                }
+            });
+        } else {
+            await loadConfig();
+            renderUI();
+        }
+    });
+
+    await listen('sounds-toggled', () => {
+        updateReinstallBanner();
+        updateInstallToggleButton();
+    });
+
+    // New files in ~/.claude/sounds become available in the sound library
+    await listen('sound-library-changed', (event) => {
+        const added = event.payload.filter(path => !config.sound_library.includes(path));
+        if (added.length === 0) return;
+        config.sound_library.push(...added);
+        markChanged();
+        renderSoundLibrary();
+        populateSoundSelectors();
+    });
+}
+
 // ===== Close Handler =====
 
 async function setupCloseHandler() {
@@ -351,6 +502,12 @@ function setupEventListeners() {
     }
 
     // Respect Do Not Disturb toggle
+    document.getElementById('voiceTemplate').addEventListener('input', (e) => {
+        config.global_settings.voice_template = e.target.value;
+        markChanged();
+        checkVoiceTemplate();
+    });
+
     document.getElementById('respectDND').addEventListener('change', (e) => {
         config.global_settings.respect_do_not_disturb = e.target.checked;
         markChanged();
@@ -399,13 +556,10 @@ function setupEventListeners() {
                         const text = `${eventText} event`;
 
                         try {
-                            await invoke('preview_voice', {
-                                text,
-                                apiKey: config.global_settings.fish_audio_api_key
-                            });
+                            await invoke('preview_voice', { text });
                         } catch (error) {
                             console.error('Failed to preview voice:', error);
-                            showToast('Voice preview failed: ' + error, 'error');
+                            showToast('Voice preview failed: ' + describeVoiceError(error), 'error');
                         }
                     } else {
                         // Regular sound file
@@ -655,6 +809,70 @@ function setupEventListeners() {
         });
     }
 
+    // Named profiles
+    setupProfileControls();
+
+    // Fish Audio API key storage
+    setupApiKeyControls();
+
+    // Saved config versions
+    setupHistoryControls();
+
+    // Per-event cooldowns
+    setupCooldownControls();
+
+    // Quiet hours
+    setupScheduleControls();
+
+    // Export profile button
+    const exportProfileBtn = document.getElementById('exportProfileBtn');
+    if (exportProfileBtn) {
+        exportProfileBtn.addEventListener('click', async () => {
+            try {
+                const destination = await saveDialog({
+                    filters: [{ name: 'Profile archive', extensions: ['zip'] }],
+                    defaultPath: `claude-notifications-profile-${new Date().toISOString().split('T')[0]}.zip`
+                });
+                if (!destination) return;
+
+                const summary = await invoke('export_profile', { destination });
+                showToast(`Profile exported with ${summary.sounds} sounds and ${summary.voices} voices`, 'success');
+                trackEvent('export_profile');
+            } catch (error) {
+                console.error('Export profile failed:', error);
+                showToast('Failed to export profile: ' + error, 'error');
+                trackError(error, { context: 'export_profile' });
+            }
+        });
+    }
+
+    // Import profile button
+    const importProfileBtn = document.getElementById('importProfileBtn');
+    if (importProfileBtn) {
+        importProfileBtn.addEventListener('click', async () => {
+            try {
+                const archive = await openDialog({
+                    multiple: false,
+                    filters: [{ name: 'Profile archive', extensions: ['zip'] }]
+                });
+                if (!archive) return;
+
+                const replace = confirm('Replace existing sounds and voices that have the same name?');
+                const report = await invoke('import_profile', {
+                    archive,
+                    onCollision: replace ? 'replace' : 'skip'
+                });
+                await loadConfig();
+                showToast(`Profile imported: ${report.added.length} added, ${report.replaced.length} replaced, ${report.skipped.length} skipped`, 'success');
+                trackEvent('import_profile');
+            } catch (error) {
+                console.error('Import profile failed:', error);
+                showToast('Failed to import profile: ' + error, 'error');
+                trackError(error, { context: 'import_profile' });
+            }
+        });
+    }
+
     // Open Focus Settings link
     const openFocusSettingsLink = document.getElementById('openFocusSettingsLink');
     if (openFocusSettingsLink) {
@@ -673,7 +891,516 @@ function setupEventListeners() {
     // No manual install button needed
 }
 
-// ===== Rendering =====
+// ===== Profiles =====
+
+function renderProfiles(summary) {
+    const select = document.getElementById('profileSelect');
+    if (!select) return;
+
+    select.innerHTML = '';
+    if (!summary.active) {
+        select.add(new Option('(none)', ''));
+    }
+    for (const name of summary.names) {
+        select.add(new Option(name, name));
+    }
+    select.value = summary.active || '';
+    document.getElementById('cloneProfileBtn').disabled = !summary.active;
+    document.getElementById('deleteProfileBtn').disabled = summary.names.length === 0;
+}
+
+async function refreshProfiles() {
+    try {
+        renderProfiles(await invoke('list_profiles'));
+    } catch (error) {
+        console.error('Failed to load profiles:', error);
+    }
+}
+
+// For the env backend the input holds the variable name instead of the key
+function updateApiKeyInput() {
+    const backend = document.getElementById('apiKeyBackend').value;
+    const input = document.getElementById('apiKeyInput');
+    const reference = config?.global_settings?.fish_audio_api_key;
+    input.type = backend === 'env' ? 'text' : 'password';
+    input.placeholder = backend === 'env' ? 'Variable name (default FISH_AUDIO_API_KEY)' : 'Paste API key';
+    input.value = backend === 'env' && reference?.backend === 'env' ? reference.var : '';
+}
+
+async function refreshApiKeyStatus() {
+    const status = document.getElementById('apiKeyStatus');
+    if (!status || !config?.global_settings) return;
+
+    const reference = config.global_settings.fish_audio_api_key || null;
+    if (reference) {
+        document.getElementById('apiKeyBackend').value = reference.backend;
+    }
+    updateApiKeyInput();
+    document.getElementById('removeApiKeyBtn').disabled = !reference;
+
+    if (!reference) {
+        status.textContent = 'No key configured. Voices use the built-in system voice.';
+        return;
+    }
+    try {
+        const { available, error } = await invoke('get_api_key_status', { reference });
+        const where = reference.backend === 'env' ? `$${reference.var}` : reference.backend.replace('_', ' ');
+        status.textContent = error ? `Can't read the key from ${where}: ${error}`
+            : available ? `Key found in ${where}.`
+            : `No key found in ${where}.`;
+    } catch (error) {
+        console.error('Failed to check API key:', error);
+    }
+}
+
+function setupApiKeyControls() {
+    const backendSelect = document.getElementById('apiKeyBackend');
+    if (!backendSelect) return;
+
+    backendSelect.addEventListener('change', updateApiKeyInput);
+
+    document.getElementById('saveApiKeyBtn').addEventListener('click', async () => {
+        const backend = backendSelect.value;
+        const value = document.getElementById('apiKeyInput').value;
+        const previous = config.global_settings.fish_audio_api_key || null;
+        try {
+            const reference = await invoke('set_api_key', {
+                backend,
+                key: backend === 'env' ? null : value,
+                envVar: backend === 'env' ? value : null
+            });
+            // Don't leave a copy behind in the store we moved away from
+            if (previous && previous.backend !== reference.backend) {
+                await invoke('delete_api_key', { reference: previous }).catch(() => {});
+            }
+            config.global_settings.fish_audio_api_key = reference;
+            markChanged();
+            await refreshApiKeyStatus();
+            showToast('API key saved', 'success');
+            trackEvent('api_key_saved', { backend });
+        } catch (error) {
+            console.error('Failed to save API key:', error);
+            showToast('Failed to save API key: ' + error, 'error');
+        }
+    });
+
+    document.getElementById('removeApiKeyBtn').addEventListener('click', async () => {
+        const reference = config.global_settings.fish_audio_api_key;
+        if (!reference || !confirm('Remove the Fish Audio API key?')) return;
+        try {
+            await invoke('delete_api_key', { reference });
+            config.global_settings.fish_audio_api_key = null;
+            markChanged();
+            await refreshApiKeyStatus();
+        } catch (error) {
+            console.error('Failed to remove API key:', error);
+            showToast('Failed to remove API key: ' + error, 'error');
+        }
+    });
+}
+
+function formatHistoryEntry(entry) {
+    return `${new Date(entry.saved_at).toLocaleString()} - ${entry.summary}`;
+}
+
+function formatHistoryValue(value) {
+    return value === null || value === undefined ? '(none)' : JSON.stringify(value);
+}
+
+// ===== Cooldowns =====
+
+const COOLDOWN_PRIORITIES = {
+    high: 'High: never held back',
+    normal: 'Normal',
+    low: 'Low: also waits for other events'
+};
+
+// Filled from list_hook_events once the editor starts
+let hookEvents = [];
+
+function renderCooldowns() {
+    const minInterval = document.getElementById('minInterval');
+    const container = document.getElementById('eventCooldowns');
+    if (!minInterval || !container || !config) return;
+
+    minInterval.value = config.min_interval;
+    config.global_settings.event_cooldowns ??= {};
+    config.global_settings.event_priority ??= {};
+    const cooldowns = config.global_settings.event_cooldowns;
+    const priorities = config.global_settings.event_priority;
+    container.innerHTML = '';
+
+    for (const event of hookEvents) {
+        const row = document.createElement('div');
+        row.className = 'setting-row';
+
+        const label = document.createElement('div');
+        label.className = 'setting-label';
+        label.innerHTML = `<label>${event.display_name}</label>`;
+
+        const controls = document.createElement('div');
+        controls.className = 'sound-control';
+
+        const seconds = Object.assign(document.createElement('input'), {
+            type: 'number',
+            min: 0,
+            className: 'sound-select',
+            value: cooldowns[event.key] ?? '',
+            placeholder: `${config.min_interval} (default)`,
+            title: 'Cooldown (seconds)'
+        });
+        seconds.addEventListener('change', () => {
+            if (seconds.value === '') {
+                delete cooldowns[event.key];
+            } else {
+                cooldowns[event.key] = Math.max(0, Math.round(Number(seconds.value)));
+            }
+            markChanged();
+        });
+
+        const priority = document.createElement('select');
+        priority.className = 'sound-select';
+        for (const [value, text] of Object.entries(COOLDOWN_PRIORITIES)) {
+            priority.add(new Option(text, value));
+        }
+        priority.value = priorities[event.key] ?? (event.key === 'notification' ? 'high' : 'normal');
+        priority.addEventListener('change', () => {
+            priorities[event.key] = priority.value;
+            markChanged();
+            trackEvent('event_priority_changed', { event: event.key, priority: priority.value });
+        });
+
+        controls.append(seconds, priority);
+        row.append(label, controls);
+        container.appendChild(row);
+    }
+}
+
+async function setupCooldownControls() {
+    const minInterval = document.getElementById('minInterval');
+    if (!minInterval) return;
+
+    try {
+        hookEvents = await invoke('list_hook_events');
+    } catch (error) {
+        console.error('Failed to load hook events:', error);
+    }
+
+    minInterval.addEventListener('change', () => {
+        config.min_interval = Math.max(0, Math.round(Number(minInterval.value) || 0));
+        markChanged();
+        renderCooldowns();
+    });
+
+    renderCooldowns();
+}
+
+// ===== Quiet Hours =====
+
+const SCHEDULE_DAYS = ['mon', 'tue', 'wed', 'thu', 'fri', 'sat', 'sun'];
+const SCHEDULE_ACTIONS = {
+    suppress: 'Silence completely',
+    visual_only: 'Banner only, no sound',
+    lower_volume: 'Lower the volume',
+    deliver: 'Deliver normally'
+};
+
+// Edited separately from the rest of the settings and saved with its own button
+let scheduleDraft = [];
+
+function newScheduleRule() {
+    return {
+        name: '',
+        enabled: true,
+        days: [...SCHEDULE_DAYS],
+        start: '22:00',
+        end: '07:00',
+        timezone: null,
+        action: 'suppress',
+        event_actions: {},
+        volume: 30,
+        allow_permission_requests: true
+    };
+}
+
+function renderSchedules() {
+    const container = document.getElementById('scheduleRules');
+    if (!container) return;
+    container.innerHTML = '';
+
+    scheduleDraft.forEach((rule, index) => {
+        const row = document.createElement('div');
+        row.className = 'setting-row schedule-rule';
+
+        const field = (tag, props, onChange) => {
+            const element = Object.assign(document.createElement(tag), props);
+            element.addEventListener('change', () => onChange(element));
+            row.appendChild(element);
+            return element;
+        };
+
+        field('input', { type: 'checkbox', className: 'toggle', checked: rule.enabled, title: 'Enabled' },
+            el => { rule.enabled = el.checked; });
+        field('input', { type: 'text', className: 'sound-select', value: rule.name, placeholder: 'Name' },
+            el => { rule.name = el.value; });
+
+        for (const day of SCHEDULE_DAYS) {
+            const label = document.createElement('label');
+            const box = document.createElement('input');
+            box.type = 'checkbox';
+            box.checked = rule.days.includes(day);
+            box.addEventListener('change', () => {
+                rule.days = SCHEDULE_DAYS.filter(d => d === day ? box.checked : rule.days.includes(d));
+            });
+            label.append(box, day.charAt(0).toUpperCase() + day.slice(1));
+            row.appendChild(label);
+        }
+
+        field('input', { type: 'time', className: 'sound-select', value: rule.start }, el => { rule.start = el.value; });
+        field('input', { type: 'time', className: 'sound-select', value: rule.end }, el => { rule.end = el.value; });
+        field('input', { type: 'text', className: 'sound-select', value: rule.timezone ?? '', placeholder: 'System time zone' },
+            el => { rule.timezone = el.value.trim() || null; });
+
+        const action = field('select', { className: 'sound-select' }, el => {
+            rule.action = el.value;
+            volume.style.display = el.value === 'lower_volume' ? '' : 'none';
+        });
+        for (const [value, text] of Object.entries(SCHEDULE_ACTIONS)) {
+            action.add(new Option(text, value));
+        }
+        action.value = rule.action;
+
+        const volume = field('input', { type: 'number', min: 0, max: 100, className: 'sound-select', value: rule.volume, title: 'Volume (%)' },
+            el => { rule.volume = Number(el.value); });
+        volume.style.display = rule.action === 'lower_volume' ? '' : 'none';
+
+        const permissionLabel = document.createElement('label');
+        const permission = document.createElement('input');
+        permission.type = 'checkbox';
+        permission.checked = rule.allow_permission_requests;
+        permission.addEventListener('change', () => { rule.allow_permission_requests = permission.checked; });
+        permissionLabel.append(permission, 'Let permission requests through');
+        row.appendChild(permissionLabel);
+
+        const remove = document.createElement('button');
+        remove.className = 'btn-secondary';
+        remove.textContent = 'Remove';
+        remove.addEventListener('click', () => {
+            scheduleDraft.splice(index, 1);
+            renderSchedules();
+        });
+        row.appendChild(remove);
+
+        container.appendChild(row);
+    });
+}
+
+function loadScheduleDraft() {
+    scheduleDraft = JSON.parse(JSON.stringify(config?.global_settings?.schedules ?? []));
+    renderSchedules();
+}
+
+function describeDelivery(result) {
+    if (!result.event_enabled) {
+        return 'This event is turned off, so it would not be delivered at any time.';
+    }
+    const action = {
+        deliver: 'Delivered normally',
+        lower_volume: `Played at ${result.volume}% volume`,
+        visual_only: 'Shown as a banner without sound',
+        suppress: 'Silenced'
+    }[result.action];
+    const rules = result.rules.length ? ` (${result.rules.join(', ')})` : '';
+    const exception = result.permission_exception ? ', as a permission request' : '';
+    return `${action}${exception}${rules}.`;
+}
+
+async function setupScheduleControls() {
+    const eventSelect = document.getElementById('scheduleTestEvent');
+    if (!eventSelect) return;
+
+    try {
+        const events = await invoke('list_hook_events');
+        for (const event of events) {
+            eventSelect.add(new Option(event.display_name, event.key));
+        }
+    } catch (error) {
+        console.error('Failed to load hook events:', error);
+    }
+
+    document.getElementById('addScheduleBtn').addEventListener('click', () => {
+        scheduleDraft.push(newScheduleRule());
+        renderSchedules();
+    });
+
+    document.getElementById('saveSchedulesBtn').addEventListener('click', async () => {
+        try {
+            const saved = await invoke('save_schedules', { schedules: scheduleDraft });
+            // Other unsaved edits in the editor stay as they are
+            config.global_settings.schedules = saved.global_settings.schedules;
+            savedConfig.global_settings.schedules = JSON.parse(JSON.stringify(saved.global_settings.schedules));
+            loadScheduleDraft();
+            updateSaveButton();
+            refreshHistory();
+            showToast('Quiet hours saved', 'success');
+            trackEvent('schedules_saved', { count: scheduleDraft.length });
+        } catch (error) {
+            console.error('Failed to save quiet hours:', error);
+            showToast('Failed to save quiet hours: ' + describeConfigSaveError(error), 'error');
+        }
+    });
+
+    document.getElementById('scheduleTestBtn').addEventListener('click', async () => {
+        const result = document.getElementById('scheduleTestResult');
+        try {
+            const check = await invoke('check_event_delivery', {
+                event: eventSelect.value,
+                at: document.getElementById('scheduleTestTime').value || null,
+                schedules: scheduleDraft
+            });
+            result.textContent = describeDelivery(check);
+        } catch (error) {
+            result.textContent = 'Could not check: ' + error;
+        }
+    });
+}
+
+async function refreshHistory() {
+    const select = document.getElementById('historySelect');
+    if (!select) return;
+
+    try {
+        const entries = await invoke('list_config_history');
+        const selected = select.value;
+        select.innerHTML = '';
+        if (entries.length === 0) {
+            select.add(new Option('No saved versions yet', ''));
+        }
+        for (const entry of entries) {
+            select.add(new Option(formatHistoryEntry(entry), entry.id));
+        }
+        if (entries.some(entry => entry.id === selected)) {
+            select.value = selected;
+        }
+        document.getElementById('showHistoryDiffBtn').disabled = entries.length === 0;
+        document.getElementById('restoreHistoryBtn').disabled = entries.length === 0;
+    } catch (error) {
+        console.error('Failed to load config history:', error);
+    }
+}
+
+function setupHistoryControls() {
+    const select = document.getElementById('historySelect');
+    if (!select) return;
+    const changesList = document.getElementById('historyChanges');
+
+    select.addEventListener('change', () => {
+        changesList.innerHTML = '';
+    });
+
+    document.getElementById('showHistoryDiffBtn').addEventListener('click', async () => {
+        if (!select.value) return;
+        try {
+            const changes = await invoke('diff_config_versions', { from: select.value });
+            changesList.innerHTML = '';
+            if (changes.length === 0) {
+                changesList.innerHTML = '<li>Same as the current config</li>';
+            }
+            for (const change of changes) {
+                const item = document.createElement('li');
+                item.textContent = `${change.field}: ${formatHistoryValue(change.before)} → ${formatHistoryValue(change.after)}`;
+                changesList.appendChild(item);
+            }
+        } catch (error) {
+            console.error('Failed to compare config versions:', error);
+            showToast('Failed to compare versions: ' + error, 'error');
+        }
+    });
+
+    document.getElementById('restoreHistoryBtn').addEventListener('click', async () => {
+        const id = select.value;
+        if (!id) return;
+        if (hasUnsavedChanges() && !confirm('Restoring a version discards unsaved changes. Continue?')) return;
+        try {
+            config = await invoke('restore_config_version', { id });
+            savedConfig = JSON.parse(JSON.stringify(config));
+            renderUI();
+            updateSaveButton();
+            changesList.innerHTML = '';
+            await refreshHistory();
+            showToast('Config restored', 'success');
+            trackEvent('config_version_restored');
+        } catch (error) {
+            console.error('Failed to restore config version:', error);
+            showToast('Failed to restore version: ' + error, 'error');
+        }
+    });
+
+    refreshHistory();
+}
+
+function setupProfileControls() {
+    const select = document.getElementById('profileSelect');
+    if (!select) return;
+
+    const runProfileAction = async (action, context) => {
+        try {
+            renderProfiles(await action());
+        } catch (error) {
+            console.error(`${context} failed:`, error);
+            showToast(`${context} failed: ${error}`, 'error');
+        }
+    };
+
+    select.addEventListener('change', async (e) => {
+        const name = e.target.value;
+        if (!name) return;
+        if (hasUnsavedChanges() && !confirm('Switching profiles discards unsaved changes. Continue?')) {
+            await refreshProfiles();
+            return;
+        }
+        try {
+            config = await invoke('activate_profile', { name });
+            savedConfig = JSON.parse(JSON.stringify(config));
+            renderUI();
+            updateSaveButton();
+            await refreshProfiles();
+            showToast(`Switched to profile "${name}"`, 'success');
+            trackEvent('profile_activated');
+        } catch (error) {
+            console.error('Failed to activate profile:', error);
+            showToast('Failed to switch profile: ' + error, 'error');
+            await refreshProfiles();
+        }
+    });
+
+    document.getElementById('createProfileBtn').addEventListener('click', async () => {
+        if (hasUnsavedChanges()) {
+            showToast('Save your changes before creating a profile from them', 'error');
+            return;
+        }
+        const name = prompt('Name for the new profile:');
+        if (!name) return;
+        await runProfileAction(() => invoke('create_profile', { name }), 'Creating profile');
+    });
+
+    document.getElementById('cloneProfileBtn').addEventListener('click', async () => {
+        const source = select.value;
+        const name = prompt(`Name for the copy of "${source}":`);
+        if (!name) return;
+        await runProfileAction(() => invoke('clone_profile', { source, name }), 'Cloning profile');
+    });
+
+    document.getElementById('deleteProfileBtn').addEventListener('click', async () => {
+        const name = prompt('Name of the profile to delete:', select.value);
+        if (!name || !confirm(`Delete profile "${name}"?`)) return;
+        await runProfileAction(() => invoke('delete_profile', { name }), 'Deleting profile');
+    });
+
+    refreshProfiles();
+}
+
+// ===== Rendering =====// ===== Rendering =====
 
 function renderUI() {
     // Populate all sound selectors
@@ -683,6 +1410,12 @@ function renderUI() {
     renderGlobalSettings();
 
     renderSoundLibrary();
+
+    refreshApiKeyStatus();
+
+    renderCooldowns();
+
+    loadScheduleDraft();
 }
 
 function renderGlobalSettings() {
@@ -759,6 +1492,34 @@ function renderGlobalSettings() {
     if (respectDND) {
         respectDND.checked = config.global_settings.respect_do_not_disturb || false;
     }
+
+    const voiceTemplate = document.getElementById('voiceTemplate');
+    if (voiceTemplate) {
+        voiceTemplate.value = config.global_settings.voice_template;
+        checkVoiceTemplate();
+    }
+}
+
+// Show the template's preview or its first problem under the field
+async function checkVoiceTemplate() {
+    const hint = document.getElementById('voiceTemplateHint');
+    if (!hint) return;
+
+    try {
+        const check = await invoke('check_voice_template', { template: config.global_settings.voice_template });
+        if (check.error) {
+            hint.textContent = check.message;
+            hint.classList.add('hint-error');
+            return;
+        }
+        hint.classList.remove('hint-error');
+        const runtime = check.runtime_variables.length
+            ? ` ${check.runtime_variables.map(v => `{${v}}`).join(', ')} are filled in when the hook fires (needs the audio-notifier CLI and the system voice).`
+            : '';
+        hint.textContent = `Example: "${check.preview}".${runtime}`;
+    } catch (error) {
+        console.error('Failed to check voice template:', error);
+    }
 }
 
 function getVoiceOptionValue(template) {
@@ -793,6 +1554,12 @@ function renderProjectList(containerId = 'projectList', addButtonId = 'addProjec
         card.innerHTML = `
             <div class="project-header">
                 <div class="project-path" title="${escapedPath}">${escapedPath}</div>
+                <select class="project-match" data-index="${index}" title="How the path is matched">
+                    <option value="exact">Exact</option>
+                    <option value="prefix">Folder and subfolders</option>
+                    <option value="glob">Glob</option>
+                    <option value="regex">Regex</option>
+                </select>
                 <input type="checkbox" class="toggle" ${project.enabled ? 'checked' : ''} data-index="${index}">
             </div>
             <div class="project-sounds">
@@ -838,6 +1605,13 @@ function renderProjectList(containerId = 'projectList', addButtonId = 'addProjec
         projectList.appendChild(card);
 
         // Add event listeners for this project
+        const matchSelect = card.querySelector('.project-match');
+        matchSelect.value = project.match || 'exact';
+        matchSelect.addEventListener('change', (e) => {
+            config.projects[index].match = e.target.value;
+            markChanged();
+        });
+
         const toggle = card.querySelector('.toggle');
         toggle.addEventListener('change', (e) => {
             config.projects[index].enabled = e.target.checked;
@@ -1189,8 +1963,8 @@ async function loadActivityLog() {
                 timeZoneName: 'short'
             });
 
-            // Format event name (capitalize first letter, replace underscores)
-            const eventName = event.event
+            // Use the registry label, or format the raw event name for unknown events
+            const eventName = event.label || event.event
                 .split('_')
                 .map(word => word.charAt(0).toUpperCase() + word.slice(1))
                 .join(' ');
@@ -1199,6 +1973,8 @@ async function loadActivityLog() {
             const truncatedMessage = event.message || '—';
             const fullMessage = event.full_message || event.message || '—';
             const project = event.project || '';
+            // AUDIO_NOTIFIER_* variables that were set in the session's shell
+            const overrides = (event.overrides || []).join(', ');
 
             // Check if message is expandable (has more content)
             const isExpandable = fullMessage.length > truncatedMessage.length;
@@ -1207,7 +1983,7 @@ async function loadActivityLog() {
             return `
                 <tr class="activity-row ${isExpandable ? 'expandable' : ''}" data-row-id="${rowId}">
                     <td><div class="timestamp-date">${dateStr}</div><div class="timestamp-time">${timeStr}</div></td>
-                    <td>${eventName}</td>
+                    <td>${eventName}${overrides ? `<div class="hint" title="Environment overrides">${overrides}</div>` : ''}</td>
                     <td class="message-cell">
                         <div class="message-preview">${truncatedMessage}${isExpandable ? '<span class="expand-indicator">…</span>' : ''}</div>
                         ${isExpandable ? `
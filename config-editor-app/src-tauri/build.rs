@@ -1,3 +1,6 @@
 fn main() {
-    tauri_build::build()
+    // The CLI-only build (--no-default-features) has no Tauri app to generate
+    if std::env::var_os("CARGO_FEATURE_GUI").is_some() {
+        tauri_build::build()
+    }
 }
@@ -4,6 +4,50 @@
 
 CONFIG_FILE="$HOME/.claude/audio-notifier.yaml"
 
+# Per-shell overrides, e.g. AUDIO_NOTIFIER_MUTE=1 while recording a demo.
+# The same variables are documented in env_overrides.rs of the config editor.
+#   AUDIO_NOTIFIER_MUTE=1          no sounds or voices (banners still show)
+#   AUDIO_NOTIFIER_PROFILE=<name>  use a saved profile (needs the audio-notifier CLI)
+#   AUDIO_NOTIFIER_VOICE=on|off    force generated voices on or off
+#   AUDIO_NOTIFIER_COOLDOWN=<secs> replace min_interval and every event cooldown
+ACTIVE_OVERRIDES=()
+
+# Print true/false for an override value, nothing if it isn't a boolean
+override_bool() {
+    case "$(printf '%s' "${1:-}" | tr '[:upper:]' '[:lower:]' | xargs)" in
+        1|true|yes|on) echo true ;;
+        0|false|no|off) echo false ;;
+    esac
+}
+
+MUTED=false
+if [[ "$(override_bool "${AUDIO_NOTIFIER_MUTE:-}")" == "true" ]]; then
+    MUTED=true
+    ACTIVE_OVERRIDES+=("mute")
+fi
+
+# A profile lives in audio-notifier-profiles.yaml, which is too nested for the
+# parsing below, so the CLI writes the settings the profile resolves to
+if [[ -n "${AUDIO_NOTIFIER_PROFILE:-}" ]] && command -v audio-notifier >/dev/null 2>&1; then
+    PROFILE_CONFIG=$(mktemp "${TMPDIR:-/tmp}/audio-notifier-profile.XXXXXX")
+    # CONFIG_FILE is read until the sourcing script exits, so clean up then,
+    # after whatever that script already does on exit
+    exit_trap_command() { PREVIOUS_EXIT_TRAP=${3:-}; }
+    eval "exit_trap_command $(trap -p EXIT)"
+    trap "${PREVIOUS_EXIT_TRAP:+$PREVIOUS_EXIT_TRAP; }rm -f \"\$PROFILE_CONFIG\"" EXIT
+    if audio-notifier effective "${PWD:-/tmp}" > "$PROFILE_CONFIG" 2>/dev/null; then
+        CONFIG_FILE="$PROFILE_CONFIG"
+        ACTIVE_OVERRIDES+=("profile=$AUDIO_NOTIFIER_PROFILE")
+    fi
+fi
+
+VOICE_OVERRIDE=$(override_bool "${AUDIO_NOTIFIER_VOICE:-}")
+if [[ "$VOICE_OVERRIDE" == "true" ]]; then
+    ACTIVE_OVERRIDES+=("voice=on")
+elif [[ "$VOICE_OVERRIDE" == "false" ]]; then
+    ACTIVE_OVERRIDES+=("voice=off")
+fi
+
 # Default values (if config doesn't exist)
 SOUND_ENABLED=true
 SOUND_FILE="/System/Library/Sounds/Submarine.aiff"
@@ -79,9 +123,17 @@ if [[ -f "$CONFIG_FILE" ]]; then
     done < "$CONFIG_FILE"
 fi
 
+# Applied after the config so it wins over min_interval (check_spam also
+# ignores event_cooldowns when it is set)
+if [[ "${AUDIO_NOTIFIER_COOLDOWN:-}" =~ ^[0-9]+$ ]]; then
+    MIN_INTERVAL=$AUDIO_NOTIFIER_COOLDOWN
+    ACTIVE_OVERRIDES+=("cooldown=$AUDIO_NOTIFIER_COOLDOWN")
+fi
+
 # Export variables for use in other scripts
 export SOUND_ENABLED SOUND_FILE SOUND_RANDOM MIN_INTERVAL
 export AUDIO_ENABLED TERMINAL_NOTIFIER_ENABLED TERMINAL_NOTIFIER_TITLE TERMINAL_NOTIFIER_SUBTITLE
 export NTFY_ENABLED NTFY_TOPIC NTFY_SERVER NTFY_PRIORITY
 export INACTIVITY_ENABLED INACTIVITY_TIMEOUT INACTIVITY_MESSAGE
 export LOG_NOTIFICATIONS LOG_FILE DEBUG DEBUG_FILE
+export CONFIG_FILE MUTED VOICE_OVERRIDE
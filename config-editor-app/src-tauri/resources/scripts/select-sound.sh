@@ -32,6 +32,16 @@ AVAILABLE_SOUNDS=(
     "/System/Library/Sounds/Basso.aiff"
 )
 
+# With the audio-notifier CLI installed, project rules (exact, prefix, glob,
+# regex on the full directory), repo configs and per-project voice clips are
+# resolved the same way as in the editor. The basename lookups below are only
+# used without it.
+RESOLVED_SOUND=""
+if [[ "${EVENT_TYPE:-}" =~ ^[a-z_]+$ && "$EVENT_TYPE" != "default" && "$EVENT_TYPE" != "inactivity" ]] \
+    && command -v audio-notifier >/dev/null 2>&1; then
+    RESOLVED_SOUND=$(audio-notifier project sound "$EVENT_TYPE" --dir "${PWD:-/tmp}" 2>/dev/null || true)
+fi
+
 # Load project-specific sound mappings if exists
 PROJECT_SOUNDS_FILE="$HOME/.claude/project-sounds.conf"
 CUSTOM_SOUND=""
@@ -41,58 +51,79 @@ if [[ -f "$PROJECT_SOUNDS_FILE" && -n "$PROJECT_NAME" ]]; then
     CUSTOM_SOUND=$(grep "^${PROJECT_NAME}=" "$PROJECT_SOUNDS_FILE" 2>/dev/null | cut -d= -f2)
 fi
 
+# Config files to search for event sounds, highest precedence first.
+# A repo can commit its own .claude/audio-notifier.yaml to override the global one.
+CONFIG_FILES=()
+REPO_ROOT=$(cd "${PWD:-/tmp}" 2>/dev/null && git rev-parse --show-toplevel 2>/dev/null || true)
+if [[ -n "$REPO_ROOT" && "$REPO_ROOT" != "$HOME" && -f "$REPO_ROOT/.claude/audio-notifier.yaml" ]]; then
+    CONFIG_FILES+=("$REPO_ROOT/.claude/audio-notifier.yaml")
+fi
+CONFIG_FILES+=("${CONFIG_FILE:-$HOME/.claude/audio-notifier.yaml}")
+
+# Print the first value for a key across CONFIG_FILES
+lookup_event_sound() {
+    local key="$1"
+    local value=""
+    for config_file in "${CONFIG_FILES[@]}"; do
+        value=$(grep -E "^[[:space:]]*${key}:" "$config_file" 2>/dev/null | head -1 | awk -F': ' '{print $2}' | sed 's/#.*//' | xargs)
+        if [[ -n "$value" ]]; then
+            echo "$value"
+            return
+        fi
+    done
+}
+
 # Check for event-specific sound first
 EVENT_SOUND=""
 if [[ -n "${EVENT_TYPE:-}" ]]; then
     # Look for event_sounds in YAML config (simple parsing)
     case "$EVENT_TYPE" in
         notification|permission)
-            EVENT_SOUND=$(grep -E "^[[:space:]]*notification:" "$HOME/.claude/audio-notifier.yaml" 2>/dev/null | head -1 | awk -F': ' '{print $2}' | sed 's/#.*//' | xargs)
-            ;;
-        stop)
-            EVENT_SOUND=$(grep -E "^[[:space:]]*stop:" "$HOME/.claude/audio-notifier.yaml" 2>/dev/null | head -1 | awk -F': ' '{print $2}' | sed 's/#.*//' | xargs)
+            EVENT_SOUND=$(lookup_event_sound notification)
             ;;
-        pre_tool_use)
-            EVENT_SOUND=$(grep -E "^[[:space:]]*pre_tool_use:" "$HOME/.claude/audio-notifier.yaml" 2>/dev/null | head -1 | awk -F': ' '{print $2}' | sed 's/#.*//' | xargs)
-            ;;
-        post_tool_use)
-            EVENT_SOUND=$(grep -E "^[[:space:]]*post_tool_use:" "$HOME/.claude/audio-notifier.yaml" 2>/dev/null | head -1 | awk -F': ' '{print $2}' | sed 's/#.*//' | xargs)
+        inactivity)
+            EVENT_SOUND=$(grep -E "^[[:space:]]*inactivity:" "${CONFIG_FILE:-$HOME/.claude/audio-notifier.yaml}" 2>/dev/null | sed 's/.*:[[:space:]]*\([^#]*\).*/\1/' | tr -d ' ')
             ;;
-        subagent_stop)
-            EVENT_SOUND=$(grep -E "^[[:space:]]*subagent_stop:" "$HOME/.claude/audio-notifier.yaml" 2>/dev/null | head -1 | awk -F': ' '{print $2}' | sed 's/#.*//' | xargs)
+        default)
             ;;
-        inactivity)
-            EVENT_SOUND=$(grep -E "^[[:space:]]*inactivity:" "$HOME/.claude/audio-notifier.yaml" 2>/dev/null | sed 's/.*:[[:space:]]*\([^#]*\).*/\1/' | tr -d ' ')
+        *)
+            EVENT_SOUND=$(lookup_event_sound "$EVENT_TYPE")
             ;;
     esac
 fi
 
+# AUDIO_NOTIFIER_VOICE=on|off (see read-config.sh) forces voices for every event
+if [[ "${VOICE_OVERRIDE:-}" == "false" && "$EVENT_SOUND" == voice:* ]]; then
+    EVENT_SOUND=""
+elif [[ "${VOICE_OVERRIDE:-}" == "true" && "$EVENT_SOUND" != voice:* && -n "${EVENT_TYPE:-}" ]]; then
+    EVENT_SOUND="voice:simple"
+fi
+
+# Print dir/<name>.<ext> for the first audio extension that exists
+# (same order as audio_format::find_clip)
+find_voice_file() {
+    local dir="$1" name="$2" ext
+    for ext in wav mp3 aiff ogg flac m4a; do
+        if [[ -f "$dir/$name.$ext" ]]; then
+            echo "$dir/$name.$ext"
+            return 0
+        fi
+    done
+    return 1
+}
+
 # Handle voice: prefix for Fish Audio voices
 if [[ "$EVENT_SOUND" == voice:* ]]; then
     # Map voice type to actual voice file
     VOICE_TYPE="${EVENT_SOUND#voice:}"  # Remove "voice:" prefix
 
-    # Determine voice file path based on event type
-    case "$EVENT_TYPE" in
-        notification)
-            VOICE_FILE="$HOME/.claude/voices/global/notification.mp3"
-            ;;
-        stop)
-            VOICE_FILE="$HOME/.claude/voices/global/stop.mp3"
-            ;;
-        pre_tool_use)
-            VOICE_FILE="$HOME/.claude/voices/global/pre_tool_use.mp3"
-            ;;
-        post_tool_use)
-            VOICE_FILE="$HOME/.claude/voices/global/post_tool_use.mp3"
-            ;;
-        subagent_stop)
-            VOICE_FILE="$HOME/.claude/voices/global/subagent_stop.mp3"
-            ;;
-        *)
-            VOICE_FILE=""
-            ;;
-    esac
+    # Voice files are generated per event as voices/global/<event_key>.<ext>,
+    # with the extension of their actual format (mp3, wav, aiff, ...)
+    if [[ "$EVENT_TYPE" =~ ^[a-z_]+$ ]]; then
+        VOICE_FILE=$(find_voice_file "$HOME/.claude/voices/global" "$EVENT_TYPE" || true)
+    else
+        VOICE_FILE=""
+    fi
 
     # Use voice file if it exists
     if [[ -f "$VOICE_FILE" ]]; then
@@ -100,8 +131,11 @@ if [[ "$EVENT_SOUND" == voice:* ]]; then
     fi
 fi
 
-# Select sound (priority: event > custom project > random > default)
-if [[ -n "$EVENT_SOUND" && -f "$EVENT_SOUND" ]]; then
+# Select sound (priority: resolved > event > custom project > random > default)
+if [[ -n "$RESOLVED_SOUND" && -f "$RESOLVED_SOUND" ]]; then
+    SELECTED_SOUND="$RESOLVED_SOUND"
+    SOUND_SOURCE="event (${EVENT_TYPE}, project rules)"
+elif [[ -n "$EVENT_SOUND" && -f "$EVENT_SOUND" ]]; then
     # Use event-specific sound
     SELECTED_SOUND="$EVENT_SOUND"
     SOUND_SOURCE="event (${EVENT_TYPE})"
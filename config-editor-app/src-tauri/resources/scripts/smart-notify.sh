@@ -38,33 +38,60 @@ debug_log() {
     fi
 }
 
+# Print the value for an event in a per-event map under global_settings
+# (event_enabled, event_cooldowns, event_priority), or nothing if it isn't set.
+# A repo-local <repo>/.claude/audio-notifier.yaml takes precedence over the global config
+# Usage: event_setting <map> <event_type>
+event_setting() {
+    local map="$1"
+    local event_type="$2"
+    local config_files=()
+    local repo_root=$(cd "${PWD:-/tmp}" 2>/dev/null && git rev-parse --show-toplevel 2>/dev/null || true)
+
+    if [[ -n "$repo_root" && "$repo_root" != "$HOME" && -f "$repo_root/.claude/audio-notifier.yaml" ]]; then
+        config_files+=("$repo_root/.claude/audio-notifier.yaml")
+    fi
+    if [[ -f "${CONFIG_FILE:-$HOME/.claude/audio-notifier.yaml}" ]]; then
+        config_files+=("${CONFIG_FILE:-$HOME/.claude/audio-notifier.yaml}")
+    fi
+
+    local value=""
+    local config_file
+    for config_file in "${config_files[@]}"; do
+        value=$(awk '
+            /^global_settings:/ { in_global=1; next }
+            /^[a-z_]/ { in_global=0; in_map=0 }
+            in_global && /^[[:space:]]*'"$map"':[[:space:]]*$/ { in_map=1; next }
+            in_map && /^[[:space:]]*'"$event_type"':[[:space:]]*/ {
+                gsub(/^[[:space:]]*'"$event_type"':[[:space:]]*/, "");
+                gsub(/#.*/, "");
+                gsub(/[[:space:]]*$/, "");
+                print;
+                exit
+            }
+            in_map && /^[[:space:]]*[a-z_]+:[[:space:]]*$/ && !/^[[:space:]]*'"$event_type"':/ { in_map=0 }
+        ' "$config_file")
+        [[ -n "$value" ]] && break
+    done
+
+    echo "$value"
+}
+
 # Check if a specific event type is enabled in config
+# Usage: is_event_enabled <event_type> [default]  (default applies when the key is missing)
 is_event_enabled() {
     local event_type="$1"
-    local config_file="$HOME/.claude/audio-notifier.yaml"
+    local default_enabled="${2:-true}"
 
-    if [[ ! -f "$config_file" ]]; then
-        # If no config, assume enabled
-        return 0
-    fi
-
-    # Read the event_enabled value for this event type under global_settings
-    local enabled=$(awk '
-        /^[[:space:]]*event_enabled:[[:space:]]*$/ { in_event_enabled=1; next }
-        in_event_enabled && /^[[:space:]]*'"$event_type"':[[:space:]]*/ {
-            gsub(/^[[:space:]]*'"$event_type"':[[:space:]]*/, "");
-            gsub(/#.*/, "");
-            gsub(/[[:space:]]*$/, "");
-            print;
-            exit
-        }
-        in_event_enabled && /^[[:space:]]*[a-z_]+:[[:space:]]*$/ && !/^[[:space:]]*'"$event_type"':/ { in_event_enabled=0 }
-    ' "$config_file")
+    local enabled=$(event_setting event_enabled "$event_type")
 
     debug_log "Event enabled check for $event_type: ${enabled:-not found}"
 
-    # Default to enabled if not found
-    if [[ -z "$enabled" || "$enabled" == "true" ]]; then
+    if [[ -z "$enabled" ]]; then
+        enabled="$default_enabled"
+    fi
+
+    if [[ "$enabled" == "true" ]]; then
         return 0  # Enabled
     else
         return 1  # Disabled
@@ -74,7 +101,7 @@ is_event_enabled() {
 # Check if Do Not Disturb is active and should be respected
 check_do_not_disturb() {
     # Read config setting
-    local respect_dnd=$(grep "respect_do_not_disturb:" "$HOME/.claude/audio-notifier.yaml" 2>/dev/null | awk -F': ' '{print $2}' | sed 's/#.*//' | xargs)
+    local respect_dnd=$(grep "respect_do_not_disturb:" "${CONFIG_FILE:-$HOME/.claude/audio-notifier.yaml}" 2>/dev/null | awk -F': ' '{print $2}' | sed 's/#.*//' | xargs)
 
     debug_log "DND respect setting: ${respect_dnd:-not set}"
 
@@ -103,6 +130,120 @@ check_do_not_disturb() {
     return 1  # Play audio
 }
 
+# Values for voice template variables (see template.rs in the config editor)
+HOOK_TOOL=""
+HOOK_SUBAGENT=""
+TURN_STARTED_AT=""
+# The last assistant message as written, for speak_summary; the message
+# send_notification gets is flattened to one line for display
+SUMMARY_MESSAGE=""
+
+# Read the tool, subagent and turn start from the hook payload and transcript
+# Usage: read_hook_context <hook_json>
+read_hook_context() {
+    local input="$1"
+    HOOK_TOOL=$(echo "$input" | jq -r '.tool_name // empty' 2>/dev/null || true)
+
+    local transcript_path=$(echo "$input" | jq -r '.transcript_path // empty' 2>/dev/null | sed "s|^~|$HOME|")
+    if [[ -z "$transcript_path" || "$transcript_path" =~ \.\. || ! -f "$transcript_path" ]]; then
+        return 0
+    fi
+
+    # The turn started with the last user entry holding a typed prompt (tool
+    # results are user entries too, but their content is a list)
+    TURN_STARTED_AT=$(tail -n 500 "$transcript_path" 2>/dev/null | \
+        jq -r 'select(.type == "user" and (.message.content | type) == "string") | .timestamp // empty' 2>/dev/null | \
+        tail -n 1 || true)
+
+    local last_tool=$(tail -n 200 "$transcript_path" 2>/dev/null | \
+        jq -r 'select(.message.role == "assistant") | .message.content[]? | select(.type == "tool_use") | "\(.name)\t\(.input.subagent_type // "")"' 2>/dev/null | \
+        tail -n 1 || true)
+    if [[ -n "$last_tool" ]]; then
+        HOOK_TOOL="${HOOK_TOOL:-${last_tool%%$'\t'*}}"
+        HOOK_SUBAGENT="${last_tool#*$'\t'}"
+    fi
+}
+
+# Run a command, killing it after <seconds>: coreutils timeout where there is
+# one, else a watchdog (stock macOS has neither timeout nor gtimeout)
+# Usage: run_with_timeout <seconds> <command> [args...]
+run_with_timeout() {
+    local seconds="$1"
+    shift
+    if command -v timeout >/dev/null 2>&1; then
+        timeout "$seconds" "$@"
+        return
+    elif command -v gtimeout >/dev/null 2>&1; then
+        gtimeout "$seconds" "$@"
+        return
+    fi
+
+    # Background jobs get /dev/null as stdin unless it's passed on
+    "$@" <&0 &
+    local pid=$!
+    # The watchdog mustn't hold the caller's output open, and takes the
+    # command's own children (a speech engine) with it
+    ( sleep "$seconds"; pkill -P "$pid"; kill "$pid" ) </dev/null >/dev/null 2>&1 &
+    local watchdog=$!
+    local status=0
+    wait "$pid" || status=$?
+    kill "$watchdog" 2>/dev/null || true
+    return $status
+}
+
+# The clip to play instead of <sound>: the message itself when speak_summary
+# is on for the event, or, for a pre-generated voice clip, a voice_template
+# using hook-time variables ({tool}, {summary}, ...) rendered now with the
+# configured voice provider. Prints <sound> when neither applies or the voice
+# fails or is too slow.
+# Usage: hook_time_voice <event_type> <message> <sound>
+hook_time_voice() {
+    local event_type="$1"
+    local message="$2"
+    local sound="$3"
+    if ! command -v audio-notifier >/dev/null 2>&1; then
+        echo "$sound"
+        return 0
+    fi
+
+    # The CLI gives up after timeout_ms; this is in case it hangs before then
+    local timeout_ms=$(event_setting speak_summary timeout_ms)
+    [[ "$timeout_ms" =~ ^[0-9]+$ ]] || timeout_ms=4000
+    local limit=$(( timeout_ms / 1000 + 2 ))
+
+    # speak_summary: say the last assistant message, or what is waiting for
+    # permission. The whole message goes on stdin, since markdown is stripped
+    # line by line and it may be too long for an argument
+    if [[ ("$event_type" == "stop" || "$event_type" == "notification") && -n "$message" ]] \
+        && [[ "$(event_setting speak_summary "$event_type")" == "true" ]]; then
+        local summary_file=$(printf '%s' "${SUMMARY_MESSAGE:-$message}" | \
+            run_with_timeout "$limit" audio-notifier speak-summary "$event_type" 2>>"$HOME/.claude/hook-execution.log" | \
+            tail -n 1 || true)
+        if [[ -n "$summary_file" && -f "$summary_file" ]]; then
+            echo "[$(date '+%F %T')] Speaking summary: $summary_file" >> "$HOME/.claude/hook-execution.log"
+            echo "$summary_file"
+            return 0
+        fi
+    fi
+
+    # The clip generated ahead of time has hook-time variables left empty
+    if [[ "$sound" == "$HOME/.claude/voices/"* ]] && grep -qE '^[[:space:]]*voice_template:.*\{[#^]?[[:space:]]*(tool|branch|repo|summary|duration|subagent)' "${CONFIG_FILE:-$HOME/.claude/audio-notifier.yaml}" 2>/dev/null; then
+        local render_args=("$event_type" --summary "$message" --speak)
+        [[ -n "$HOOK_TOOL" ]] && render_args+=(--tool "$HOOK_TOOL")
+        [[ -n "$TURN_STARTED_AT" ]] && render_args+=(--started-at "$TURN_STARTED_AT")
+        [[ "$event_type" == "subagent_stop" && -n "$HOOK_SUBAGENT" ]] && render_args+=(--subagent "$HOOK_SUBAGENT")
+
+        local spoken_file=$(run_with_timeout "$limit" audio-notifier template render "${render_args[@]}" 2>>"$HOME/.claude/hook-execution.log" | tail -n 1 || true)
+        if [[ -n "$spoken_file" && -f "$spoken_file" ]]; then
+            echo "[$(date '+%F %T')] Voice template rendered: $spoken_file" >> "$HOME/.claude/hook-execution.log"
+            echo "$spoken_file"
+            return 0
+        fi
+    fi
+
+    echo "$sound"
+}
+
 # Log activity event to JSON
 log_activity_event() {
     local event_type="$1"
@@ -131,6 +272,13 @@ log_activity_event() {
     # Escape project name for JSON
     local escaped_project=$(printf '%s' "$project" | sed 's/"/\\"/g')
 
+    # Per-shell overrides from read-config.sh, as a JSON array
+    local overrides_json=""
+    local override
+    for override in "${ACTIVE_OVERRIDES[@]}"; do
+        overrides_json+="${overrides_json:+, }\"$(printf '%s' "$override" | sed 's/"/\\"/g')\""
+    done
+
     # Create new event entry
     local new_event=$(cat <<EOF
 {
@@ -140,7 +288,8 @@ log_activity_event() {
   "visual": $visual_shown,
   "message": "$truncated_message",
   "full_message": "$full_message",
-  "project": "$escaped_project"
+  "project": "$escaped_project",
+  "overrides": [$overrides_json]
 }
 EOF
 )
@@ -186,6 +335,10 @@ send_notification() {
         subagent_stop-hook)
             event_type="subagent_stop"
             ;;
+        *-hook)
+            # Other registered events use their config key as the reason prefix
+            event_type="${reason%-hook}"
+            ;;
     esac
 
     # ALWAYS log event type
@@ -193,11 +346,29 @@ send_notification() {
 
     debug_log "Event type: $event_type"
 
+    # Quiet hours (global_settings.schedules) are evaluated by the audio-notifier CLI
+    local quiet_action="deliver"
+    local quiet_volume=""
+    if [[ "$event_type" != "default" ]] && command -v audio-notifier >/dev/null 2>&1 \
+        && grep -qE '^[[:space:]]*schedules:[[:space:]]*$' "${CONFIG_FILE:-$HOME/.claude/audio-notifier.yaml}" 2>/dev/null; then
+        local schedule_result=$(audio-notifier schedule check "$event_type" 2>/dev/null || echo deliver)
+        quiet_action="${schedule_result%% *}"
+        if [[ "$schedule_result" =~ volume=([0-9]+) ]]; then
+            quiet_volume="${BASH_REMATCH[1]}"
+        fi
+        echo "[$(date '+%F %T')] Quiet hours: $schedule_result" >> "$HOME/.claude/hook-execution.log"
+    fi
+    if [[ "$quiet_action" == "suppress" ]]; then
+        debug_log "Suppressed by quiet hours"
+        log_activity_event "$event_type" "false" "false" "$message" "${PWD:-/tmp}"
+        return 0
+    fi
+
     # Get custom message for this event type (if configured)
     local custom_message=""
     if [[ -n "$event_type" && "$event_type" != "default" ]]; then
         # Look under the messages: section specifically
-        custom_message=$(awk '/^[[:space:]]*messages:[[:space:]]*$/{flag=1;next}/^[[:space:]]*[a-z_]+:[[:space:]]*$/{flag=0}flag && /^[[:space:]]*'"$event_type"':[[:space:]]*/{gsub(/^[[:space:]]*'"$event_type"':[[:space:]]*"|"[[:space:]]*$/,"");print;exit}' "$HOME/.claude/audio-notifier.yaml" 2>/dev/null)
+        custom_message=$(awk '/^[[:space:]]*messages:[[:space:]]*$/{flag=1;next}/^[[:space:]]*[a-z_]+:[[:space:]]*$/{flag=0}flag && /^[[:space:]]*'"$event_type"':[[:space:]]*/{gsub(/^[[:space:]]*'"$event_type"':[[:space:]]*"|"[[:space:]]*$/,"");print;exit}' "${CONFIG_FILE:-$HOME/.claude/audio-notifier.yaml}" 2>/dev/null)
     fi
     debug_log "Custom message for $event_type: ${custom_message:-none}"
 
@@ -249,15 +420,29 @@ send_notification() {
     local audio_played="false"
     local visual_shown="false"
 
-    # Check if audio should be skipped due to Do Not Disturb
-    if check_do_not_disturb; then
+    # Check if audio should be skipped due to AUDIO_NOTIFIER_MUTE or Do Not Disturb
+    if [[ "${MUTED:-false}" == "true" ]]; then
+        debug_log "Skipping audio: muted by AUDIO_NOTIFIER_MUTE"
+        echo "[$(date '+%F %T')] SKIPPED: AUDIO_NOTIFIER_MUTE" >> "$HOME/.claude/hook-execution.log"
+    elif [[ "$quiet_action" == "visual_only" ]]; then
+        debug_log "Skipping audio: visual only during quiet hours"
+        echo "[$(date '+%F %T')] SKIPPED: quiet hours (visual only)" >> "$HOME/.claude/hook-execution.log"
+    elif check_do_not_disturb; then
         debug_log "Skipping audio due to Do Not Disturb"
         echo "[$(date '+%F %T')] SKIPPED: DND active" >> "$HOME/.claude/hook-execution.log"
     elif [[ "$SOUNDS_ENABLED" == "true" && -f "$sound" ]]; then
-        debug_log "About to play sound: $sound"
-        echo "[$(date '+%F %T')] PLAYING: $sound" >> "$HOME/.claude/hook-execution.log"
+        # Voices spoken at hook time are only generated once they will be heard
+        sound=$(hook_time_voice "$event_type" "$message" "$sound")
+
+        # afplay volume is a multiplier, 1 being normal
+        local volume_arg=""
+        if [[ "$quiet_action" == "lower_volume" && -n "$quiet_volume" ]]; then
+            volume_arg="-v $(awk -v v="$quiet_volume" 'BEGIN { printf "%.2f", v / 100 }') "
+        fi
+        debug_log "About to play sound: $sound ${volume_arg}"
+        echo "[$(date '+%F %T')] PLAYING: $sound ${volume_arg}" >> "$HOME/.claude/hook-execution.log"
         # Use osascript for better audio device access from hooks
-        osascript -e "do shell script \"afplay $(printf '%q' "$sound")\"" >/dev/null 2>&1 &
+        osascript -e "do shell script \"afplay ${volume_arg}$(printf '%q' "$sound")\"" >/dev/null 2>&1 &
         local pid=$!
         debug_log "Audio notification sent (osascript PID: $pid)"
         audio_played="true"
@@ -325,32 +510,71 @@ send_notification() {
     log_activity_event "$event_type" "$audio_played" "$visual_shown" "$message" "${full_project_path:-}"
 }
 
-# Anti-spam check
+# File-name-safe key for a project directory: the SHA-256 of its path (same as
+# project_key in cooldown.rs)
+cooldown_key() {
+    if command -v shasum >/dev/null 2>&1; then
+        printf '%s' "$1" | shasum -a 256 | cut -d' ' -f1
+    else
+        printf '%s' "$1" | sha256sum | cut -d' ' -f1
+    fi
+}
+
+# Anti-spam check: cooldowns are per event and per project, and high-priority
+# events (permission prompts by default) are never throttled.
+# See cooldown.rs in the config editor.
+# Usage: check_spam <event_type>
 check_spam() {
-    local cooldown="${MIN_INTERVAL:-5}"
-    local last_notify_file="$HOME/.claude/.last-notification-time"
+    local event_type="$1"
+    local project_dir=$(cd "${PWD:-/tmp}" 2>/dev/null && git rev-parse --show-toplevel 2>/dev/null || echo "${PWD:-/tmp}")
+
+    # The CLI also applies project rules and profiles
+    if command -v audio-notifier >/dev/null 2>&1; then
+        local result=$(audio-notifier cooldown check "$event_type" --project "$project_dir" --record 2>/dev/null || echo allow)
+        debug_log "Anti-spam check for $event_type: $result"
+        [[ "$result" == allow* ]]
+        return
+    fi
 
-    # Get current time
-    local current_time=$(date +%s)
+    local priority=$(event_setting event_priority "$event_type")
+    if [[ -z "$priority" ]]; then
+        case "$event_type" in
+            notification) priority="high" ;;
+            *) priority="normal" ;;
+        esac
+    fi
 
-    # Get last notification time
-    local last_time=0
-    if [[ -f "$last_notify_file" ]]; then
-        last_time=$(cat "$last_notify_file" 2>/dev/null || echo 0)
+    local cooldown=$(event_setting event_cooldowns "$event_type")
+    if [[ "${AUDIO_NOTIFIER_COOLDOWN:-}" =~ ^[0-9]+$ || ! "$cooldown" =~ ^[0-9]+$ ]]; then
+        cooldown="${MIN_INTERVAL:-5}"
     fi
 
-    # Calculate time since last notification
-    local time_since=$((current_time - last_time))
+    local state_dir="$HOME/.claude/cooldowns"
+    local key=$(cooldown_key "$project_dir")
+    local current_time=$(date +%s)
+
+    if [[ "$priority" != "high" ]]; then
+        local last_time=$(cat "$state_dir/$key--$event_type" 2>/dev/null || echo 0)
+        if [[ "$priority" == "low" ]]; then
+            local last_any=$(cat "$state_dir/$key--any" 2>/dev/null || echo 0)
+            (( last_any > last_time )) && last_time=$last_any
+        fi
 
-    debug_log "Anti-spam check: ${time_since}s since last notification (cooldown: ${cooldown}s)"
+        local time_since=$((current_time - last_time))
+        debug_log "Anti-spam check for $event_type: ${time_since}s since last notification (cooldown: ${cooldown}s, priority: $priority)"
 
-    if [[ $time_since -lt $cooldown ]]; then
-        debug_log "Anti-spam: Skipping notification (cooldown active)"
-        return 1  # Skip notification
+        if [[ $time_since -lt $cooldown ]]; then
+            debug_log "Anti-spam: Skipping notification (cooldown active)"
+            return 1  # Skip notification
+        fi
+    else
+        debug_log "Anti-spam: $event_type is high priority, skipping cooldown"
     fi
 
-    # Update last notification time
-    echo "$current_time" > "$last_notify_file"
+    # Start the cooldown for this event and for low-priority events
+    mkdir -p "$state_dir"
+    echo "$current_time" > "$state_dir/$key--$event_type"
+    echo "$current_time" > "$state_dir/$key--any"
     return 0  # Allow notification
 }
 
@@ -366,6 +590,7 @@ handle_notification_hook() {
 
     # Read JSON input from stdin
     local input=$(cat)
+    read_hook_context "$input"
 
     # Parse notification details
     local message=$(echo "$input" | jq -r '.message // "Claude needs your attention"' 2>/dev/null || echo "Claude needs your attention")
@@ -400,7 +625,7 @@ handle_notification_hook() {
     fi
 
     # Check anti-spam
-    if ! check_spam; then
+    if ! check_spam "notification"; then
         return 0
     fi
 
@@ -422,6 +647,7 @@ handle_stop_hook() {
 
     # Read JSON input from stdin
     local input=$(cat)
+    read_hook_context "$input"
 
     # Get transcript path
     local transcript_path=$(echo "$input" | jq -r '.transcript_path' 2>/dev/null | sed "s|^~|$HOME|")
@@ -437,10 +663,13 @@ handle_stop_hook() {
         return 0
     fi
 
-    # Get last assistant message
-    local last_message=$(tail -n 200 "$transcript_path" 2>/dev/null | \
-        jq -r 'select(.message.role == "assistant") | .message.content[]? | select(.type=="text") | .text' 2>/dev/null | \
+    # Get last assistant message: each text block stays one JSON string until
+    # the last one is picked, so a multi-line message comes out whole
+    SUMMARY_MESSAGE=$(tail -n 200 "$transcript_path" 2>/dev/null | \
+        jq -c 'select(.message.role == "assistant") | .message.content[]? | select(.type=="text") | .text' 2>/dev/null | \
         tail -n 1 | \
+        jq -r '.' 2>/dev/null || true)
+    local last_message=$(printf '%s' "$SUMMARY_MESSAGE" | \
         tr '\n' ' ' | \
         sed 's/[[:space:]]\+/ /g')
 
@@ -452,7 +681,7 @@ handle_stop_hook() {
     debug_log "Last message: ${last_message:0:100}"
 
     # Check anti-spam
-    if ! check_spam; then
+    if ! check_spam "stop"; then
         return 0
     fi
 
@@ -474,12 +703,13 @@ handle_post_tool_use_hook() {
 
     # Read JSON input from stdin
     local input=$(cat)
+    read_hook_context "$input"
 
     local message="Tool execution completed"
     local title="PostToolUse notification from Claude"
 
     # Check anti-spam
-    if ! check_spam; then
+    if ! check_spam "post_tool_use"; then
         return 0
     fi
 
@@ -501,12 +731,13 @@ handle_subagent_stop_hook() {
 
     # Read JSON input from stdin
     local input=$(cat)
+    read_hook_context "$input"
 
     local message="Subagent task completed"
     local title="SubagentStop notification from Claude"
 
     # Check anti-spam
-    if ! check_spam; then
+    if ! check_spam "subagent_stop"; then
         return 0
     fi
 
@@ -528,12 +759,13 @@ handle_pre_tool_use_hook() {
 
     # Read JSON input from stdin
     local input=$(cat)
+    read_hook_context "$input"
 
     local message="Permission required"
     local title="PreToolUse notification from Claude"
 
     # Check anti-spam
-    if ! check_spam; then
+    if ! check_spam "pre_tool_use"; then
         return 0
     fi
 
@@ -543,6 +775,34 @@ handle_pre_tool_use_hook() {
     debug_log "PreToolUse hook completed"
 }
 
+# Handle session lifecycle hooks (UserPromptSubmit, SessionStart, SessionEnd, PreCompact)
+# These are off unless enabled in the config
+handle_lifecycle_hook() {
+    local event_type="$1"
+    local hook_name="$2"
+    local message="$3"
+
+    debug_log "$hook_name hook triggered"
+
+    if ! is_event_enabled "$event_type" false; then
+        debug_log "$hook_name event is disabled, skipping"
+        return 0
+    fi
+
+    # Read JSON input from stdin
+    local input=$(cat)
+    read_hook_context "$input"
+
+    # Check anti-spam
+    if ! check_spam "$event_type"; then
+        return 0
+    fi
+
+    send_notification "$message" "$hook_name notification from Claude" "$event_type-hook"
+
+    debug_log "$hook_name hook completed"
+}
+
 # Main execution
 case "$HOOK_TYPE" in
     notification)
@@ -560,8 +820,20 @@ case "$HOOK_TYPE" in
     subagent_stop|SubagentStop)
         handle_subagent_stop_hook
         ;;
+    user_prompt_submit|UserPromptSubmit)
+        handle_lifecycle_hook "user_prompt_submit" "UserPromptSubmit" "Prompt submitted"
+        ;;
+    session_start|SessionStart)
+        handle_lifecycle_hook "session_start" "SessionStart" "Session started"
+        ;;
+    session_end|SessionEnd)
+        handle_lifecycle_hook "session_end" "SessionEnd" "Session ended"
+        ;;
+    pre_compact|PreCompact)
+        handle_lifecycle_hook "pre_compact" "PreCompact" "Compacting conversation"
+        ;;
     *)
-        echo "Usage: $0 {notification|stop|pre_tool_use|post_tool_use|subagent_stop}" >&2
+        echo "Usage: $0 {notification|stop|pre_tool_use|post_tool_use|subagent_stop|user_prompt_submit|session_start|session_end|pre_compact}" >&2
         echo "" >&2
         echo "This script is called by Claude Code hooks:" >&2
         echo "  notification    - Fired when Claude needs permission or after 60s idle" >&2
@@ -569,6 +841,10 @@ case "$HOOK_TYPE" in
         echo "  pre_tool_use    - Fired before tool calls (permission prompts)" >&2
         echo "  post_tool_use   - Fired after tool calls complete" >&2
         echo "  subagent_stop   - Fired when subagent tasks complete" >&2
+        echo "  user_prompt_submit - Fired when a prompt is submitted" >&2
+        echo "  session_start   - Fired when a session starts or resumes" >&2
+        echo "  session_end     - Fired when a session ends" >&2
+        echo "  pre_compact     - Fired before the conversation is compacted" >&2
         exit 1
         ;;
 esac
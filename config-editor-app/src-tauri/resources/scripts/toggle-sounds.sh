@@ -5,6 +5,16 @@
 SETTINGS_FILE="$HOME/.claude/settings.json"
 FLAG_FILE="$HOME/.claude/.sounds-enabled"
 
+# Prefer the audio-notifier CLI when it's installed
+if command -v audio-notifier >/dev/null 2>&1; then
+    case "$1" in
+        on) exec audio-notifier enable ;;
+        off) exec audio-notifier disable ;;
+        status) exec audio-notifier status ;;
+        *) exec audio-notifier toggle ;;
+    esac
+fi
+
 enable_sounds() {
     touch "$FLAG_FILE"
     echo "✅ Tool sounds ENABLED"
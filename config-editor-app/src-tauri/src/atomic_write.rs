@@ -0,0 +1,179 @@
+// Atomic, lock-protected file writes with optimistic concurrency checks
+//
+// Used for audio-notifier.yaml and ~/.claude/settings.json, which can be
+// edited by hand or by another Claude Code process while the editor is open.
+// Writers take an advisory lock on a sibling `.lock` file, write to a temp
+// file in the same directory and rename it over the target, so readers never
+// see a half-written file.
+
+use fs2::FileExt;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum WriteError {
+    Io(io::Error),
+    /// The file on disk no longer matches the content the caller last read
+    Conflict,
+}
+
+impl From<io::Error> for WriteError {
+    fn from(e: io::Error) -> Self {
+        WriteError::Io(e)
+    }
+}
+
+/// Advisory lock on `<path>.lock`, released when dropped.
+pub struct FileLock {
+    file: File,
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+/// Block until we hold the advisory lock for `path`.
+pub fn lock(path: &Path) -> io::Result<FileLock> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path(path))?;
+    file.lock_exclusive()?;
+    Ok(FileLock { file })
+}
+
+/// Hash of the file's current contents. A missing file hashes like an empty one.
+pub fn current_hash(path: &Path) -> io::Result<String> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(content_hash(&bytes)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(content_hash(b"")),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn content_hash(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Write `contents` to `path` via temp file and rename. Caller must hold the lock.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(parent)?;
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = parent.join(format!(".{}.tmp-{}", file_name, std::process::id()));
+
+    let result = (|| {
+        // Only the owner can read the temp file while it holds the new contents;
+        // a stale one from a crashed writer may have looser permissions
+        let _ = fs::remove_file(&temp_path);
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut temp = options.open(&temp_path)?;
+        temp.write_all(contents)?;
+        temp.sync_all()?;
+
+        // Keep the original file's permissions; new files stay 0600
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp_path, metadata.permissions())?;
+        }
+
+        fs::rename(&temp_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Lock `path`, check it still hashes to `expected_hash` (if given), then write
+/// atomically. Returns the hash of the new contents.
+pub fn write_if_unchanged(
+    path: &Path,
+    expected_hash: Option<&str>,
+    contents: &[u8],
+) -> Result<String, WriteError> {
+    let _lock = lock(path)?;
+
+    if let Some(expected) = expected_hash {
+        if current_hash(path)? != expected {
+            return Err(WriteError::Conflict);
+        }
+    }
+
+    write_atomic(path, contents)?;
+    Ok(content_hash(contents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn mode(path: &Path) -> u32 {
+        fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn new_files_are_private_and_existing_permissions_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+
+        write_atomic(&path, b"{}").unwrap();
+        assert_eq!(mode(&path), 0o600);
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_atomic(&path, b"{\"a\": 1}").unwrap();
+        assert_eq!(mode(&path), 0o644);
+        assert_eq!(fs::read(&path).unwrap(), b"{\"a\": 1}");
+    }
+
+    #[test]
+    fn stale_temp_files_are_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audio-notifier.yaml");
+        let temp_path = dir.path().join(format!(".audio-notifier.yaml.tmp-{}", std::process::id()));
+        fs::write(&temp_path, "stale").unwrap();
+        fs::set_permissions(&temp_path, fs::Permissions::from_mode(0o666)).unwrap();
+
+        write_atomic(&path, b"fresh").unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"fresh");
+        assert_eq!(mode(&path), 0o600);
+        assert!(!temp_path.exists());
+    }
+
+    #[test]
+    fn writes_are_rejected_after_an_external_edit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audio-notifier.yaml");
+        let loaded = write_if_unchanged(&path, Some(&current_hash(&path).unwrap()), b"one").unwrap();
+
+        fs::write(&path, "edited by hand").unwrap();
+
+        assert!(matches!(write_if_unchanged(&path, Some(&loaded), b"two"), Err(WriteError::Conflict)));
+        assert_eq!(fs::read(&path).unwrap(), b"edited by hand");
+    }
+}
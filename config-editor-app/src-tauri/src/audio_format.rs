@@ -0,0 +1,212 @@
+// Audio format detection from file contents
+//
+// Providers don't always return what they were asked for: `say` writes AIFF,
+// Piper and espeak-ng write WAV, and a self-hosted OpenAI-compatible server may
+// ignore response_format. Clips are therefore stored under the extension their
+// magic bytes match, and looked up by name without assuming an extension.
+// select-sound.sh resolves voice files the same way (see find_voice_file).
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    Mp3,
+    Wav,
+    Aiff,
+    Ogg,
+    Flac,
+    M4a,
+}
+
+impl AudioFormat {
+    /// Lookup order when a clip exists under several extensions. Normalized
+    /// clips are .wav, so one wins over a reinstalled copy of its original.
+    pub const ALL: [AudioFormat; 6] =
+        [AudioFormat::Wav, AudioFormat::Mp3, AudioFormat::Aiff, AudioFormat::Ogg, AudioFormat::Flac, AudioFormat::M4a];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Aiff => "aiff",
+            AudioFormat::Ogg => "ogg",
+            AudioFormat::Flac => "flac",
+            AudioFormat::M4a => "m4a",
+        }
+    }
+
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "mp3" => Some(AudioFormat::Mp3),
+            "wav" => Some(AudioFormat::Wav),
+            "aiff" | "aif" | "aifc" => Some(AudioFormat::Aiff),
+            "ogg" | "oga" | "opus" => Some(AudioFormat::Ogg),
+            "flac" => Some(AudioFormat::Flac),
+            "m4a" | "mp4" | "aac" => Some(AudioFormat::M4a),
+            _ => None,
+        }
+    }
+}
+
+/// The format `bytes` start with, if it's one we know.
+pub fn sniff(bytes: &[u8]) -> Option<AudioFormat> {
+    let tag = |range: std::ops::Range<usize>| bytes.get(range);
+    if bytes.starts_with(b"ID3") || (bytes.len() >= 2 && bytes[0] == 0xFF && bytes[1] & 0xE0 == 0xE0) {
+        Some(AudioFormat::Mp3)
+    } else if bytes.starts_with(b"RIFF") && tag(8..12) == Some(b"WAVE") {
+        Some(AudioFormat::Wav)
+    } else if bytes.starts_with(b"FORM") && matches!(tag(8..12), Some(b"AIFF") | Some(b"AIFC")) {
+        Some(AudioFormat::Aiff)
+    } else if bytes.starts_with(b"OggS") {
+        Some(AudioFormat::Ogg)
+    } else if bytes.starts_with(b"fLaC") {
+        Some(AudioFormat::Flac)
+    } else if tag(4..8) == Some(b"ftyp") {
+        Some(AudioFormat::M4a)
+    } else {
+        None
+    }
+}
+
+/// The format of the file at `path`, from its first bytes.
+pub fn sniff_file(path: &Path) -> Option<AudioFormat> {
+    let mut header = [0u8; 12];
+    let read = fs::File::open(path).and_then(|mut f| f.read(&mut header)).ok()?;
+    sniff(&header[..read])
+}
+
+/// The clip `dir/<stem>.<ext>` for whichever audio extension exists.
+pub fn find_clip(dir: &Path, stem: &str) -> Option<PathBuf> {
+    AudioFormat::ALL
+        .iter()
+        .map(|format| dir.join(format!("{}.{}", stem, format.extension())))
+        .find(|path| path.is_file())
+}
+
+/// Copy the clip at `src` to `dest` with the extension of its actual format,
+/// removing copies of the same clip under other extensions so lookups don't
+/// find a stale one. Returns where it was written.
+pub fn install_clip(src: &Path, dest: &Path) -> Result<PathBuf, String> {
+    let format = sniff_file(src)
+        .or_else(|| src.extension().and_then(|ext| AudioFormat::from_extension(&ext.to_string_lossy())))
+        .ok_or_else(|| format!("{} is not a recognised audio file", src.display()))?;
+    let target = dest.with_extension(format.extension());
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create voice directory: {}", e))?;
+    }
+    fs::copy(src, &target).map_err(|e| format!("Failed to write voice file: {}", e))?;
+    for other in AudioFormat::ALL.iter().filter(|&&other| other != format) {
+        let _ = fs::remove_file(dest.with_extension(other.extension()));
+    }
+    Ok(target)
+}
+
+/// Rename clips under `dir` whose extension doesn't match their contents,
+/// such as AIFF from `say` saved as .mp3 by earlier versions. Returns how
+/// many were renamed.
+pub fn relabel_dir(dir: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else { return 0 };
+    let mut renamed = 0;
+    for path in entries.filter_map(|entry| entry.ok().map(|e| e.path())) {
+        if path.is_dir() {
+            renamed += relabel_dir(&path);
+            continue;
+        }
+        let Some(labelled) = path.extension().and_then(|ext| AudioFormat::from_extension(&ext.to_string_lossy())) else {
+            continue;
+        };
+        match sniff_file(&path) {
+            Some(actual) if actual != labelled => {
+                // A correctly named copy is newer than the mislabelled one
+                let target = path.with_extension(actual.extension());
+                let moved = if target.exists() { fs::remove_file(&path) } else { fs::rename(&path, &target) };
+                if moved.is_ok() {
+                    renamed += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    renamed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_each_format_from_its_magic_bytes() {
+        assert_eq!(sniff(b"ID3\x04\0"), Some(AudioFormat::Mp3));
+        // An MPEG frame without an ID3 tag
+        assert_eq!(sniff(&[0xFF, 0xFB, 0x90, 0x64]), Some(AudioFormat::Mp3));
+        assert_eq!(sniff(b"RIFF\x24\0\0\0WAVEfmt "), Some(AudioFormat::Wav));
+        assert_eq!(sniff(b"FORM\0\0\0\0AIFF"), Some(AudioFormat::Aiff));
+        assert_eq!(sniff(b"FORM\0\0\0\0AIFC"), Some(AudioFormat::Aiff));
+        assert_eq!(sniff(b"OggS\0\x02"), Some(AudioFormat::Ogg));
+        assert_eq!(sniff(b"fLaC\0\0\0\x22"), Some(AudioFormat::Flac));
+        assert_eq!(sniff(b"\0\0\0\x20ftypM4A "), Some(AudioFormat::M4a));
+    }
+
+    #[test]
+    fn rejects_unknown_and_truncated_headers() {
+        assert_eq!(sniff(b""), None);
+        assert_eq!(sniff(b"{\"error\": \"bad key\"}"), None);
+        assert_eq!(sniff(&[0xFF]), None);
+        assert_eq!(sniff(&[0xFF, 0x00]), None);
+        // RIFF containers that aren't WAVE, and headers cut short
+        assert_eq!(sniff(b"RIFF\0\0\0\0AVI "), None);
+        assert_eq!(sniff(b"RIFF\0\0\0\0WA"), None);
+        assert_eq!(sniff(b"FORM\0\0\0\08SVX"), None);
+        assert_eq!(sniff(b"\0\0\0\x20fty"), None);
+    }
+
+    #[test]
+    fn sniffs_files_and_falls_back_to_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let clip = dir.path().join("clip.mp3");
+        fs::write(&clip, b"FORM\0\0\0\0AIFF and more audio").unwrap();
+        assert_eq!(sniff_file(&clip), Some(AudioFormat::Aiff));
+        assert_eq!(sniff_file(&dir.path().join("missing.wav")), None);
+    }
+
+    #[test]
+    fn installs_under_the_sniffed_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("cached.mp3");
+        fs::write(&src, b"FORM\0\0\0\0AIFF").unwrap();
+        fs::create_dir_all(dir.path().join("global")).unwrap();
+        fs::write(dir.path().join("global/stop.mp3"), b"ID3 old").unwrap();
+
+        let installed = install_clip(&src, &dir.path().join("global/stop")).unwrap();
+        assert_eq!(installed, dir.path().join("global/stop.aiff"));
+        assert!(!dir.path().join("global/stop.mp3").exists());
+        assert_eq!(find_clip(&dir.path().join("global"), "stop"), Some(installed));
+        assert_eq!(find_clip(&dir.path().join("global"), "notification"), None);
+
+        let text = dir.path().join("notes.txt");
+        fs::write(&text, b"hello").unwrap();
+        assert!(install_clip(&text, &dir.path().join("x")).is_err());
+    }
+
+    #[test]
+    fn relabels_mislabelled_clips() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("projects/abc");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(dir.path().join("notification.mp3"), b"RIFF\0\0\0\0WAVE").unwrap();
+        // A correctly named copy already exists
+        fs::write(project.join("stop.mp3"), b"FORM\0\0\0\0AIFF").unwrap();
+        fs::write(project.join("stop.aiff"), b"FORM\0\0\0\0AIFF").unwrap();
+        fs::write(project.join("ok.mp3"), b"ID3").unwrap();
+        fs::write(project.join("notes.txt"), b"RIFF\0\0\0\0WAVE").unwrap();
+
+        assert_eq!(relabel_dir(dir.path()), 2);
+        assert!(dir.path().join("notification.wav").exists());
+        assert!(!project.join("stop.mp3").exists() && project.join("stop.aiff").exists());
+        assert!(project.join("ok.mp3").exists() && project.join("notes.txt").exists());
+    }
+}
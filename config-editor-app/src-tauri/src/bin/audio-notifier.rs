@@ -0,0 +1,1008 @@
+// Headless command-line interface for Claude Code Audio Notifier
+//
+// Reads and writes the same ~/.claude/audio-notifier.yaml as the config editor,
+// through the same loading, migration, validation and atomic-save code, for
+// use over SSH or on machines without a GUI. Replaces toggle-sounds.sh.
+//
+//   audio-notifier status
+//   audio-notifier effective [DIR]
+//   audio-notifier toggle | enable [EVENT] | disable [EVENT]
+//   audio-notifier get global_settings.voice_template
+//   audio-notifier set projects[0].enabled false
+//   audio-notifier project add ~/src/api --match prefix
+//   audio-notifier project sound stop --dir "$PWD"
+//   audio-notifier schedule check stop --at 2026-01-09T23:30
+//   audio-notifier cooldown check post_tool_use --record
+//   audio-notifier template render stop --summary "$MESSAGE" --speak
+//   audio-notifier speak-summary stop --message "$MESSAGE"
+//   audio-notifier tts speak "Build finished" --output done.wav
+//   audio-notifier voice-cache stats
+//   audio-notifier --json project list
+
+use audio_notifier_config_editor::cooldown;
+use audio_notifier_config_editor::env_overrides::EnvOverrides;
+use audio_notifier_config_editor::events::{EventMap, HookEvent};
+use audio_notifier_config_editor::layered_config::{self, EffectiveConfig};
+use audio_notifier_config_editor::schedule;
+use audio_notifier_config_editor::speech_summary;
+use audio_notifier_config_editor::system_tts::{self, TtsEngine};
+use audio_notifier_config_editor::voice_cache::{GcReport, VoiceCache};
+use audio_notifier_config_editor::voice_jobs::VoiceJob;
+use audio_notifier_config_editor::voice_provider;
+use audio_notifier_config_editor::template::{self, Template, TemplateVars};
+use audio_notifier_config_editor::project_match::{self, MatchKind};
+use audio_notifier_config_editor::{
+    audio_format, expand_home, get_config_path, get_cooldown_dir, get_home_dir, get_profiles_path, get_sounds_enabled_path,
+    get_voice_cache_dir, hash_string, profiles, read_config, validation, write_config, Config, ConfigSaveError, GlobalSettings,
+    ProjectConfig,
+};
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use serde_yaml::Value;
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(name = "audio-notifier", version, about = "Configure Claude Code Audio Notifier from the terminal")]
+struct Cli {
+    /// Print machine-readable JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show whether sounds are on and which events are enabled
+    Status,
+    /// Print the config hooks would use in DIR (default: the current
+    /// directory), including AUDIO_NOTIFIER_* overrides from this shell
+    Effective { dir: Option<PathBuf> },
+    /// Turn all sounds off if they are on, and on if they are off
+    Toggle,
+    /// Turn sounds on, or only the given event (e.g. stop, pre_tool_use)
+    Enable { event: Option<String> },
+    /// Turn sounds off, or only the given event
+    Disable { event: Option<String> },
+    /// Print a config value, e.g. global_settings.voice_template or projects[0].path
+    Get { key: String },
+    /// Change a config value; VALUE is parsed as YAML (true, 3, "text")
+    Set { key: String, value: String },
+    /// Manage per-project overrides
+    Project {
+        #[command(subcommand)]
+        command: ProjectCommand,
+    },
+    /// Inspect quiet hours
+    Schedule {
+        #[command(subcommand)]
+        command: ScheduleCommand,
+    },
+    /// Inspect per-event cooldowns and priorities
+    Cooldown {
+        #[command(subcommand)]
+        command: CooldownCommand,
+    },
+    /// Check and render voice templates
+    Template {
+        #[command(subcommand)]
+        command: TemplateCommand,
+    },
+    /// Speak MESSAGE for EVENT (stop or notification) with the configured
+    /// voice and print the clip's path. Used by the hook scripts; fails when
+    /// speak_summary is off for EVENT or the voice takes longer than timeout_ms
+    SpeakSummary {
+        event: String,
+        /// Text to speak; read from standard input when omitted
+        #[arg(long)]
+        message: Option<String>,
+        /// Print the text that would be spoken without generating it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Offline speech engines for the system voice provider
+    Tts {
+        #[command(subcommand)]
+        command: TtsCommand,
+    },
+    /// Inspect and trim the cache of generated voices
+    VoiceCache {
+        #[command(subcommand)]
+        command: VoiceCacheCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum VoiceCacheCommand {
+    /// Show how many clips are cached and how much space they use
+    Stats,
+    /// Evict least recently used clips down to voice_cache_max_mb
+    Gc,
+    /// Delete every cached clip
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum TtsCommand {
+    /// Show which speech engines are installed and which one is used
+    Engines,
+    /// Write TEXT spoken by the system speech engine to an audio file
+    Speak {
+        text: String,
+        /// WAV file (AIFF with say)
+        #[arg(long, short)]
+        output: PathBuf,
+        /// Use this engine instead of the configured one
+        #[arg(long, value_enum)]
+        engine: Option<EngineArg>,
+        /// Voice for the engine, replacing the configured one
+        #[arg(long)]
+        voice: Option<String>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum EngineArg {
+    Say,
+    Piper,
+    EspeakNg,
+    Pico2wave,
+}
+
+impl From<EngineArg> for TtsEngine {
+    fn from(engine: EngineArg) -> Self {
+        match engine {
+            EngineArg::Say => TtsEngine::Say,
+            EngineArg::Piper => TtsEngine::Piper,
+            EngineArg::EspeakNg => TtsEngine::EspeakNg,
+            EngineArg::Pico2wave => TtsEngine::Pico2wave,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum TemplateCommand {
+    /// Report problems with a template and the variables it uses
+    Check { template: String },
+    /// Print the voice_template text for EVENT in the current directory.
+    /// Used by the hook scripts.
+    Render {
+        event: String,
+        /// Tool from the hook payload
+        #[arg(long)]
+        tool: Option<String>,
+        /// Last assistant message; {summary} is its first sentence
+        #[arg(long)]
+        summary: Option<String>,
+        /// When the turn started (RFC 3339), for {duration}
+        #[arg(long)]
+        started_at: Option<String>,
+        #[arg(long)]
+        subagent: Option<String>,
+        /// Render this instead of the configured voice_template
+        #[arg(long)]
+        template: Option<String>,
+        /// Speak the text with the configured voice provider and print the
+        /// clip's path instead
+        #[arg(long)]
+        speak: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CooldownCommand {
+    List,
+    /// Print whether EVENT may notify now: allow or throttle. Used by the
+    /// hook scripts.
+    Check {
+        event: String,
+        /// Project directory the cooldown is tracked for (default: the
+        /// current directory)
+        #[arg(long)]
+        project: Option<String>,
+        /// Start the cooldown if the event is allowed
+        #[arg(long)]
+        record: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScheduleCommand {
+    List,
+    /// Print what quiet hours do to EVENT now, or at --at: deliver,
+    /// lower_volume, visual_only or suppress. Used by the hook scripts.
+    Check {
+        event: String,
+        /// RFC 3339, or YYYY-MM-DDTHH:MM in local time
+        #[arg(long)]
+        at: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProjectCommand {
+    /// Add a project rule, starting from the global sounds and events
+    Add {
+        path: String,
+        #[arg(long = "match", value_enum, default_value_t = MatchArg::Exact)]
+        match_kind: MatchArg,
+        /// Name shown in the editor
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Remove the project rule with this path
+    Remove { path: String },
+    List,
+    /// Print the file EVENT plays in DIR (default: the current directory),
+    /// after project rules and repo config, with voice:* resolved to the
+    /// generated clip. Used by select-sound.sh
+    Sound {
+        event: String,
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum MatchArg {
+    Exact,
+    Prefix,
+    Glob,
+    Regex,
+}
+
+impl From<MatchArg> for MatchKind {
+    fn from(arg: MatchArg) -> Self {
+        match arg {
+            MatchArg::Exact => MatchKind::Exact,
+            MatchArg::Prefix => MatchKind::Prefix,
+            MatchArg::Glob => MatchKind::Glob,
+            MatchArg::Regex => MatchKind::Regex,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Status {
+    sounds_enabled: bool,
+    config_path: String,
+    schema_version: u32,
+    active_profile: Option<String>,
+    enabled_events: Vec<&'static str>,
+    voice_provider: String,
+    projects: usize,
+    /// AUDIO_NOTIFIER_* overrides set in this shell
+    overrides: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command, cli.json) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("audio-notifier: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command, json: bool) -> Result<(), String> {
+    match command {
+        Command::Status => status(json),
+        Command::Effective { dir } => effective(dir, json),
+        Command::Toggle => set_sounds_enabled(!get_sounds_enabled_path().exists(), json),
+        Command::Enable { event: None } => set_sounds_enabled(true, json),
+        Command::Disable { event: None } => set_sounds_enabled(false, json),
+        Command::Enable { event: Some(event) } => set_event_enabled(&event, true, json),
+        Command::Disable { event: Some(event) } => set_event_enabled(&event, false, json),
+        Command::Get { key } => {
+            let (config, _) = load()?;
+            let doc = to_doc(&config)?;
+            let value = lookup(&doc, &key)?.ok_or_else(|| format!("No config key {}", key))?;
+            print_value(value, json)
+        }
+        Command::Set { key, value } => set_value(&key, &value, json),
+        Command::Project { command } => project(command, json),
+        Command::Schedule { command } => schedule(command, json),
+        Command::Cooldown { command } => cooldowns(command, json),
+        Command::Template { command } => templates(command, json),
+        Command::SpeakSummary { event, message, dry_run } => speak_summary(&event, message, dry_run, json),
+        Command::Tts { command } => tts(command, json),
+        Command::VoiceCache { command } => voice_cache(command, json),
+    }
+}
+
+// ===== Commands =====
+
+fn status(json: bool) -> Result<(), String> {
+    let (config, _) = load()?;
+    let active_profile = profiles::load(&get_profiles_path(), &home())
+        .ok()
+        .and_then(|(store, _)| store.active);
+    let overrides = env_overrides();
+
+    let status = Status {
+        sounds_enabled: get_sounds_enabled_path().exists(),
+        config_path: get_config_path().to_string_lossy().to_string(),
+        schema_version: config.schema_version,
+        active_profile,
+        enabled_events: HookEvent::ALL
+            .iter()
+            .filter(|event| config.global_settings.event_enabled.is_enabled(**event))
+            .map(|event| event.key())
+            .collect(),
+        voice_provider: config.global_settings.voice_provider.clone(),
+        projects: config.projects.len(),
+        overrides: overrides.active(),
+    };
+
+    if json {
+        return print_json(&status);
+    }
+    println!("Sounds:         {}", if status.sounds_enabled { "on" } else { "off" });
+    println!("Config:         {} (schema {})", status.config_path, status.schema_version);
+    println!("Profile:        {}", status.active_profile.as_deref().unwrap_or("(none)"));
+    println!("Events:         {}", status.enabled_events.join(", "));
+    println!("Voice provider: {}", status.voice_provider);
+    println!("Projects:       {}", status.projects);
+    if !status.overrides.is_empty() {
+        println!("Overrides:      {}", status.overrides.join(", "));
+    }
+    Ok(())
+}
+
+fn effective(dir: Option<PathBuf>, json: bool) -> Result<(), String> {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))?,
+    };
+    let effective = effective_config(&dir)?;
+
+    if json {
+        return print_json(&effective);
+    }
+    let yaml = serde_yaml::to_string(&effective.config).map_err(|e| format!("Failed to serialize config: {}", e))?;
+    print!("{}", yaml);
+    Ok(())
+}
+
+/// Same flag file as the editor's sound toggle and the hook scripts.
+fn set_sounds_enabled(enabled: bool, json: bool) -> Result<(), String> {
+    let path = get_sounds_enabled_path();
+    if enabled {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to enable sounds: {}", e))?;
+        }
+        fs::write(&path, "").map_err(|e| format!("Failed to enable sounds: {}", e))?;
+    } else if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Failed to disable sounds: {}", e))?;
+    }
+
+    if json {
+        return print_json(&serde_json::json!({ "sounds_enabled": enabled }));
+    }
+    println!("Sounds {}", if enabled { "on" } else { "off" });
+    Ok(())
+}
+
+fn set_event_enabled(key: &str, enabled: bool, json: bool) -> Result<(), String> {
+    let event = HookEvent::from_key(key).ok_or_else(|| {
+        let known: Vec<&str> = HookEvent::ALL.iter().map(|e| e.key()).collect();
+        format!("Unknown event {}; expected one of {}", key, known.join(", "))
+    })?;
+
+    let (mut config, hash) = load()?;
+    config.global_settings.event_enabled.set(event, enabled);
+    // Enabling an event brings its sound into validation
+    let sound = format!("global_settings.event_sounds.{}", event.key());
+    save(&config, &hash, &["global_settings.event_enabled", &sound])?;
+
+    if json {
+        return print_json(&serde_json::json!({ "event": event.key(), "enabled": enabled }));
+    }
+    println!("{} {}", event.info().display_name, if enabled { "enabled" } else { "disabled" });
+    Ok(())
+}
+
+fn set_value(key: &str, raw: &str, json: bool) -> Result<(), String> {
+    let (config, hash) = load()?;
+    let mut doc = to_doc(&config)?;
+
+    let parsed: Value = serde_yaml::from_str(raw).unwrap_or_else(|_| Value::from(raw));
+    let as_text = Value::from(raw);
+    *lookup_mut(&mut doc, key)? = parsed.clone();
+
+    // `set voice_id 123` means the string "123" when the field holds text
+    let updated: Config = match serde_yaml::from_value(doc.clone()) {
+        Ok(updated) => updated,
+        Err(e) if parsed == as_text => return Err(format!("Invalid value for {}: {}", key, e)),
+        Err(e) => {
+            *lookup_mut(&mut doc, key)? = as_text;
+            serde_yaml::from_value(doc).map_err(|_| format!("Invalid value for {}: {}", key, e))?
+        }
+    };
+    // Keys serde doesn't know about are dropped on parse rather than rejected
+    let updated_doc = to_doc(&updated)?;
+    let stored = lookup(&updated_doc, key)?.ok_or_else(|| format!("No config key {}", key))?;
+    save(&updated, &hash, &[key])?;
+    print_value(stored, json)
+}
+
+fn project(command: ProjectCommand, json: bool) -> Result<(), String> {
+    match command {
+        ProjectCommand::Add { path, match_kind, name } => {
+            let match_kind = MatchKind::from(match_kind);
+            let path = normalize_project_path(&path, match_kind);
+            let (mut config, hash) = load()?;
+            if config.projects.iter().any(|p| p.path == path) {
+                return Err(format!("A project rule for {} already exists", path));
+            }
+
+            let global = &config.global_settings;
+            let project = ProjectConfig {
+                path,
+                match_kind,
+                display_name: name,
+                enabled: true,
+                event_sounds: global.event_sounds.clone(),
+                event_enabled: global.event_enabled.clone(),
+                voice_enabled: global.voice_enabled.clone(),
+                event_cooldowns: EventMap::default(),
+            };
+            config.projects.push(project.clone());
+            save(&config, &hash, &[&format!("projects[{}]", config.projects.len() - 1)])?;
+
+            if json {
+                return print_json(&project);
+            }
+            println!("Added {}", project.path);
+            Ok(())
+        }
+        ProjectCommand::Remove { path } => {
+            let (mut config, hash) = load()?;
+            let before = config.projects.len();
+            let normalized = normalize_project_path(&path, MatchKind::Exact);
+            config.projects.retain(|p| p.path != path && p.path != normalized);
+            if config.projects.len() == before {
+                return Err(format!("No project rule for {}", path));
+            }
+            save(&config, &hash, &[])?;
+
+            if json {
+                return print_json(&serde_json::json!({ "removed": path }));
+            }
+            println!("Removed {}", path);
+            Ok(())
+        }
+        ProjectCommand::Sound { event, dir } => project_sound(&event, dir, json),
+        ProjectCommand::List => {
+            let (config, _) = load()?;
+            if json {
+                return print_json(&config.projects);
+            }
+            if config.projects.is_empty() {
+                println!("No project rules");
+            }
+            for project in &config.projects {
+                let kind = variant_name(&project.match_kind);
+                let name = project.display_name.as_deref().map(|n| format!(" ({})", n)).unwrap_or_default();
+                let disabled = if project.enabled { "" } else { " [disabled]" };
+                println!("{:<7} {}{}{}", kind, project.path, name, disabled);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ProjectSound {
+    event: String,
+    /// Path of the project rule that applies, if any
+    project: Option<String>,
+    /// The event_sounds value: a file or voice:*
+    sound: String,
+    /// What to play; None when a voice clip hasn't been generated
+    file: Option<PathBuf>,
+}
+
+fn project_sound(event: &str, dir: Option<PathBuf>, json: bool) -> Result<(), String> {
+    let event = HookEvent::from_key(event).ok_or_else(|| format!("Unknown event {}", event))?;
+    let dir = match dir {
+        Some(dir) => dir,
+        None => env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))?,
+    };
+    let (config, _) = load()?;
+    let rule = project_match::best_match(&config.projects, &dir, Path::new(&home()));
+    let effective = effective_config(&dir)?;
+    let sound = effective.config.global_settings.event_sounds.get(event).cloned().unwrap_or_default();
+
+    let file = if sound.starts_with("voice:") {
+        // A matching project has its own clips, rendered with its name
+        let voices = get_voice_cache_dir();
+        let project_dir = rule.map(|rule| voices.join("projects").join(hash_string(&rule.path)));
+        project_dir
+            .into_iter()
+            .chain([voices.join("global")])
+            .find_map(|dir| audio_format::find_clip(&dir, event.key()))
+    } else {
+        Some(expand_home(&sound)).filter(|path| path.is_file())
+    };
+
+    let result = ProjectSound { event: event.key().to_string(), project: rule.map(|rule| rule.path.clone()), sound, file };
+    if json {
+        return print_json(&result);
+    }
+    match result.file {
+        Some(file) => {
+            println!("{}", file.display());
+            Ok(())
+        }
+        None => Err(format!("No sound file for {} ({})", result.event, result.sound)),
+    }
+}
+
+fn schedule(command: ScheduleCommand, json: bool) -> Result<(), String> {
+    match command {
+        ScheduleCommand::List => {
+            let (config, _) = load()?;
+            let rules = &config.global_settings.schedules;
+            if json {
+                return print_json(rules);
+            }
+            if rules.is_empty() {
+                println!("No quiet hours");
+            }
+            for rule in rules {
+                let days: Vec<String> = rule.days.iter().map(variant_name).collect();
+                let name = if rule.name.is_empty() { String::new() } else { format!("{}: ", rule.name) };
+                let disabled = if rule.enabled { "" } else { " [disabled]" };
+                println!(
+                    "{}{}-{} {} ({}) {}{}",
+                    name,
+                    rule.start,
+                    rule.end,
+                    days.join(","),
+                    rule.timezone.as_deref().unwrap_or("local time"),
+                    variant_name(&rule.action),
+                    disabled,
+                );
+            }
+            Ok(())
+        }
+        ScheduleCommand::Check { event, at } => {
+            let event = HookEvent::from_key(&event).ok_or_else(|| format!("Unknown event {}", event))?;
+            let at = match at {
+                Some(at) => schedule::parse_instant(&at)?,
+                None => Utc::now(),
+            };
+            // The same layers the hooks see, so profile and repo schedules count
+            let dir = env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))?;
+            let config = effective_config(&dir)?.config;
+            let delivery = schedule::delivery(&config.global_settings.schedules, event, at)?;
+
+            if json {
+                return print_json(&delivery);
+            }
+            let mut line = variant_name(&delivery.action);
+            if let Some(volume) = delivery.volume {
+                line.push_str(&format!(" volume={}", volume));
+            }
+            if !delivery.rules.is_empty() {
+                line.push_str(&format!(" rules={}", delivery.rules.join(",")));
+            }
+            if delivery.permission_exception {
+                line.push_str(" permission_exception");
+            }
+            println!("{}", line);
+            Ok(())
+        }
+    }
+}
+
+fn cooldowns(command: CooldownCommand, json: bool) -> Result<(), String> {
+    let cwd = env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))?;
+    let config = effective_config(&cwd)?.config;
+
+    match command {
+        CooldownCommand::List => {
+            let rows: Vec<_> = HookEvent::ALL
+                .iter()
+                .map(|&event| (event.key(), cooldown::cooldown(&config, event), cooldown::priority(&config, event)))
+                .collect();
+            if json {
+                let rows: Vec<_> = rows
+                    .iter()
+                    .map(|(event, cooldown, priority)| {
+                        serde_json::json!({ "event": event, "cooldown": cooldown, "priority": priority })
+                    })
+                    .collect();
+                return print_json(&rows);
+            }
+            for (event, cooldown, priority) in &rows {
+                println!("{:<20} {:>4}s {}", event, cooldown, variant_name(priority));
+            }
+            Ok(())
+        }
+        CooldownCommand::Check { event, project, record } => {
+            let event = HookEvent::from_key(&event).ok_or_else(|| format!("Unknown event {}", event))?;
+            let project = project.unwrap_or_else(|| cwd.to_string_lossy().to_string());
+            let now = Utc::now().timestamp();
+            let check = cooldown::check(&config, event, &project, now, &get_cooldown_dir());
+            if record && check.allowed {
+                cooldown::record(event, &project, now, &get_cooldown_dir())?;
+            }
+
+            if json {
+                return print_json(&check);
+            }
+            let mut line = format!(
+                "{} priority={} cooldown={}",
+                if check.allowed { "allow" } else { "throttle" },
+                variant_name(&check.priority),
+                check.cooldown,
+            );
+            if !check.allowed {
+                line.push_str(&format!(" remaining={}", check.remaining));
+            }
+            println!("{}", line);
+            Ok(())
+        }
+    }
+}
+
+fn templates(command: TemplateCommand, json: bool) -> Result<(), String> {
+    match command {
+        TemplateCommand::Check { template } => {
+            let parsed = Template::parse(&template).map_err(|e| e.to_string())?;
+            if json {
+                return print_json(&serde_json::json!({
+                    "variables": parsed.variables(),
+                    "runtime_variables": parsed.runtime_variables(),
+                }));
+            }
+            println!("OK: {}", parsed.render(&TemplateVars::sample(HookEvent::Stop)));
+            let runtime = parsed.runtime_variables();
+            if !runtime.is_empty() {
+                println!("Filled in when the hook fires: {}", runtime.join(", "));
+            }
+            Ok(())
+        }
+        TemplateCommand::Render { event, tool, summary, started_at, subagent, template, speak } => {
+            let event = HookEvent::from_key(&event).ok_or_else(|| format!("Unknown event {}", event))?;
+            let cwd = env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))?;
+            let config = effective_config(&cwd)?.config;
+            let source = template.unwrap_or_else(|| config.global_settings.voice_template.clone());
+            let parsed = Template::parse(&source).map_err(|e| format!("Invalid voice template: {}", e))?;
+
+            let mut vars = TemplateVars::for_event(event);
+            vars.set_git(&cwd);
+            let project = project_match::best_match(&config.projects, &cwd, Path::new(&home()))
+                .map(|p| p.display_name.clone().unwrap_or_else(|| p.path.clone()))
+                .or_else(|| Some(vars.get("repo").to_string()).filter(|r| !r.is_empty()))
+                .or_else(|| cwd.file_name().map(|n| n.to_string_lossy().to_string()))
+                .unwrap_or_default();
+            vars.set("project", project);
+            vars.set("tool", tool.unwrap_or_default());
+            vars.set("summary", template::summarize(&summary.unwrap_or_default()));
+            vars.set("subagent", subagent.unwrap_or_default().replace(['-', '_'], " "));
+            if let Some(started_at) = started_at {
+                let started = DateTime::parse_from_rfc3339(started_at.trim())
+                    .map_err(|_| format!("\"{}\" is not an RFC 3339 time", started_at))?;
+                let seconds = (Utc::now() - started.with_timezone(&Utc)).num_seconds().max(0);
+                vars.set("duration", template::spoken_duration(seconds as u64));
+            }
+
+            let text = parsed.render(&vars);
+            if speak {
+                let (file, cached) = speak_rendered(&config.global_settings, event, &text)?;
+                if json {
+                    return print_json(&serde_json::json!({ "text": text, "path": file, "cached": cached }));
+                }
+                println!("{}", file.display());
+                return Ok(());
+            }
+            if json {
+                return print_json(&serde_json::json!({ "text": text, "variables": vars }));
+            }
+            println!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+/// Write `text` spoken by the configured provider to
+/// ~/.claude/voices/rendered/EVENT, normalized like generated voices. Gives
+/// up after speak_summary.timeout_ms, like spoken summaries.
+fn speak_rendered(settings: &GlobalSettings, event: HookEvent, text: &str) -> Result<(PathBuf, bool), String> {
+    if text.trim().is_empty() {
+        return Err("The voice template rendered to nothing".to_string());
+    }
+    let provider = voice_provider::from_settings(settings, None)?;
+    let voice = voice_provider::configured_voice(settings, provider.as_ref());
+    let cache = VoiceCache::for_settings(settings);
+    let job = VoiceJob::new(get_voice_cache_dir().join("rendered").join(event.key()), text.to_string());
+    speech_summary::block_on_detached(speech_summary::generate_in_time(settings, &job, provider.as_ref(), &cache, voice))?
+}
+
+fn speak_summary(event: &str, message: Option<String>, dry_run: bool, json: bool) -> Result<(), String> {
+    let event = HookEvent::from_key(event).ok_or_else(|| format!("Unknown event {}", event))?;
+    // Messages come on stdin from the hooks: they run to many lines, and
+    // could pass the argument length limit
+    let message = match message {
+        Some(message) => message,
+        None => {
+            let mut message = String::new();
+            io::stdin().read_to_string(&mut message).map_err(|e| format!("Failed to read message: {}", e))?;
+            message
+        }
+    };
+    let cwd = env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))?;
+    let settings = effective_config(&cwd)?.config.global_settings;
+
+    if dry_run {
+        let text = speech_summary::spoken_text(&settings.speak_summary, &message);
+        if json {
+            return print_json(&serde_json::json!({ "text": text, "enabled": settings.speak_summary.enabled_for(event) }));
+        }
+        println!("{}", text);
+        return Ok(());
+    }
+
+    let spoken = speech_summary::block_on_detached(speech_summary::speak(&settings, event, &message))??;
+    if json {
+        return print_json(&spoken);
+    }
+    println!("{}", spoken.path.display());
+    Ok(())
+}
+
+fn tts(command: TtsCommand, json: bool) -> Result<(), String> {
+    let (config, _) = load()?;
+    let mut settings = config.global_settings.system_tts;
+    match command {
+        TtsCommand::Engines => {
+            let engines = system_tts::detect(&settings);
+            let selected = system_tts::select(&settings);
+            if json {
+                return print_json(&serde_json::json!({
+                    "engines": engines,
+                    "selected": selected.as_ref().ok().map(|status| status.engine),
+                }));
+            }
+            for status in &engines {
+                let marker = match &selected {
+                    Ok(selected) if selected.engine == status.engine => "*",
+                    _ => " ",
+                };
+                let detail = match (&status.problem, &status.program) {
+                    (Some(problem), _) => problem.clone(),
+                    (None, Some(program)) => program.display().to_string(),
+                    (None, None) => String::new(),
+                };
+                println!("{} {:<10} {}", marker, variant_name(&status.engine), detail);
+            }
+            if let Err(e) = selected {
+                println!("{}", e);
+            }
+            Ok(())
+        }
+        TtsCommand::Speak { text, output, engine, voice } => {
+            if let Some(engine) = engine {
+                settings.engine = Some(engine.into());
+            }
+            let (bytes, format) = system_tts::synthesize(&settings, &text, voice.as_deref())?;
+            fs::write(&output, bytes).map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+            if json {
+                return print_json(&serde_json::json!({ "output": output, "format": format }));
+            }
+            println!("Wrote {}", output.display());
+            Ok(())
+        }
+    }
+}
+
+fn voice_cache(command: VoiceCacheCommand, json: bool) -> Result<(), String> {
+    let (config, _) = load()?;
+    let cache = VoiceCache::for_settings(&config.global_settings);
+    let print_report = |report: GcReport| {
+        if json {
+            return print_json(&report);
+        }
+        println!(
+            "Removed {} clips and {} stray files, freeing {}",
+            report.removed,
+            report.orphans,
+            megabytes(report.freed_bytes)
+        );
+        Ok(())
+    };
+    match command {
+        VoiceCacheCommand::Stats => {
+            let stats = cache.stats();
+            if json {
+                return print_json(&stats);
+            }
+            println!("{}", stats.dir.display());
+            println!("{} clips, {} of {}", stats.entries, megabytes(stats.bytes), megabytes(stats.max_bytes));
+            for (provider, usage) in &stats.by_provider {
+                println!("  {:<18} {:>5} clips  {}", provider, usage.entries, megabytes(usage.bytes));
+            }
+            Ok(())
+        }
+        VoiceCacheCommand::Gc => print_report(cache.gc()?),
+        VoiceCacheCommand::Clear => print_report(cache.clear()?),
+    }
+}
+
+// ===== Helpers =====
+
+fn megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn home() -> String {
+    get_home_dir().unwrap_or_default()
+}
+
+/// Layered config for `dir` with this shell's overrides, as the hooks see it.
+fn effective_config(dir: &Path) -> Result<EffectiveConfig, String> {
+    let (config, _) = load()?;
+    // A broken profiles file shouldn't silence every hook
+    let profiles = profiles::load(&get_profiles_path(), &home()).map(|(profiles, _)| profiles).unwrap_or_else(|e| {
+        eprintln!("audio-notifier: ignoring profiles: {}", e);
+        profiles::ProfileStore::default()
+    });
+    let config_path = get_config_path();
+    let global_path = config_path.exists().then_some(config_path.as_path());
+
+    layered_config::resolve_with_env(dir, &config, global_path, Path::new(&home()), &env_overrides(), &profiles)
+}
+
+/// Overrides from this shell, warning about values that were ignored.
+fn env_overrides() -> EnvOverrides {
+    let overrides = EnvOverrides::from_env();
+    for name in &overrides.ignored {
+        eprintln!("audio-notifier: ignoring {}, value not recognized", name);
+    }
+    overrides
+}
+
+fn load() -> Result<(Config, String), String> {
+    read_config().map_err(|e| format!("Failed to load config: {}", e))
+}
+
+/// Validate and write, refusing if the file changed since it was loaded.
+/// Only errors in `touched` fields stop the save; problems elsewhere in the
+/// file aren't this command's to fix.
+fn save(config: &Config, hash: &str, touched: &[&str]) -> Result<(), String> {
+    let findings: Vec<_> = validation::validate(config)
+        .into_iter()
+        .filter(|finding| touched.iter().any(|key| validation::touches(&finding.field, key)))
+        .collect();
+    let result = if validation::has_errors(&findings) {
+        Err(ConfigSaveError::Invalid { findings })
+    } else {
+        write_config(config, Some(hash)).map(|_| ())
+    };
+    result.map_err(|e| format!("Not saved: {}", e))
+}
+
+/// Absolute path for existing directories given to exact/prefix rules, so
+/// `project add .` works; globs, regexes and bare names are kept as typed.
+fn normalize_project_path(path: &str, kind: MatchKind) -> String {
+    let literal = matches!(kind, MatchKind::Exact | MatchKind::Prefix);
+    let relative = path == "." || path.starts_with("./") || path.starts_with("../");
+    let expanded = match path.strip_prefix("~/") {
+        Some(rest) => Path::new(&home()).join(rest),
+        None => Path::new(path).to_path_buf(),
+    };
+    if literal && (relative || expanded.is_absolute()) {
+        if let Ok(canonical) = fs::canonicalize(&expanded) {
+            return canonical.to_string_lossy().to_string();
+        }
+    }
+    path.to_string()
+}
+
+/// How an enum value is spelled in the config, e.g. `visual_only`.
+fn variant_name<T: Serialize>(value: &T) -> String {
+    serde_yaml::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn to_doc(config: &Config) -> Result<Value, String> {
+    serde_yaml::to_value(config).map_err(|e| format!("Failed to serialize config: {}", e))
+}
+
+/// Split `projects[0].event_sounds.stop` into keys and indexes.
+fn key_segments(key: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    for part in key.split('.') {
+        let (name, indexes) = part.split_once('[').map_or((part, ""), |(n, rest)| (n, rest));
+        if name.is_empty() {
+            return Err(format!("Invalid key {}", key));
+        }
+        segments.push(Segment::Key(name.to_string()));
+        if !indexes.is_empty() {
+            for index in format!("[{}", indexes).split('[').skip(1) {
+                let index = index
+                    .strip_suffix(']')
+                    .and_then(|i| i.parse().ok())
+                    .ok_or_else(|| format!("Invalid key {}", key))?;
+                segments.push(Segment::Index(index));
+            }
+        }
+    }
+    Ok(segments)
+}
+
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+fn lookup<'a>(doc: &'a Value, key: &str) -> Result<Option<&'a Value>, String> {
+    let mut value = doc;
+    for segment in key_segments(key)? {
+        let next = match segment {
+            Segment::Key(name) => value.get(name.as_str()),
+            Segment::Index(i) => value.get(i),
+        };
+        match next {
+            Some(next) => value = next,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(value))
+}
+
+/// Like lookup, but a missing last key in a mapping is created (as null).
+fn lookup_mut<'a>(doc: &'a mut Value, key: &str) -> Result<&'a mut Value, String> {
+    let segments = key_segments(key)?;
+    let last = segments.len() - 1;
+    let mut value = doc;
+    for (i, segment) in segments.into_iter().enumerate() {
+        let next = match (segment, value) {
+            (Segment::Key(name), Value::Mapping(map)) => {
+                if i == last {
+                    Some(map.entry(Value::from(name)).or_insert(Value::Null))
+                } else {
+                    map.get_mut(name.as_str())
+                }
+            }
+            (Segment::Index(index), Value::Sequence(items)) => items.get_mut(index),
+            _ => None,
+        };
+        value = next.ok_or_else(|| format!("No config key {}", key))?;
+    }
+    Ok(value)
+}
+
+fn print_value(value: &Value, json: bool) -> Result<(), String> {
+    if json {
+        return print_json(value);
+    }
+    match value {
+        Value::String(s) => println!("{}", s),
+        Value::Null => println!("null"),
+        other => {
+            let yaml = serde_yaml::to_string(other).map_err(|e| format!("Failed to format value: {}", e))?;
+            print!("{}", yaml);
+        }
+    }
+    Ok(())
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| format!("Failed to format JSON: {}", e))?;
+    println!("{}", json);
+    Ok(())
+}
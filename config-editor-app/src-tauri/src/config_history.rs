@@ -0,0 +1,199 @@
+// Bounded history of saved configs in ~/.claude/audio-notifier-history/
+//
+//   index.json      HistoryEntry list, oldest first
+//   <id>.yaml       the config as saved
+//
+// write_config records every config it writes, with a summary of the fields
+// that changed since the previous entry. The first recording also keeps the
+// file it replaced, so the state before the first save can be restored too.
+// Only the newest MAX_ENTRIES versions are kept.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::migration::{self, MigrationContext};
+use crate::{atomic_write, get_home_dir, Config};
+
+const MAX_ENTRIES: usize = 50;
+const INDEX_NAME: &str = "index.json";
+/// Number of changed fields named in a summary before "and N more"
+const SUMMARY_FIELDS: usize = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    pub id: String,
+    pub saved_at: String,
+    pub summary: String,
+}
+
+/// One changed field, e.g. `global_settings.event_sounds.stop`. `before` is
+/// None for added fields and `after` is None for removed ones.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+pub struct ConfigHistory {
+    dir: PathBuf,
+}
+
+impl ConfigHistory {
+    pub fn new(dir: PathBuf) -> Self {
+        ConfigHistory { dir }
+    }
+
+    /// Entries, newest first.
+    pub fn list(&self) -> Result<Vec<HistoryEntry>, String> {
+        let mut entries = self.read_index()?;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    pub fn load(&self, id: &str) -> Result<Config, String> {
+        if !self.read_index()?.iter().any(|entry| entry.id == id) {
+            return Err(format!("No config version {}", id));
+        }
+        let path = self.version_path(id);
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let doc: serde_yaml::Value = serde_yaml::from_str(&contents)
+            .map_err(|e| format!("Config version {} is not valid YAML: {}", id, e))?;
+
+        // Versions saved by older builds are brought up to the current schema
+        let ctx = MigrationContext {
+            home: get_home_dir().unwrap_or_default(),
+            ..Default::default()
+        };
+        let (doc, _) = migration::migrate(doc, &ctx)
+            .map_err(|e| format!("Config version {} can't be migrated: {}", id, e))?;
+        serde_yaml::from_value(doc).map_err(|e| format!("Config version {} is invalid: {}", id, e))
+    }
+
+    /// Record `config` as the newest version. `replaced` is the config file it
+    /// overwrote, kept as the first entry when the history is empty. Saves that
+    /// change nothing aren't recorded.
+    pub fn record(&self, config: &Config, replaced: Option<&Config>) -> Result<(), String> {
+        let index_path = self.dir.join(INDEX_NAME);
+        let _lock = atomic_write::lock(&index_path).map_err(|e| format!("Failed to lock config history: {}", e))?;
+        let mut entries = self.read_index()?;
+
+        let previous = match entries.last() {
+            // A damaged version file shouldn't stop new versions being recorded
+            Some(last) => self.load(&last.id).ok(),
+            None => match replaced {
+                Some(replaced) => {
+                    entries.push(self.write_version(replaced, "Before first recorded save".to_string())?);
+                    Some(replaced.clone())
+                }
+                None => None,
+            },
+        };
+
+        let summary = match &previous {
+            Some(previous) => {
+                let changes = diff(previous, config);
+                if changes.is_empty() {
+                    return Ok(());
+                }
+                summarize(&changes)
+            }
+            None if entries.is_empty() => "Initial version".to_string(),
+            None => "Saved".to_string(),
+        };
+        entries.push(self.write_version(config, summary)?);
+
+        while entries.len() > MAX_ENTRIES {
+            let removed = entries.remove(0);
+            let _ = fs::remove_file(self.version_path(&removed.id));
+        }
+
+        let json = serde_json::to_vec_pretty(&entries)
+            .map_err(|e| format!("Failed to serialize config history: {}", e))?;
+        atomic_write::write_atomic(&index_path, &json)
+            .map_err(|e| format!("Failed to write {}: {}", index_path.display(), e))
+    }
+
+    fn write_version(&self, config: &Config, summary: String) -> Result<HistoryEntry, String> {
+        let now = chrono::Utc::now();
+        // Millisecond ids sort by time; bump on the rare collision
+        let mut id = now.format("%Y%m%dT%H%M%S%3f").to_string();
+        while self.version_path(&id).exists() {
+            id.push('0');
+        }
+
+        let yaml = serde_yaml::to_string(config).map_err(|e| format!("Failed to serialize config: {}", e))?;
+        let path = self.version_path(&id);
+        atomic_write::write_atomic(&path, yaml.as_bytes())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+        Ok(HistoryEntry { id, saved_at: now.to_rfc3339(), summary })
+    }
+
+    fn read_index(&self) -> Result<Vec<HistoryEntry>, String> {
+        let path = self.dir.join(INDEX_NAME);
+        match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("{} is not valid JSON: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    fn version_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.yaml", id))
+    }
+}
+
+/// Fields that differ between two configs, using the YAML field names
+/// (`projects[0].match`, not `match_kind`).
+pub fn diff(before: &Config, after: &Config) -> Vec<FieldChange> {
+    let before = serde_json::to_value(before).unwrap_or_default();
+    let after = serde_json::to_value(after).unwrap_or_default();
+    let mut changes = Vec::new();
+    diff_values("", Some(&before), Some(&after), &mut changes);
+    changes
+}
+
+fn diff_values(field: &str, before: Option<&Value>, after: Option<&Value>, changes: &mut Vec<FieldChange>) {
+    match (before, after) {
+        (Some(Value::Object(b)), Some(Value::Object(a))) => {
+            let mut keys: Vec<&String> = b.keys().chain(a.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = if field.is_empty() { key.clone() } else { format!("{}.{}", field, key) };
+                diff_values(&child, b.get(key), a.get(key), changes);
+            }
+        }
+        (Some(Value::Array(b)), Some(Value::Array(a))) => {
+            for i in 0..b.len().max(a.len()) {
+                diff_values(&format!("{}[{}]", field, i), b.get(i), a.get(i), changes);
+            }
+        }
+        (b, a) if b != a => changes.push(FieldChange {
+            field: field.to_string(),
+            before: b.cloned(),
+            after: a.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+/// e.g. "global_settings.voice_template, projects[2] and 4 more". An added or
+/// removed project is a single change, so it's named once.
+fn summarize(changes: &[FieldChange]) -> String {
+    let mut summary = changes
+        .iter()
+        .take(SUMMARY_FIELDS)
+        .map(|change| change.field.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if changes.len() > SUMMARY_FIELDS {
+        summary.push_str(&format!(" and {} more", changes.len() - SUMMARY_FIELDS));
+    }
+    summary
+}
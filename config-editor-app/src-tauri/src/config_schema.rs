@@ -0,0 +1,34 @@
+// JSON Schema for audio-notifier.yaml, generated from the Config types
+//
+// write_config starts the file with a yaml-language-server modeline pointing
+// at audio-notifier.schema.json next to it, and keeps that file current, so
+// editors with the YAML language server (VS Code's YAML extension, Helix,
+// Neovim) autocomplete and validate hand edits.
+
+use std::fs;
+use std::path::Path;
+
+use crate::{atomic_write, Config};
+
+pub const SCHEMA_FILE_NAME: &str = "audio-notifier.schema.json";
+
+/// First line of every config written by the app.
+pub fn modeline() -> String {
+    format!("# yaml-language-server: $schema=./{}\n", SCHEMA_FILE_NAME)
+}
+
+pub fn config_schema() -> serde_json::Value {
+    schemars::schema_for!(Config).to_value()
+}
+
+/// Write the schema into `dir` unless it is already up to date.
+pub fn write_schema_file(dir: &Path) -> Result<(), String> {
+    let path = dir.join(SCHEMA_FILE_NAME);
+    let json = serde_json::to_vec_pretty(&config_schema())
+        .map_err(|e| format!("Failed to serialize config schema: {}", e))?;
+
+    if fs::read(&path).is_ok_and(|existing| existing == json) {
+        return Ok(());
+    }
+    atomic_write::write_atomic(&path, &json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
@@ -0,0 +1,192 @@
+// Per-event cooldowns and priorities
+//
+// Every notification starts a cooldown for its event in its project, so a
+// post_tool_use ding no longer holds back the permission prompt that follows
+// it, and activity in one project doesn't silence another. The cooldown for
+// an event is global_settings.event_cooldowns[event] (a project rule's
+// event_cooldowns win over the global ones), or min_interval when unset.
+//
+// global_settings.event_priority decides how an event is throttled:
+//
+//   high    never throttled (permission prompts by default)
+//   normal  throttled by the cooldown of its own event
+//   low     also held back while any notification from the same project
+//           is more recent than its cooldown
+//
+// Timestamps live in ~/.claude/cooldowns/, one file per project and event
+// (plus one per project for "any event"), holding Unix seconds. smart-notify.sh
+// reads and writes the same files when the audio-notifier CLI isn't installed.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::events::{EventMap, HookEvent};
+use crate::{hash_string, Config};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+impl Priority {
+    /// Only the notification a permission prompt raises is high by
+    /// default; pre_tool_use fires for every tool call, prompted or not.
+    pub fn default_for(event: HookEvent) -> Self {
+        match event {
+            HookEvent::Notification => Priority::High,
+            _ => Priority::Normal,
+        }
+    }
+}
+
+pub fn default_event_priority() -> EventMap<Priority> {
+    EventMap::from_fn(Priority::default_for)
+}
+
+/// Name of the per-project timestamp for notifications of any event.
+const ANY_EVENT: &str = "any";
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct CooldownCheck {
+    pub allowed: bool,
+    pub priority: Priority,
+    /// Seconds
+    pub cooldown: u32,
+    /// Seconds left before the event may notify again, when throttled
+    pub remaining: u64,
+}
+
+pub fn priority(config: &Config, event: HookEvent) -> Priority {
+    config
+        .global_settings
+        .event_priority
+        .get(event)
+        .copied()
+        .unwrap_or_else(|| Priority::default_for(event))
+}
+
+pub fn cooldown(config: &Config, event: HookEvent) -> u32 {
+    config
+        .global_settings
+        .event_cooldowns
+        .get(event)
+        .copied()
+        .unwrap_or(config.min_interval)
+}
+
+/// File-name-safe key for a project directory: the SHA-256 of its path, so
+/// `/a/b` and `/a_b` don't share cooldowns. Must match `cooldown_key` in
+/// smart-notify.sh.
+pub fn project_key(project_dir: &str) -> String {
+    hash_string(project_dir)
+}
+
+fn stamp_path(state_dir: &Path, project_dir: &str, name: &str) -> PathBuf {
+    state_dir.join(format!("{}--{}", project_key(project_dir), name))
+}
+
+fn read_stamp(path: &Path) -> i64 {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Whether `event` from `project_dir` may notify at `now` (Unix seconds).
+pub fn check(config: &Config, event: HookEvent, project_dir: &str, now: i64, state_dir: &Path) -> CooldownCheck {
+    let priority = priority(config, event);
+    let cooldown = cooldown(config, event);
+    let mut check = CooldownCheck { allowed: true, priority, cooldown, remaining: 0 };
+    if priority == Priority::High {
+        return check;
+    }
+
+    let mut last = read_stamp(&stamp_path(state_dir, project_dir, event.key()));
+    if priority == Priority::Low {
+        last = last.max(read_stamp(&stamp_path(state_dir, project_dir, ANY_EVENT)));
+    }
+    let elapsed = now.saturating_sub(last);
+    if elapsed < i64::from(cooldown) {
+        check.allowed = false;
+        check.remaining = (i64::from(cooldown) - elapsed) as u64;
+    }
+    check
+}
+
+/// Start the cooldown for `event` (and for any event) in `project_dir`.
+pub fn record(event: HookEvent, project_dir: &str, now: i64, state_dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(state_dir).map_err(|e| format!("Failed to create cooldown directory: {}", e))?;
+    for name in [event.key(), ANY_EVENT] {
+        fs::write(stamp_path(state_dir, project_dir, name), now.to_string())
+            .map_err(|e| format!("Failed to record notification time: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        let mut config = Config { min_interval: 10, ..Default::default() };
+        config.global_settings.event_cooldowns.set(HookEvent::PostToolUse, 30);
+        config.global_settings.event_priority.set(HookEvent::SubagentStop, Priority::Low);
+        config
+    }
+
+    #[test]
+    fn project_keys_are_distinct_hashes() {
+        assert_eq!(project_key("/a/b").len(), 64);
+        assert_ne!(project_key("/a/b"), project_key("/a_b"));
+        assert_ne!(project_key("/src/café"), project_key("/src/caf__"));
+    }
+
+    #[test]
+    fn cooldowns_are_per_event_and_per_project() {
+        let state = tempfile::tempdir().unwrap();
+        let config = config();
+        record(HookEvent::Stop, "/src/api", 1000, state.path()).unwrap();
+
+        let stop = check(&config, HookEvent::Stop, "/src/api", 1004, state.path());
+        assert_eq!((stop.allowed, stop.cooldown, stop.remaining), (false, 10, 6));
+        assert!(check(&config, HookEvent::Stop, "/src/api", 1010, state.path()).allowed);
+        assert!(check(&config, HookEvent::Stop, "/src/web", 1004, state.path()).allowed);
+        assert!(check(&config, HookEvent::PostToolUse, "/src/api", 1004, state.path()).allowed);
+
+        record(HookEvent::PostToolUse, "/src/api", 1004, state.path()).unwrap();
+        let post = check(&config, HookEvent::PostToolUse, "/src/api", 1020, state.path());
+        assert_eq!((post.allowed, post.cooldown, post.remaining), (false, 30, 14));
+    }
+
+    #[test]
+    fn priority_decides_which_stamps_count() {
+        let state = tempfile::tempdir().unwrap();
+        let config = config();
+        record(HookEvent::Notification, "/src/api", 1000, state.path()).unwrap();
+
+        // High priority is never throttled, even by its own event
+        let prompt = check(&config, HookEvent::Notification, "/src/api", 1001, state.path());
+        assert_eq!((prompt.allowed, prompt.priority), (true, Priority::High));
+
+        // Low priority waits for any recent notification from the project
+        let subagent = check(&config, HookEvent::SubagentStop, "/src/api", 1003, state.path());
+        assert_eq!((subagent.allowed, subagent.remaining), (false, 7));
+        assert!(check(&config, HookEvent::Stop, "/src/api", 1003, state.path()).allowed);
+    }
+
+    #[test]
+    fn pre_tool_use_is_throttled_like_any_event() {
+        let state = tempfile::tempdir().unwrap();
+        let config = config();
+        assert_eq!(priority(&config, HookEvent::PreToolUse), Priority::Normal);
+
+        record(HookEvent::PreToolUse, "/src/api", 1000, state.path()).unwrap();
+        let tool = check(&config, HookEvent::PreToolUse, "/src/api", 1004, state.path());
+        assert_eq!((tool.allowed, tool.priority, tool.remaining), (false, Priority::Normal, 6));
+    }
+}
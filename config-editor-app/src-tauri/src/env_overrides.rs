@@ -0,0 +1,128 @@
+// Per-shell overrides from environment variables
+//
+// Hooks inherit the environment of the shell that started the session, so
+// these change notifier behavior for one terminal without touching the config
+// files, e.g. `AUDIO_NOTIFIER_MUTE=1 claude` while recording a demo.
+//
+//   AUDIO_NOTIFIER_MUTE=1          no sounds or voices (banners still show)
+//   AUDIO_NOTIFIER_PROFILE=<name>  use a saved profile instead of the live settings
+//   AUDIO_NOTIFIER_VOICE=on|off    force generated voices on or off for every event
+//   AUDIO_NOTIFIER_COOLDOWN=<secs> replace min_interval and every event cooldown
+//
+// Booleans accept 1/true/yes/on and 0/false/no/off. Unset or empty variables
+// are ignored, as are values that don't parse. smart-notify.sh reads the same
+// variables and records the active ones in the activity log.
+
+use serde::Serialize;
+use std::env;
+
+use crate::events::HookEvent;
+use crate::profiles::ProfileStore;
+use crate::Config;
+
+pub const MUTE_VAR: &str = "AUDIO_NOTIFIER_MUTE";
+pub const PROFILE_VAR: &str = "AUDIO_NOTIFIER_PROFILE";
+pub const VOICE_VAR: &str = "AUDIO_NOTIFIER_VOICE";
+pub const COOLDOWN_VAR: &str = "AUDIO_NOTIFIER_COOLDOWN";
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct EnvOverrides {
+    pub mute: bool,
+    pub profile: Option<String>,
+    pub voice: Option<bool>,
+    pub cooldown: Option<u32>,
+    /// Variables that were set to something that doesn't parse
+    pub ignored: Vec<String>,
+}
+
+impl EnvOverrides {
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let mut overrides = EnvOverrides::default();
+        let get = |name: &str| var(name).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+        if let Some(raw) = get(MUTE_VAR) {
+            match parse_bool(&raw) {
+                Some(mute) => overrides.mute = mute,
+                None => overrides.ignored.push(MUTE_VAR.to_string()),
+            }
+        }
+        overrides.profile = get(PROFILE_VAR);
+        if let Some(raw) = get(VOICE_VAR) {
+            match parse_bool(&raw) {
+                Some(voice) => overrides.voice = Some(voice),
+                None => overrides.ignored.push(VOICE_VAR.to_string()),
+            }
+        }
+        if let Some(raw) = get(COOLDOWN_VAR) {
+            match raw.parse() {
+                Ok(cooldown) => overrides.cooldown = Some(cooldown),
+                Err(_) => overrides.ignored.push(COOLDOWN_VAR.to_string()),
+            }
+        }
+        overrides
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.active().is_empty()
+    }
+
+    /// Active overrides as they appear in the activity log, e.g. `mute`,
+    /// `profile=demo`, `voice=off`, `cooldown=0`.
+    pub fn active(&self) -> Vec<String> {
+        let mut active = Vec::new();
+        if self.mute {
+            active.push("mute".to_string());
+        }
+        if let Some(profile) = &self.profile {
+            active.push(format!("profile={}", profile));
+        }
+        if let Some(voice) = self.voice {
+            active.push(format!("voice={}", if voice { "on" } else { "off" }));
+        }
+        if let Some(cooldown) = self.cooldown {
+            active.push(format!("cooldown={}", cooldown));
+        }
+        active
+    }
+
+    /// Swap in the profile's settings. Runs before project and repo layering,
+    /// the same as if the profile had been activated.
+    pub fn apply_profile(&self, config: &mut Config, profiles: &ProfileStore) -> Result<(), String> {
+        match &self.profile {
+            Some(name) => profiles
+                .apply(name, config)
+                .map_err(|e| format!("{} is set but can't be used: {}", PROFILE_VAR, e)),
+            None => Ok(()),
+        }
+    }
+
+    /// Apply mute, voice and cooldown on top of the fully layered config.
+    pub fn apply_settings(&self, config: &mut Config) {
+        if self.mute {
+            config.global_settings.enabled = false;
+        }
+        if let Some(voice) = self.voice {
+            for event in HookEvent::ALL {
+                config.global_settings.voice_enabled.set(event, voice);
+            }
+        }
+        if let Some(cooldown) = self.cooldown {
+            config.min_interval = cooldown;
+            for event in HookEvent::ALL {
+                config.global_settings.event_cooldowns.set(event, cooldown);
+            }
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
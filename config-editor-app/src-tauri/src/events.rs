@@ -0,0 +1,251 @@
+// Registry of Claude Code hook events
+//
+// Every place that needs the list of events (config maps, the hooks written
+// to settings.json, voice generation, the activity log) goes through
+// HookEvent::ALL so a new hook only has to be added here and in smart-notify.sh.
+
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    Notification,
+    Stop,
+    PreToolUse,
+    PostToolUse,
+    SubagentStop,
+    UserPromptSubmit,
+    SessionStart,
+    SessionEnd,
+    PreCompact,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct EventInfo {
+    pub event: HookEvent,
+    /// Key used in audio-notifier.yaml and passed to smart-notify.sh
+    pub key: &'static str,
+    /// Hook name in ~/.claude/settings.json
+    pub hook_name: &'static str,
+    /// Human-readable name for the UI and activity log
+    pub display_name: &'static str,
+    /// Name substituted for {event} in voice templates
+    pub spoken_name: &'static str,
+    pub matcher: &'static str,
+    pub default_enabled: bool,
+    /// Fires for permission prompts, which quiet hours can let through
+    pub permission_request: bool,
+}
+
+/// Indexed by `HookEvent as usize`, so the order must match the enum.
+const EVENTS: &[EventInfo] = &[
+    EventInfo {
+        event: HookEvent::Notification,
+        key: "notification",
+        hook_name: "Notification",
+        display_name: "Notification",
+        spoken_name: "notification",
+        matcher: "",
+        default_enabled: true,
+        permission_request: true,
+    },
+    EventInfo {
+        event: HookEvent::Stop,
+        key: "stop",
+        hook_name: "Stop",
+        display_name: "Stop",
+        spoken_name: "stop",
+        matcher: ".*",
+        default_enabled: true,
+        permission_request: false,
+    },
+    EventInfo {
+        event: HookEvent::PreToolUse,
+        key: "pre_tool_use",
+        hook_name: "PreToolUse",
+        display_name: "Pre Tool Use",
+        spoken_name: "pre tool use",
+        matcher: "",
+        default_enabled: false,
+        permission_request: true,
+    },
+    EventInfo {
+        event: HookEvent::PostToolUse,
+        key: "post_tool_use",
+        hook_name: "PostToolUse",
+        display_name: "Post Tool Use",
+        spoken_name: "post tool use",
+        matcher: "",
+        default_enabled: false,
+        permission_request: false,
+    },
+    EventInfo {
+        event: HookEvent::SubagentStop,
+        key: "subagent_stop",
+        hook_name: "SubagentStop",
+        display_name: "Subagent Stop",
+        spoken_name: "subagent stop",
+        matcher: "",
+        default_enabled: true,
+        permission_request: false,
+    },
+    EventInfo {
+        event: HookEvent::UserPromptSubmit,
+        key: "user_prompt_submit",
+        hook_name: "UserPromptSubmit",
+        display_name: "User Prompt Submit",
+        spoken_name: "user prompt submit",
+        matcher: "",
+        default_enabled: false,
+        permission_request: false,
+    },
+    EventInfo {
+        event: HookEvent::SessionStart,
+        key: "session_start",
+        hook_name: "SessionStart",
+        display_name: "Session Start",
+        spoken_name: "session start",
+        matcher: "",
+        default_enabled: false,
+        permission_request: false,
+    },
+    EventInfo {
+        event: HookEvent::SessionEnd,
+        key: "session_end",
+        hook_name: "SessionEnd",
+        display_name: "Session End",
+        spoken_name: "session end",
+        matcher: "",
+        default_enabled: false,
+        permission_request: false,
+    },
+    EventInfo {
+        event: HookEvent::PreCompact,
+        key: "pre_compact",
+        hook_name: "PreCompact",
+        display_name: "Pre Compact",
+        spoken_name: "pre compact",
+        matcher: "",
+        default_enabled: false,
+        permission_request: false,
+    },
+];
+
+impl HookEvent {
+    pub const ALL: [HookEvent; 9] = [
+        HookEvent::Notification,
+        HookEvent::Stop,
+        HookEvent::PreToolUse,
+        HookEvent::PostToolUse,
+        HookEvent::SubagentStop,
+        HookEvent::UserPromptSubmit,
+        HookEvent::SessionStart,
+        HookEvent::SessionEnd,
+        HookEvent::PreCompact,
+    ];
+
+    pub fn info(self) -> &'static EventInfo {
+        &EVENTS[self as usize]
+    }
+
+    pub fn key(self) -> &'static str {
+        self.info().key
+    }
+
+    /// Look up an event by config key or settings.json hook name.
+    /// "permission" is accepted as the legacy name for notification.
+    pub fn from_key(key: &str) -> Option<HookEvent> {
+        if key == "permission" {
+            return Some(HookEvent::Notification);
+        }
+        EVENTS
+            .iter()
+            .find(|info| info.key == key || info.hook_name == key)
+            .map(|info| info.event)
+    }
+}
+
+pub fn all_event_info() -> &'static [EventInfo] {
+    EVENTS
+}
+
+/// Per-event values, serialized as a map keyed by event key, e.g.
+/// `{ notification: ..., stop: ... }`. Unknown keys are ignored when
+/// reading so files written by newer versions still load.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EventMap<T>(BTreeMap<HookEvent, T>);
+
+pub type EventSounds = EventMap<String>;
+pub type EventEnabled = EventMap<bool>;
+
+impl<T> EventMap<T> {
+    /// Map with an entry for every registered event.
+    pub fn from_fn(f: impl Fn(HookEvent) -> T) -> Self {
+        EventMap(HookEvent::ALL.iter().map(|&e| (e, f(e))).collect())
+    }
+
+    pub fn get(&self, event: HookEvent) -> Option<&T> {
+        self.0.get(&event)
+    }
+
+    pub fn set(&mut self, event: HookEvent, value: T) {
+        self.0.insert(event, value);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (HookEvent, &T)> {
+        self.0.iter().map(|(e, v)| (*e, v))
+    }
+}
+
+impl EventMap<bool> {
+    /// Whether `event` is on, falling back to the registry default.
+    pub fn is_enabled(&self, event: HookEvent) -> bool {
+        self.get(event).copied().unwrap_or(event.info().default_enabled)
+    }
+}
+
+impl<T: Serialize> Serialize for EventMap<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for EventMap<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = BTreeMap::<String, T>::deserialize(deserializer)?;
+        Ok(EventMap(
+            raw.into_iter()
+                .filter_map(|(key, value)| HookEvent::from_key(&key).map(|e| (e, value)))
+                .collect(),
+        ))
+    }
+}
+
+/// One optional property per registered event. Unknown keys are rejected here
+/// (unlike when loading) so typos show up in the editor.
+impl<T: JsonSchema> JsonSchema for EventMap<T> {
+    fn schema_name() -> Cow<'static, str> {
+        format!("EventMap_{}", T::schema_name()).into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let properties: serde_json::Map<String, serde_json::Value> = HookEvent::ALL
+            .iter()
+            .map(|event| {
+                let mut schema = generator.subschema_for::<T>();
+                schema.insert("description".to_string(), event.info().display_name.into());
+                (event.key().to_string(), schema.into())
+            })
+            .collect();
+
+        json_schema!({
+            "type": "object",
+            "properties": properties,
+            "additionalProperties": false,
+        })
+    }
+}
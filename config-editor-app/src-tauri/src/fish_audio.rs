@@ -0,0 +1,424 @@
+// Fish Audio API client (voice_provider: fish_audio)
+//
+// Requests share one HTTP client with connect and request timeouts. 429 and
+// 5xx responses, timeouts and dropped connections are retried with
+// exponential backoff (0.5s, 1s, 2s, ...), waiting as long as a Retry-After
+// header asks when there is one. Other failures are reported as FishAudioError
+// variants, and reach the editor as a ProviderError code, so it can tell a
+// bad key from an empty balance.
+//
+// global_settings.fish_audio_base_url points the client at another server,
+// e.g. a local mock in tests.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+use crate::audio_format::AudioFormat;
+use crate::voice_provider::{http_client, CostEstimate, ProviderError, VoiceInfo, VoiceProvider};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.fish.audio";
+/// Voice used when voice_id is unset
+const DEFAULT_VOICE: &str = "af_bella";
+/// US dollars per million UTF-8 bytes
+const PRICE: f64 = 15.0;
+
+pub fn default_base_url() -> String {
+    DEFAULT_BASE_URL.to_string()
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FishAudioError {
+    /// 401/403: missing, wrong or revoked API key
+    Auth { message: String },
+    /// 402: the account has no credit left
+    QuotaExhausted { message: String },
+    /// The reference_id doesn't name a voice model
+    InvalidVoice { voice: String, message: String },
+    /// Still 429 after every retry
+    RateLimited { message: String },
+    /// Still 5xx after every retry
+    Server { status: u16, message: String },
+    /// No response: DNS, connection refused, timeout
+    Network { message: String },
+    /// Any other status, or a response that couldn't be read
+    Unexpected { status: Option<u16>, message: String },
+}
+
+impl fmt::Display for FishAudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FishAudioError::Auth { message } => write!(f, "Fish Audio rejected the API key: {}", message),
+            FishAudioError::QuotaExhausted { message } => write!(f, "Fish Audio credit is used up: {}", message),
+            FishAudioError::InvalidVoice { voice, message } => {
+                write!(f, "Fish Audio has no voice \"{}\": {}", voice, message)
+            }
+            FishAudioError::RateLimited { message } => write!(f, "Fish Audio rate limit still hit after retrying: {}", message),
+            FishAudioError::Server { status, message } => write!(f, "Fish Audio server error {}: {}", status, message),
+            FishAudioError::Network { message } => write!(f, "Failed to reach Fish Audio: {}", message),
+            FishAudioError::Unexpected { status: Some(status), message } => {
+                write!(f, "Fish Audio API error {}: {}", status, message)
+            }
+            FishAudioError::Unexpected { status: None, message } => write!(f, "Fish Audio API error: {}", message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts after the first
+    pub retries: u32,
+    /// Wait before the first retry; doubled for each one after
+    pub base_delay: Duration,
+    /// Longest wait, also for Retry-After
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy { retries: 3, base_delay: Duration::from_millis(500), max_delay: Duration::from_secs(30) }
+    }
+}
+
+impl RetryPolicy {
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.base_delay.saturating_mul(2u32.saturating_pow(attempt)).min(self.max_delay)
+    }
+}
+
+/// Seconds, or an HTTP date, from a Retry-After header.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (at.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().ok()
+}
+
+pub struct FishAudio {
+    api_key: String,
+    base_url: String,
+    retry: RetryPolicy,
+}
+
+impl FishAudio {
+    pub fn new(api_key: String, base_url: &str) -> Self {
+        FishAudio { api_key, base_url: base_url.trim_end_matches('/').to_string(), retry: RetryPolicy::default() }
+    }
+
+    /// Send the request `build` makes, retrying what may succeed later.
+    /// `voice` is named in InvalidVoice errors.
+    async fn send(
+        &self,
+        build: impl Fn() -> reqwest::RequestBuilder,
+        voice: Option<&str>,
+    ) -> Result<reqwest::Response, FishAudioError> {
+        let mut attempt = 0;
+        loop {
+            let outcome = build().bearer_auth(&self.api_key).send().await;
+            let retry_in = match &outcome {
+                Ok(response) if response.status().is_success() => None,
+                Ok(response) if response.status().as_u16() == 429 || response.status().is_server_error() => {
+                    Some(retry_after(response).unwrap_or_else(|| self.retry.backoff(attempt)).min(self.retry.max_delay))
+                }
+                Ok(_) => None,
+                Err(e) if e.is_timeout() || e.is_connect() => Some(self.retry.backoff(attempt)),
+                Err(_) => None,
+            };
+
+            match retry_in {
+                Some(delay) if attempt < self.retry.retries => {
+                    attempt += 1;
+                    tokio::time::sleep(delay).await;
+                }
+                _ => {
+                    return match outcome {
+                        Ok(response) if response.status().is_success() => Ok(response),
+                        Ok(response) => Err(classify(response, voice).await),
+                        Err(e) => Err(FishAudioError::Network { message: e.to_string() }),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Speak `text` with the voice model `voice`.
+    pub async fn tts(&self, text: &str, voice: Option<&str>) -> Result<Vec<u8>, FishAudioError> {
+        let voice = voice.unwrap_or(DEFAULT_VOICE);
+        let url = format!("{}/v1/tts", self.base_url);
+        let body = serde_json::json!({
+            "text": text,
+            "reference_id": voice,
+            "format": "mp3",
+            "latency": "normal"
+        });
+        let response = self.send(|| http_client().post(&url).json(&body), Some(voice)).await?;
+        response
+            .bytes()
+            .await
+            .map(|b| b.to_vec())
+            .map_err(|e| FishAudioError::Network { message: format!("Failed to read response: {}", e) })
+    }
+
+    /// Voice models in the account, including ones saved from the library.
+    pub async fn models(&self) -> Result<Vec<VoiceInfo>, FishAudioError> {
+        let url = format!("{}/model", self.base_url);
+        let response = self
+            .send(|| http_client().get(&url).query(&[("self", "true"), ("page_size", "100")]), None)
+            .await?;
+        let page: FishModelPage = response
+            .json()
+            .await
+            .map_err(|e| FishAudioError::Unexpected { status: None, message: format!("Failed to read voices: {}", e) })?;
+        Ok(page
+            .items
+            .into_iter()
+            .map(|model| VoiceInfo { id: model.id, name: model.title, language: model.languages.into_iter().next() })
+            .collect())
+    }
+}
+
+/// Map a failed response onto an error variant.
+async fn classify(response: reqwest::Response, voice: Option<&str>) -> FishAudioError {
+    let status = response.status().as_u16();
+    let body = response.text().await.unwrap_or_default();
+    let message = body.trim().to_string();
+    // Fish Audio answers an unknown reference_id with 404, or 400 naming it
+    let names_voice = ["reference", "model", "voice"].iter().any(|word| message.to_lowercase().contains(word));
+    match (status, voice) {
+        (401 | 403, _) => FishAudioError::Auth { message },
+        (402, _) => FishAudioError::QuotaExhausted { message },
+        (404, Some(voice)) => FishAudioError::InvalidVoice { voice: voice.to_string(), message },
+        (400 | 422, Some(voice)) if names_voice => FishAudioError::InvalidVoice { voice: voice.to_string(), message },
+        (429, _) => FishAudioError::RateLimited { message },
+        (500..=599, _) => FishAudioError::Server { status, message },
+        _ => FishAudioError::Unexpected { status: Some(status), message },
+    }
+}
+
+#[derive(Deserialize)]
+struct FishModelPage {
+    items: Vec<FishModel>,
+}
+
+#[derive(Deserialize)]
+struct FishModel {
+    #[serde(rename = "_id")]
+    id: String,
+    title: String,
+    #[serde(default)]
+    languages: Vec<String>,
+}
+
+#[async_trait]
+impl VoiceProvider for FishAudio {
+    fn id(&self) -> &'static str {
+        "fish_audio"
+    }
+
+    fn output_format(&self) -> AudioFormat {
+        AudioFormat::Mp3
+    }
+
+    async fn synthesize(&self, text: &str, voice: Option<&str>) -> Result<Vec<u8>, ProviderError> {
+        Ok(self.tts(text, voice).await?)
+    }
+
+    async fn list_voices(&self) -> Result<Vec<VoiceInfo>, ProviderError> {
+        Ok(self.models().await?)
+    }
+
+    fn estimate_cost(&self, texts: &[String]) -> CostEstimate {
+        let bytes: usize = texts.iter().map(|t| t.len()).sum();
+        CostEstimate {
+            characters: texts.iter().map(|t| t.chars().count()).sum(),
+            usd: Some(bytes as f64 * PRICE / 1_000_000.0),
+        }
+    }
+
+    /// A different server may have different voices under the same ids
+    fn cache_options(&self) -> String {
+        if self.base_url == DEFAULT_BASE_URL {
+            String::new()
+        } else {
+            self.base_url.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice_provider::ProviderErrorCode;
+    use wiremock::matchers::{body_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const FAST: RetryPolicy =
+        RetryPolicy { retries: 2, base_delay: Duration::from_millis(1), max_delay: Duration::from_millis(10) };
+
+    fn fish(base_url: &str) -> FishAudio {
+        FishAudio { retry: FAST, ..FishAudio::new("fish-key".to_string(), base_url) }
+    }
+
+    async fn answering(status: u16, body: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/tts"))
+            .respond_with(ResponseTemplate::new(status).set_body_string(body))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn speaks_with_the_key_and_voice() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/tts"))
+            .and(header("authorization", "Bearer fish-key"))
+            .and(body_json(serde_json::json!({
+                "text": "Build finished",
+                "reference_id": "voice-1",
+                "format": "mp3",
+                "latency": "normal"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"ID3 audio".to_vec()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let audio = fish(&server.uri()).tts("Build finished", Some("voice-1")).await.unwrap();
+        assert_eq!(audio, b"ID3 audio");
+    }
+
+    #[tokio::test]
+    async fn failed_responses_map_to_variants() {
+        for status in [401, 403] {
+            let server = answering(status, "invalid token").await;
+            let error = fish(&server.uri()).tts("hi", None).await.unwrap_err();
+            assert_eq!(error, FishAudioError::Auth { message: "invalid token".to_string() });
+        }
+
+        let server = answering(402, "no credit").await;
+        let error = fish(&server.uri()).tts("hi", None).await.unwrap_err();
+        assert!(matches!(error, FishAudioError::QuotaExhausted { .. }), "{:?}", error);
+
+        let server = answering(404, "not found").await;
+        let error = fish(&server.uri()).tts("hi", Some("gone")).await.unwrap_err();
+        assert!(matches!(&error, FishAudioError::InvalidVoice { voice, .. } if voice == "gone"), "{:?}", error);
+
+        let server = answering(400, "Unknown reference_id").await;
+        let error = fish(&server.uri()).tts("hi", None).await.unwrap_err();
+        assert!(matches!(&error, FishAudioError::InvalidVoice { voice, .. } if voice == DEFAULT_VOICE), "{:?}", error);
+
+        let server = answering(400, "text too long").await;
+        let error = fish(&server.uri()).tts("hi", None).await.unwrap_err();
+        assert_eq!(error, FishAudioError::Unexpected { status: Some(400), message: "text too long".to_string() });
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried_until_they_clear() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/tts"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/tts"))
+            .respond_with(ResponseTemplate::new(429))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/tts"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"audio".to_vec()))
+            .mount(&server)
+            .await;
+
+        assert_eq!(fish(&server.uri()).tts("hi", None).await.unwrap(), b"audio");
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn persistent_failures_are_reported_after_the_last_retry() {
+        let server = answering(429, "slow down").await;
+        let error = fish(&server.uri()).tts("hi", None).await.unwrap_err();
+        assert!(matches!(error, FishAudioError::RateLimited { .. }), "{:?}", error);
+        assert_eq!(server.received_requests().await.unwrap().len(), 1 + FAST.retries as usize);
+
+        let server = answering(502, "bad gateway").await;
+        let error = fish(&server.uri()).tts("hi", None).await.unwrap_err();
+        assert_eq!(error, FishAudioError::Server { status: 502, message: "bad gateway".to_string() });
+        assert_eq!(server.received_requests().await.unwrap().len(), 1 + FAST.retries as usize);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let server = answering(401, "invalid token").await;
+        fish(&server.uri()).tts("hi", None).await.unwrap_err();
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn retry_after_replaces_the_backoff() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/tts"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/tts"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"audio".to_vec()))
+            .mount(&server)
+            .await;
+        // Without the header the retry would wait a minute
+        let slow = RetryPolicy { retries: 1, base_delay: Duration::from_secs(60), max_delay: Duration::from_secs(60) };
+        let client = FishAudio { retry: slow, ..fish(&server.uri()) };
+
+        let audio = tokio::time::timeout(Duration::from_secs(5), client.tts("hi", None)).await.unwrap();
+        assert_eq!(audio.unwrap(), b"audio");
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy::default();
+        let delays: Vec<u64> = (0..8).map(|attempt| policy.backoff(attempt).as_millis() as u64).collect();
+        assert_eq!(delays, [500, 1000, 2000, 4000, 8000, 16000, 30000, 30000]);
+        assert_eq!(policy.backoff(u32::MAX), policy.max_delay);
+    }
+
+    #[tokio::test]
+    async fn unreachable_servers_are_network_errors() {
+        // Bind and drop a listener for a port nothing listens on
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let error = fish(&format!("http://127.0.0.1:{}", port)).tts("hi", None).await.unwrap_err();
+        assert!(matches!(error, FishAudioError::Network { .. }), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn lists_models_and_carries_codes_through_the_provider() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/model"))
+            .and(query_param("self", "true"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "items": [{ "_id": "m1", "title": "Narrator", "languages": ["en", "ja"] }]
+            })))
+            .mount(&server)
+            .await;
+        let voices = fish(&server.uri()).list_voices().await.unwrap();
+        assert_eq!(voices, [VoiceInfo { id: "m1".into(), name: "Narrator".into(), language: Some("en".into()) }]);
+
+        let server = answering(402, "no credit").await;
+        let error = fish(&server.uri()).synthesize("hi", None).await.unwrap_err();
+        assert_eq!(error.code, ProviderErrorCode::QuotaExhausted);
+        assert!(error.message.starts_with("Fish Audio credit is used up"), "{}", error);
+    }
+}
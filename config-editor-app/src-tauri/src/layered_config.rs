@@ -0,0 +1,197 @@
+// Layered configuration: repo-local overrides on top of the global config
+//
+// The effective config for a working directory is built from these layers,
+// lowest precedence first:
+//
+//   1. defaults  - Config::default()
+//   2. global    - ~/.claude/audio-notifier.yaml
+//   3. project   - the most specific global `projects` rule matching the directory
+//                  (see project_match), applied onto global_settings
+//   4. repo      - <repo>/.claude/audio-notifier.yaml, committed with the repo
+//   5. environment - AUDIO_NOTIFIER_* variables of the hook's shell (see
+//                  env_overrides); a profile override replaces layer 2
+//
+// The repo file uses the same layout as the global file, but every key is
+// optional. Mappings are merged key by key; scalars and lists replace the
+// value from the layer below.
+
+use serde::Serialize;
+use serde_yaml::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::env_overrides::EnvOverrides;
+use crate::migration::CURRENT_SCHEMA_VERSION;
+use crate::profiles::ProfileStore;
+use crate::project_match;
+use crate::Config;
+
+/// Location of the repo-local config, relative to the repository root.
+pub const REPO_CONFIG_PATH: &str = ".claude/audio-notifier.yaml";
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LayerSource {
+    Defaults,
+    Global,
+    Project,
+    Repo,
+    Environment,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ConfigLayer {
+    pub source: LayerSource,
+    /// File the layer came from (the project path for the project layer)
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EffectiveConfig {
+    pub config: Config,
+    /// Layers that contributed, lowest precedence first
+    pub layers: Vec<ConfigLayer>,
+}
+
+/// Find `<repo>/.claude/audio-notifier.yaml` for `dir`, searching upwards and
+/// stopping at the git root. HOME is never treated as a repo, since its
+/// .claude/audio-notifier.yaml is the global file.
+pub fn find_repo_config(dir: &Path, home: &Path) -> Option<PathBuf> {
+    for ancestor in dir.ancestors() {
+        if ancestor == home {
+            return None;
+        }
+
+        let candidate = ancestor.join(REPO_CONFIG_PATH);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+
+        if ancestor.join(".git").exists() {
+            return None;
+        }
+    }
+    None
+}
+
+/// Recursively merge `overlay` into `base`.
+pub fn merge_values(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Mapping(base_map), Value::Mapping(overlay_map)) => {
+            for (key, value) in overlay_map {
+                match base_map.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn read_repo_overlay(path: &Path) -> Result<Value, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut overlay: Value = serde_yaml::from_str(&contents)
+        .map_err(|e| format!("{} is not valid YAML: {}", path.display(), e))?;
+
+    if overlay.is_null() {
+        return Ok(Value::Mapping(Default::default()));
+    }
+    if !overlay.is_mapping() {
+        return Err(format!("{} must be a YAML mapping", path.display()));
+    }
+
+    if let Some(version) = overlay.get("schema_version").and_then(|v| v.as_u64()) {
+        if version as u32 > CURRENT_SCHEMA_VERSION {
+            return Err(format!(
+                "{} uses schema version {}, newer than this app supports ({})",
+                path.display(),
+                version,
+                CURRENT_SCHEMA_VERSION
+            ));
+        }
+    }
+
+    // Repo files are shared via version control, so secrets are never taken
+    // from them, nor the speech server a key would be sent to
+    if let Some(Value::Mapping(settings)) = overlay.get_mut("global_settings") {
+        for key in ["fish_audio_api_key", "fish_audio_base_url", "openai_api_key", "openai_tts"] {
+            settings.remove(key);
+        }
+    }
+
+    Ok(overlay)
+}
+
+/// Build the effective config for `dir` on top of the already-loaded global config.
+pub fn resolve(
+    dir: &Path,
+    global: &Config,
+    global_path: Option<&Path>,
+    home: &Path,
+) -> Result<EffectiveConfig, String> {
+    let mut layers = vec![ConfigLayer { source: LayerSource::Defaults, path: None }];
+    if let Some(path) = global_path {
+        layers.push(ConfigLayer {
+            source: LayerSource::Global,
+            path: Some(path.to_string_lossy().to_string()),
+        });
+    }
+
+    let mut effective = global.clone();
+    if let Some(project) = project_match::best_match(&global.projects, dir, home) {
+        effective.global_settings.event_sounds = project.event_sounds.clone();
+        effective.global_settings.event_enabled = project.event_enabled.clone();
+        effective.global_settings.voice_enabled = project.voice_enabled.clone();
+        for (event, seconds) in project.event_cooldowns.iter() {
+            effective.global_settings.event_cooldowns.set(event, *seconds);
+        }
+        layers.push(ConfigLayer {
+            source: LayerSource::Project,
+            path: Some(project.path.clone()),
+        });
+    }
+
+    if let Some(repo_path) = find_repo_config(dir, home) {
+        let mut doc = serde_yaml::to_value(&effective)
+            .map_err(|e| format!("Failed to serialize config: {}", e))?;
+        merge_values(&mut doc, read_repo_overlay(&repo_path)?);
+        effective = serde_yaml::from_value(doc)
+            .map_err(|e| format!("{} does not produce a valid config: {}", repo_path.display(), e))?;
+        layers.push(ConfigLayer {
+            source: LayerSource::Repo,
+            path: Some(repo_path.to_string_lossy().to_string()),
+        });
+    }
+
+    // Overlays may carry their own version number; the result is always current
+    effective.schema_version = CURRENT_SCHEMA_VERSION;
+
+    Ok(EffectiveConfig { config: effective, layers })
+}
+
+/// Like `resolve`, with a shell's environment overrides on top.
+pub fn resolve_with_env(
+    dir: &Path,
+    global: &Config,
+    global_path: Option<&Path>,
+    home: &Path,
+    overrides: &EnvOverrides,
+    profiles: &ProfileStore,
+) -> Result<EffectiveConfig, String> {
+    let mut global = global.clone();
+    overrides.apply_profile(&mut global, profiles)?;
+
+    let mut effective = resolve(dir, &global, global_path, home)?;
+    if !overrides.is_empty() {
+        overrides.apply_settings(&mut effective.config);
+        effective.layers.push(ConfigLayer {
+            source: LayerSource::Environment,
+            path: None,
+        });
+    }
+    Ok(effective)
+}
@@ -0,0 +1,597 @@
+// Config model and file handling for Claude Code Audio Notifier
+//
+// Shared by the config editor (src/main.rs) and the headless `audio-notifier`
+// CLI (src/bin/audio-notifier.rs): the Config types, where files live under
+// ~/.claude, and reading, migrating and atomically writing audio-notifier.yaml.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use chrono::Utc;
+
+pub mod atomic_write;
+pub mod audio_format;
+pub mod config_history;
+pub mod config_schema;
+pub mod cooldown;
+pub mod env_overrides;
+pub mod events;
+pub mod fish_audio;
+pub mod layered_config;
+pub mod loudness;
+pub mod migration;
+pub mod profile_archive;
+pub mod profiles;
+pub mod project_match;
+pub mod schedule;
+pub mod secrets;
+pub mod speech_summary;
+pub mod system_tts;
+pub mod template;
+pub mod validation;
+pub mod voice_cache;
+pub mod voice_jobs;
+pub mod voice_provider;
+
+use atomic_write::WriteError;
+use config_history::ConfigHistory;
+use cooldown::Priority;
+use events::{EventEnabled, EventMap, EventSounds};
+use loudness::LoudnessSettings;
+use speech_summary::SpeakSummarySettings;
+use migration::{MigrationContext, MigrationError, CURRENT_SCHEMA_VERSION};
+use project_match::MatchKind;
+use schedule::ScheduleRule;
+use secrets::SecretRef;
+use system_tts::SystemTtsSettings;
+use validation::{Severity, ValidationFinding};
+use voice_provider::OpenAiTtsSettings;
+
+// ===== Config Structures =====
+
+/// Sound and voice overrides for the projects matched by `path`
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ProjectConfig {
+    /// Directory, glob or regex, depending on `match`
+    pub path: String,
+    #[serde(default, rename = "match")]
+    pub match_kind: MatchKind,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    pub enabled: bool,
+    /// Sound file path, or voice:simple / voice:project, per event
+    pub event_sounds: EventSounds,
+    #[serde(default = "default_event_enabled")]
+    pub event_enabled: EventEnabled,
+    /// Events announced with a generated voice instead of a sound file
+    #[serde(default = "default_event_voice_enabled")]
+    pub voice_enabled: EventEnabled,
+    /// Seconds, replacing the global event_cooldowns for these events
+    #[serde(default)]
+    pub event_cooldowns: EventMap<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct GlobalSettings {
+    pub enabled: bool,
+    /// Sound file path, or voice:simple / voice:project, per event
+    pub event_sounds: EventSounds,
+    #[serde(default = "default_event_enabled")]
+    pub event_enabled: EventEnabled,
+    /// Events announced with a generated voice instead of a sound file
+    #[serde(default = "default_event_voice_enabled")]
+    pub voice_enabled: EventEnabled,
+    /// Text spoken by generated voices, with {event}, {branch}, {summary} and
+    /// other variables; see template.rs
+    #[serde(default = "default_voice_template")]
+    pub voice_template: String,
+    #[serde(default = "default_voice_provider")]
+    #[schemars(extend("enum" = ["fish_audio", "fish-audio", "system", "openai_compatible"]))]
+    pub voice_provider: String,
+    /// Voice for the provider: a Fish Audio reference_id, an OpenAI voice
+    /// such as "alloy", or a `say -v` voice name
+    #[serde(default)]
+    pub voice_id: Option<String>,
+    /// Where the key is stored; the key itself never appears in the config
+    #[serde(default)]
+    pub fish_audio_api_key: Option<SecretRef>,
+    /// Fish Audio API server, e.g. a local mock for testing
+    #[serde(default = "fish_audio::default_base_url")]
+    pub fish_audio_base_url: String,
+    /// Server and model for the openai_compatible provider
+    #[serde(default)]
+    pub openai_tts: OpenAiTtsSettings,
+    /// Key for the openai_compatible provider; local servers usually need none
+    #[serde(default)]
+    pub openai_api_key: Option<SecretRef>,
+    /// Engine and voice for the system provider; see system_tts.rs
+    #[serde(default)]
+    pub system_tts: SystemTtsSettings,
+    /// Megabytes of generated speech kept in ~/.claude/voice-cache; the least
+    /// recently used clips are deleted first
+    #[serde(default = "voice_cache::default_max_mb")]
+    pub voice_cache_max_mb: u32,
+    /// Target level for generated voices and uploaded sounds; see loudness.rs
+    #[serde(default)]
+    pub loudness: LoudnessSettings,
+    /// Speak the message itself for stop and notification; see speech_summary.rs
+    #[serde(default)]
+    pub speak_summary: SpeakSummarySettings,
+    #[serde(default)]
+    pub respect_do_not_disturb: bool,
+    /// Quiet hours; see schedule.rs
+    #[serde(default)]
+    pub schedules: Vec<ScheduleRule>,
+    /// Seconds between notifications of one event in one project; min_interval when unset
+    #[serde(default)]
+    pub event_cooldowns: EventMap<u32>,
+    /// high skips cooldowns, low also waits for other events; see cooldown.rs
+    #[serde(default = "cooldown::default_event_priority")]
+    pub event_priority: EventMap<Priority>,
+}
+
+impl GlobalSettings {
+    /// Drop the API key references, for settings that are shared with others
+    /// or applied over another config (profiles, archives).
+    pub fn clear_api_keys(&mut self) {
+        self.fish_audio_api_key = None;
+        self.openai_api_key = None;
+    }
+
+    /// Keep the API key references of `current` instead of our own.
+    pub fn keep_api_keys(&mut self, current: &GlobalSettings) {
+        self.fish_audio_api_key = current.fish_audio_api_key.clone();
+        self.openai_api_key = current.openai_api_key.clone();
+    }
+}
+
+pub fn default_event_sounds() -> EventSounds {
+    EventSounds::from_fn(|_| "voice:simple".to_string())
+}
+
+pub fn default_event_enabled() -> EventEnabled {
+    EventEnabled::from_fn(|event| event.info().default_enabled)
+}
+
+pub fn default_event_voice_enabled() -> EventEnabled {
+    EventEnabled::from_fn(|event| event.info().default_enabled)
+}
+
+pub fn default_voice_template() -> String {
+    "{event} event".to_string()
+}
+
+pub fn default_voice_provider() -> String {
+    "fish_audio".to_string()
+}
+
+pub fn default_schema_version() -> u32 {
+    CURRENT_SCHEMA_VERSION
+}
+
+/// ~/.claude/audio-notifier.yaml
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct Config {
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
+    pub global_mode: bool,
+    pub global_settings: GlobalSettings,
+    pub projects: Vec<ProjectConfig>,
+    pub sound_library: Vec<String>,
+    /// Default cooldown in seconds for events without an event_cooldowns entry
+    pub min_interval: u32,
+    pub debug: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let system_sounds = vec![
+            "/System/Library/Sounds/Ping.aiff".to_string(),
+            "/System/Library/Sounds/Glass.aiff".to_string(),
+            "/System/Library/Sounds/Hero.aiff".to_string(),
+            "/System/Library/Sounds/Submarine.aiff".to_string(),
+            "/System/Library/Sounds/Tink.aiff".to_string(),
+            "/System/Library/Sounds/Pop.aiff".to_string(),
+            "/System/Library/Sounds/Funk.aiff".to_string(),
+            "/System/Library/Sounds/Purr.aiff".to_string(),
+            "/System/Library/Sounds/Blow.aiff".to_string(),
+            "/System/Library/Sounds/Bottle.aiff".to_string(),
+            "/System/Library/Sounds/Frog.aiff".to_string(),
+            "/System/Library/Sounds/Basso.aiff".to_string(),
+        ];
+
+        Config {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            global_mode: true,
+            global_settings: GlobalSettings {
+                enabled: true,
+                event_sounds: default_event_sounds(),
+                event_enabled: default_event_enabled(),
+                voice_enabled: default_event_voice_enabled(),
+                voice_template: default_voice_template(),
+                voice_provider: "fish_audio".to_string(),
+                voice_id: None,
+                fish_audio_api_key: None,
+                fish_audio_base_url: fish_audio::default_base_url(),
+                openai_tts: OpenAiTtsSettings::default(),
+                openai_api_key: None,
+                system_tts: SystemTtsSettings::default(),
+                voice_cache_max_mb: voice_cache::default_max_mb(),
+                loudness: LoudnessSettings::default(),
+                speak_summary: SpeakSummarySettings::default(),
+                respect_do_not_disturb: false,
+                schedules: vec![],
+                event_cooldowns: EventMap::default(),
+                event_priority: cooldown::default_event_priority(),
+            },
+            projects: vec![],
+            sound_library: system_sounds,
+            min_interval: 2,
+            debug: false,
+        }
+    }
+}
+
+// ===== Paths =====
+
+pub fn get_home_dir() -> Result<String, String> {
+    std::env::var("HOME").map_err(|_| "Could not determine HOME directory".to_string())
+}
+
+/// `path` with a leading `~/` replaced by the home directory, as the hook
+/// scripts do.
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => Path::new(&get_home_dir().unwrap_or_default()).join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+pub fn get_config_path() -> PathBuf {
+    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(home).join(".claude/audio-notifier.yaml")
+}
+
+pub fn get_sounds_enabled_path() -> PathBuf {
+    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(home).join(".claude/.sounds-enabled")
+}
+
+pub fn get_project_sounds_conf_path() -> PathBuf {
+    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(home).join(".claude/project-sounds.conf")
+}
+
+pub fn get_profiles_path() -> PathBuf {
+    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(home).join(".claude/audio-notifier-profiles.yaml")
+}
+
+pub fn get_history_dir() -> PathBuf {
+    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(home).join(".claude/audio-notifier-history")
+}
+
+pub fn get_credentials_path() -> PathBuf {
+    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(home).join(".claude/audio-notifier-credentials.json")
+}
+
+pub fn get_cooldown_dir() -> PathBuf {
+    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(home).join(".claude/cooldowns")
+}
+
+pub fn get_custom_sounds_dir() -> PathBuf {
+    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(home).join(".claude/sounds")
+}
+
+pub fn get_voice_cache_dir() -> PathBuf {
+    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(home).join(".claude/voices")
+}
+
+pub fn get_speech_cache_dir() -> PathBuf {
+    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(home).join(".claude/voice-cache")
+}
+
+pub fn get_voice_queue_path() -> PathBuf {
+    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(home).join(".claude/voice-generation-queue.json")
+}
+
+pub fn hash_string(s: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(s.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+pub fn get_backup_dir() -> PathBuf {
+    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
+    PathBuf::from(home).join(".claude/backups")
+}
+
+// ===== Loading and Saving =====
+
+/// Error returned to the UI when the config file can't be loaded or migrated.
+/// The original file is always backed up and left untouched.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConfigLoadError {
+    Io { message: String },
+    UnsupportedVersion { found: u64, supported: u32, backup_path: String },
+    MigrationFailed { from_version: u32, message: String, backup_path: String },
+    Invalid { message: String, backup_path: String },
+}
+
+impl std::fmt::Display for ConfigLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigLoadError::Io { message } => write!(f, "{}", message),
+            ConfigLoadError::UnsupportedVersion { found, supported, backup_path } => write!(
+                f,
+                "config schema version {} is newer than this app supports ({}); backed up to {}",
+                found, supported, backup_path
+            ),
+            ConfigLoadError::MigrationFailed { from_version, message, backup_path } => write!(
+                f,
+                "migrating config from schema version {} failed: {}; backed up to {}",
+                from_version, message, backup_path
+            ),
+            ConfigLoadError::Invalid { message, backup_path } => {
+                write!(f, "{}; backed up to {}", message, backup_path)
+            }
+        }
+    }
+}
+
+pub fn backup_config_file(config_path: &PathBuf) -> Result<String, ConfigLoadError> {
+    let contents = fs::read(config_path).map_err(|e| ConfigLoadError::Io {
+        message: format!("Failed to back up config file: {}", e),
+    })?;
+    backup_config_contents(&get_backup_dir(), &contents)
+}
+
+fn backup_config_contents(backup_dir: &Path, contents: &[u8]) -> Result<String, ConfigLoadError> {
+    fs::create_dir_all(backup_dir).map_err(|e| ConfigLoadError::Io {
+        message: format!("Failed to create backup directory: {}", e),
+    })?;
+
+    // Two backups in the same second get a counter instead of overwriting each other
+    let timestamp = Utc::now().timestamp();
+    let mut counter = 0;
+    let (backup_path, mut file) = loop {
+        let name = match counter {
+            0 => format!("audio-notifier-{}.yaml", timestamp),
+            n => format!("audio-notifier-{}-{}.yaml", timestamp, n),
+        };
+        let path = backup_dir.join(name);
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        // The original may still hold a plaintext API key
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        match options.open(&path) {
+            Ok(file) => break (path, file),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => counter += 1,
+            Err(e) => {
+                return Err(ConfigLoadError::Io { message: format!("Failed to back up config file: {}", e) });
+            }
+        }
+    };
+
+    file.write_all(contents).map_err(|e| ConfigLoadError::Io {
+        message: format!("Failed to back up config file: {}", e),
+    })?;
+
+    Ok(backup_path.to_string_lossy().to_string())
+}
+
+/// Config file text with any plaintext API key replaced. Files that don't
+/// parse are left out, since a key could be anywhere in them.
+pub fn redacted_config(contents: &str) -> String {
+    match serde_yaml::from_str::<serde_yaml::Value>(contents) {
+        Ok(mut doc) => {
+            secrets::redact_plaintext_keys(&mut doc);
+            serde_yaml::to_string(&doc).unwrap_or_default()
+        }
+        Err(e) => format!("# config omitted: not valid YAML ({})", e),
+    }
+}
+
+/// Error returned to the UI when a config save is refused.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConfigSaveError {
+    Io { message: String },
+    Invalid { findings: Vec<ValidationFinding> },
+    /// The file was edited outside the editor since it was last loaded
+    Conflict { message: String },
+}
+
+impl std::fmt::Display for ConfigSaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSaveError::Io { message } | ConfigSaveError::Conflict { message } => write!(f, "{}", message),
+            ConfigSaveError::Invalid { findings } => {
+                let errors: Vec<String> = findings
+                    .iter()
+                    .filter(|finding| finding.severity == Severity::Error)
+                    .map(|finding| format!("{}: {}", finding.field, finding.message))
+                    .collect();
+                write!(f, "config is invalid: {}", errors.join("; "))
+            }
+        }
+    }
+}
+
+/// Read, migrate and parse the config file. Returns the config together with
+/// the content hash of the file on disk.
+pub fn read_config() -> Result<(Config, String), ConfigLoadError> {
+    let config_path = get_config_path();
+    let conf_path = get_project_sounds_conf_path();
+
+    let ctx = MigrationContext {
+        project_sounds_conf: fs::read_to_string(&conf_path).ok(),
+        home: get_home_dir().unwrap_or_default(),
+        ..Default::default()
+    };
+
+    if !config_path.exists() {
+        let missing_hash = atomic_write::content_hash(b"");
+        // Nothing to migrate unless the pre-YAML project-sounds.conf is still around
+        if ctx.project_sounds_conf.is_none() {
+            return Ok((Config::default(), missing_hash));
+        }
+        let (doc, _) = migration::migrate(serde_yaml::Value::Null, &ctx).map_err(|e| {
+            ConfigLoadError::Io { message: format!("Failed to migrate project-sounds.conf: {}", e) }
+        })?;
+        let config: Config = serde_yaml::from_value(doc).map_err(|e| ConfigLoadError::Io {
+            message: format!("Failed to migrate project-sounds.conf: {}", e),
+        })?;
+        let hash = write_config(&config, Some(&missing_hash)).map_err(save_error_to_load_error)?;
+        return Ok((config, hash));
+    }
+
+    let contents = fs::read_to_string(&config_path).map_err(|e| ConfigLoadError::Io {
+        message: format!("Failed to read config file: {}", e),
+    })?;
+    let hash = atomic_write::content_hash(contents.as_bytes());
+
+    let doc: serde_yaml::Value = match serde_yaml::from_str(&contents) {
+        Ok(doc) => doc,
+        Err(e) => {
+            return Err(ConfigLoadError::Invalid {
+                message: format!("Config file is not valid YAML: {}", e),
+                backup_path: backup_config_file(&config_path)?,
+            });
+        }
+    };
+
+    let (doc, from_version) = match migration::migrate(doc, &ctx) {
+        Ok(migrated) => migrated,
+        Err(MigrationError::Unrecognized) => {
+            return Err(ConfigLoadError::Invalid {
+                message: "Config file does not match any known layout".to_string(),
+                backup_path: backup_config_file(&config_path)?,
+            });
+        }
+        Err(MigrationError::UnsupportedVersion(found)) => {
+            return Err(ConfigLoadError::UnsupportedVersion {
+                found,
+                supported: CURRENT_SCHEMA_VERSION,
+                backup_path: backup_config_file(&config_path)?,
+            });
+        }
+        Err(MigrationError::StepFailed { from_version, message }) => {
+            return Err(ConfigLoadError::MigrationFailed {
+                from_version,
+                message,
+                backup_path: backup_config_file(&config_path)?,
+            });
+        }
+    };
+
+    let config: Config = match serde_yaml::from_value(doc) {
+        Ok(config) => config,
+        Err(e) => {
+            return Err(ConfigLoadError::Invalid {
+                message: format!("Failed to parse config: {}", e),
+                backup_path: backup_config_file(&config_path)?,
+            });
+        }
+    };
+
+    // Only rewrite the file when a migration actually ran, keeping the original as a backup
+    if from_version < CURRENT_SCHEMA_VERSION {
+        // The key must be in the secret store before the plaintext copy goes away
+        for secret in ctx.moved_secrets.take() {
+            SecretRef::default_for(&secret.name).store(&secret.value).map_err(|e| ConfigLoadError::Io {
+                message: format!("Failed to move {} to the secret store: {}", secret.name, e),
+            })?;
+        }
+        backup_config_contents(&get_backup_dir(), contents.as_bytes())?;
+        let new_hash = write_config(&config, Some(&hash)).map_err(save_error_to_load_error)?;
+        return Ok((config, new_hash));
+    }
+
+    Ok((config, hash))
+}
+
+/// Serialize and atomically write the config. When `expected_hash` is given the
+/// write is refused if the file changed on disk since that hash was taken.
+/// Returns the hash of the written file.
+pub fn write_config(config: &Config, expected_hash: Option<&str>) -> Result<String, ConfigSaveError> {
+    let config_path = get_config_path();
+
+    let yaml = serde_yaml::to_string(config).map_err(|e| ConfigSaveError::Io {
+        message: format!("Failed to serialize config: {}", e),
+    })?;
+    let yaml = format!("{}{}", config_schema::modeline(), yaml);
+
+    // Editors can still open the config without the schema, so don't fail the save
+    if let Some(dir) = config_path.parent() {
+        if let Err(e) = config_schema::write_schema_file(dir) {
+            println!("{}", e);
+        }
+    }
+
+    // Only needed to seed an empty history, so anything unparseable is skipped
+    let replaced: Option<Config> = fs::read_to_string(&config_path)
+        .ok()
+        .and_then(|contents| serde_yaml::from_str(&contents).ok());
+
+    let hash = atomic_write::write_if_unchanged(&config_path, expected_hash, yaml.as_bytes()).map_err(|e| match e {
+        WriteError::Conflict => ConfigSaveError::Conflict {
+            message: format!("{} changed on disk since it was loaded", config_path.display()),
+        },
+        WriteError::Io(e) => ConfigSaveError::Io {
+            message: format!("Failed to write config file: {}", e),
+        },
+    })?;
+
+    // The config is already saved; a history failure shouldn't report the save as failed
+    if let Err(e) = ConfigHistory::new(get_history_dir()).record(config, replaced.as_ref()) {
+        println!("Failed to record config history: {}", e);
+    }
+
+    Ok(hash)
+}
+
+fn save_error_to_load_error(e: ConfigSaveError) -> ConfigLoadError {
+    let message = match e {
+        ConfigSaveError::Io { message } | ConfigSaveError::Conflict { message } => message,
+        ConfigSaveError::Invalid { .. } => "Migrated config failed validation".to_string(),
+    };
+    ConfigLoadError::Io { message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backups_in_the_same_second_are_all_kept() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let first = backup_config_contents(dir.path(), b"first").unwrap();
+        let second = backup_config_contents(dir.path(), b"second").unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(fs::read(&first).unwrap(), b"first");
+        assert_eq!(fs::read(&second).unwrap(), b"second");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&second).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+    }
+}
@@ -0,0 +1,747 @@
+// Loudness normalization (global_settings.loudness)
+//
+// Integrated loudness is measured as in ITU-R BS.1770: K-weighting, 400 ms
+// blocks overlapping by 75%, an absolute gate at -70 LUFS and a relative gate
+// 10 LU below the ungated level. A clip is then scaled to target_lufs, with
+// the gain capped so its peak stays at -1 dBFS, and written as 16-bit WAV
+// since there is no pure-Rust MP3 encoder.
+//
+// The file being replaced is moved to originals/ next to it. The
+// originals/normalized.json record lets a later run skip clips already at the
+// target, and start from the original rather than the processed copy when
+// the target changes. `restore` undoes a normalization, for when the config
+// can't be pointed at the new file.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CodecParameters, Decoder, DecoderOptions};
+use symphonia::core::errors::Error as DecodeError;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::atomic_write;
+use crate::audio_format;
+use crate::events::EventSounds;
+use crate::{expand_home, Config};
+
+pub const ORIGINALS_DIR: &str = "originals";
+const RECORD_FILE: &str = "normalized.json";
+/// Clips this close to the target are left alone
+const TOLERANCE_DB: f64 = 0.5;
+const PEAK_CEILING_DBFS: f64 = -1.0;
+/// Range accepted for target_lufs
+pub const MIN_TARGET_LUFS: f64 = -40.0;
+pub const MAX_TARGET_LUFS: f64 = -5.0;
+
+fn default_target_lufs() -> f64 {
+    -16.0
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+pub struct LoudnessSettings {
+    /// Integrated loudness to aim for, in LUFS
+    #[serde(default = "default_target_lufs")]
+    pub target_lufs: f64,
+    /// Normalize newly generated voice notifications
+    #[serde(default = "default_true")]
+    pub normalize_voices: bool,
+    /// Normalize sounds added through upload_sound
+    #[serde(default)]
+    pub normalize_uploads: bool,
+}
+
+impl Default for LoudnessSettings {
+    fn default() -> Self {
+        LoudnessSettings { target_lufs: default_target_lufs(), normalize_voices: true, normalize_uploads: false }
+    }
+}
+
+/// Decoded audio, interleaved.
+pub struct Pcm {
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub samples: Vec<f32>,
+}
+
+impl Pcm {
+    fn peak(&self) -> f32 {
+        self.samples.iter().fold(0.0, |peak, s| peak.max(s.abs()))
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Normalized {
+    /// Where the clip is now; a .wav when it was changed
+    pub path: PathBuf,
+    /// Where the file it replaced went; None when nothing changed
+    pub original: Option<PathBuf>,
+    /// Loudness before, or None for silence
+    pub loudness_lufs: Option<f64>,
+    pub gain_db: f64,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct NormalizeFailure {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct LibraryReport {
+    pub normalized: Vec<Normalized>,
+    /// Already at the target, or silent
+    pub unchanged: usize,
+    pub failed: Vec<NormalizeFailure>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Record {
+    /// File name in originals/
+    original: String,
+    /// content_hash of the normalized file, to notice it being replaced
+    hash: String,
+    target_lufs: f64,
+}
+
+/// Normalized files in a directory, by file name.
+type Records = BTreeMap<String, Record>;
+
+fn read_records(originals: &Path) -> Records {
+    fs::read_to_string(originals.join(RECORD_FILE))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn write_records(originals: &Path, records: &Records) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(records).map_err(|e| format!("Failed to serialize loudness records: {}", e))?;
+    atomic_write::write_atomic(&originals.join(RECORD_FILE), &json)
+        .map_err(|e| format!("Failed to write loudness records: {}", e))
+}
+
+/// Fail on a corrupt header or a file cut short, without decoding the whole
+/// file: the header is read, the first packet decoded and the last packet
+/// the header promises read.
+pub fn probe(path: &Path) -> Result<(), String> {
+    let Track { mut reader, mut decoder, id: track_id, params } = open(path)?;
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(DecodeError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Err(format!("{} has no decodable audio", path.display()));
+            }
+            Err(e) => return Err(format!("Failed to decode {}: {}", path.display(), e)),
+        };
+        if packet.track_id() == track_id {
+            decoder.decode(&packet).map_err(|e| format!("Failed to decode {}: {}", path.display(), e))?;
+            break;
+        }
+    }
+
+    // The last packet the header promises must be there to read
+    if let Some(frames) = params.n_frames {
+        // MP3 doesn't say how long a packet is; 4096 frames covers its 1152
+        let last = frames.saturating_sub(params.max_frames_per_packet.unwrap_or(4096).max(1));
+        let truncated = || format!("{} is truncated: it ends before frame {}", path.display(), frames);
+        reader
+            .seek(SeekMode::Coarse, SeekTo::TimeStamp { ts: last, track_id })
+            .map_err(|_| truncated())?;
+        loop {
+            match reader.next_packet() {
+                Ok(packet) if packet.track_id() == track_id => break,
+                Ok(_) => continue,
+                Err(_) => return Err(truncated()),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The default track of an audio file, ready to read and decode.
+struct Track {
+    reader: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    id: u32,
+    /// What the header declares, such as the frame count
+    params: CodecParameters,
+}
+
+fn open(path: &Path) -> Result<Track, String> {
+    let file = fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hint = Hint::new();
+    if let Some(format) = audio_format::sniff_file(path) {
+        hint.with_extension(format.extension());
+    }
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let reader = probed.format;
+    let track = reader.default_track().ok_or_else(|| format!("{} has no audio track", path.display()))?;
+    let decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| format!("Failed to decode {}: {}", path.display(), e))?;
+    let (id, params) = (track.id, track.codec_params.clone());
+    Ok(Track { reader, decoder, id, params })
+}
+
+pub fn decode(path: &Path) -> Result<Pcm, String> {
+    let Track { mut reader, mut decoder, id: track_id, params } = open(path)?;
+
+    let mut pcm = Pcm {
+        sample_rate: params.sample_rate.unwrap_or(0),
+        channels: params.channels.map(|c| c.count()).unwrap_or(0),
+        samples: Vec::new(),
+    };
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(DecodeError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(DecodeError::ResetRequired) => break,
+            Err(e) => return Err(format!("Failed to decode {}: {}", path.display(), e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        match decoder.decode(&packet) {
+            Ok(buffer) => {
+                let spec = *buffer.spec();
+                pcm.sample_rate = spec.rate;
+                pcm.channels = spec.channels.count();
+                let mut samples = SampleBuffer::<f32>::new(buffer.capacity() as u64, spec);
+                samples.copy_interleaved_ref(buffer);
+                pcm.samples.extend_from_slice(samples.samples());
+            }
+            // A corrupt frame is skipped, as players do
+            Err(DecodeError::DecodeError(_)) => continue,
+            Err(e) => return Err(format!("Failed to decode {}: {}", path.display(), e)),
+        }
+    }
+
+    if pcm.sample_rate == 0 || pcm.channels == 0 {
+        return Err(format!("{} has no decodable audio", path.display()));
+    }
+    Ok(pcm)
+}
+
+/// Second-order IIR filter, direct form I.
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x0: f64) -> f64 {
+        let y0 = self.b[0] * x0 + self.b[1] * self.x[0] + self.b[2] * self.x[1] - self.a[1] * self.y[0] - self.a[2] * self.y[1];
+        self.x = [x0, self.x[0]];
+        self.y = [y0, self.y[0]];
+        y0
+    }
+}
+
+/// The BS.1770 pre-filter (high shelf) and RLB high-pass, for any sample rate.
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        x: [0.0; 2],
+        y: [0.0; 2],
+    };
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        x: [0.0; 2],
+        y: [0.0; 2],
+    };
+    [shelf, high_pass]
+}
+
+fn block_loudness(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+/// Integrated loudness in LUFS, or None when everything is below the
+/// absolute gate (silence).
+pub fn integrated_loudness(pcm: &Pcm) -> Option<f64> {
+    let channels = pcm.channels;
+    let frames = pcm.samples.len() / channels;
+    if frames == 0 {
+        return None;
+    }
+
+    // Squared K-weighted samples per channel
+    let mut squared = vec![vec![0.0f64; frames]; channels];
+    for (channel, out) in squared.iter_mut().enumerate() {
+        let [mut shelf, mut high_pass] = k_weighting(f64::from(pcm.sample_rate));
+        for (frame, value) in out.iter_mut().enumerate() {
+            let x = f64::from(pcm.samples[frame * channels + channel]);
+            let y = high_pass.process(shelf.process(x));
+            *value = y * y;
+        }
+    }
+    // Surround channels (4th and 5th) count for more, as in BS.1770
+    let weight = |channel: usize| if channel >= 3 { 1.41 } else { 1.0 };
+
+    let block = ((f64::from(pcm.sample_rate) * 0.4) as usize).min(frames).max(1);
+    let step = ((f64::from(pcm.sample_rate) * 0.1) as usize).max(1);
+    let mut powers = Vec::new();
+    let mut start = 0;
+    while start + block <= frames {
+        let power: f64 = squared
+            .iter()
+            .enumerate()
+            .map(|(channel, values)| weight(channel) * values[start..start + block].iter().sum::<f64>() / block as f64)
+            .sum();
+        powers.push(power);
+        start += step;
+    }
+
+    let gated: Vec<f64> = powers.into_iter().filter(|&p| p > 0.0 && block_loudness(p) > -70.0).collect();
+    if gated.is_empty() {
+        return None;
+    }
+    let relative_gate = block_loudness(gated.iter().sum::<f64>() / gated.len() as f64) - 10.0;
+    let kept: Vec<f64> = gated.into_iter().filter(|&p| block_loudness(p) > relative_gate).collect();
+    Some(block_loudness(kept.iter().sum::<f64>() / kept.len() as f64))
+}
+
+/// Loudness of the file at `path`.
+pub fn measure(path: &Path) -> Result<Option<f64>, String> {
+    Ok(integrated_loudness(&decode(path)?))
+}
+
+/// Gain in dB that brings `pcm` to `target_lufs` without its peak passing
+/// -1 dBFS. None for silence.
+fn gain_to_target(pcm: &Pcm, target_lufs: f64) -> Option<(f64, f64)> {
+    let loudness = integrated_loudness(pcm)?;
+    let peak = f64::from(pcm.peak());
+    let headroom = if peak > 0.0 { PEAK_CEILING_DBFS - 20.0 * peak.log10() } else { f64::INFINITY };
+    Some((loudness, (target_lufs - loudness).min(headroom)))
+}
+
+fn write_wav(pcm: &Pcm, gain_db: f64, path: &Path) -> Result<(), String> {
+    let spec = hound::WavSpec {
+        channels: pcm.channels as u16,
+        sample_rate: pcm.sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let scale = 10f64.powf(gain_db / 20.0) as f32;
+    let mut writer = hound::WavWriter::create(path, spec).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    for sample in &pcm.samples {
+        let value = (sample * scale).clamp(-1.0, 1.0) * f32::from(i16::MAX);
+        writer
+            .write_sample(value.round() as i16)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    writer.finalize().map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Bring the clip at `path` to `target_lufs`. The result is `<stem>.wav` in
+/// the same directory and the file it replaced is moved to originals/.
+pub fn normalize_file(path: &Path, target_lufs: f64) -> Result<Normalized, String> {
+    let dir = path.parent().ok_or_else(|| format!("{} has no parent directory", path.display()))?;
+    let name = path.file_name().ok_or_else(|| format!("{} has no file name", path.display()))?.to_string_lossy().to_string();
+    let originals = dir.join(ORIGINALS_DIR);
+    let mut records = read_records(&originals);
+    let unchanged = |loudness_lufs| Normalized { path: path.to_path_buf(), original: None, loudness_lufs, gain_db: 0.0 };
+
+    // A file this module wrote, not replaced since: redo it from the original
+    let hash = atomic_write::content_hash(&fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?);
+    let previous = records
+        .get(&name)
+        .filter(|record| record.hash == hash && originals.join(&record.original).is_file())
+        .cloned();
+    if let Some(record) = &previous {
+        if (record.target_lufs - target_lufs).abs() < f64::EPSILON {
+            return Ok(unchanged(None));
+        }
+    }
+
+    // Never write over a .wav this module didn't make, e.g. ding.wav next
+    // to the ding.mp3 being converted
+    let output = path.with_extension("wav");
+    let output_name = output.file_name().unwrap_or_default().to_string_lossy().to_string();
+    if output != path && output.exists() {
+        let ours = fs::read(&output)
+            .ok()
+            .is_some_and(|bytes| records.get(&output_name).is_some_and(|record| record.hash == atomic_write::content_hash(&bytes)));
+        if !ours {
+            return Err(format!("{} already exists; rename {} to normalize it", output.display(), name));
+        }
+    }
+
+    let source = match &previous {
+        Some(record) => originals.join(&record.original),
+        None => path.to_path_buf(),
+    };
+    let pcm = decode(&source)?;
+    let Some((loudness, gain_db)) = gain_to_target(&pcm, target_lufs) else {
+        return Ok(unchanged(None));
+    };
+    if previous.is_none() && gain_db.abs() < TOLERANCE_DB {
+        return Ok(unchanged(Some(loudness)));
+    }
+
+    fs::create_dir_all(&originals).map_err(|e| format!("Failed to create originals directory: {}", e))?;
+    let original = match &previous {
+        Some(record) => originals.join(&record.original),
+        None => {
+            let kept = unused_path(&originals, &name);
+            fs::rename(path, &kept).map_err(|e| format!("Failed to keep original {}: {}", path.display(), e))?;
+            kept
+        }
+    };
+
+    write_wav(&pcm, gain_db, &output)?;
+    let hash = atomic_write::content_hash(&fs::read(&output).map_err(|e| format!("Failed to read {}: {}", output.display(), e))?);
+    let original_name = original.file_name().unwrap_or_default().to_string_lossy().to_string();
+    records.insert(output_name, Record { original: original_name, hash, target_lufs });
+    write_records(&originals, &records)?;
+
+    Ok(Normalized { path: output, original: Some(original), loudness_lufs: Some(loudness), gain_db })
+}
+
+/// `name` in `dir`, or `<stem>-2.<ext>`, `<stem>-3.<ext>`... if taken, so an
+/// original kept earlier is never replaced.
+fn unused_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if !path.exists() {
+        return path;
+    }
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (name, String::new()),
+    };
+    (2..)
+        .map(|n| dir.join(format!("{}-{}{}", stem, n, extension)))
+        .find(|path| !path.exists())
+        .expect("some numbered name is free")
+}
+
+/// Undo `normalize_file`: the original goes back next to the clip and the
+/// .wav that replaced it is removed.
+pub fn restore(normalized: &Normalized) -> Result<(), String> {
+    let Some(original) = &normalized.original else { return Ok(()) };
+    let dir = normalized.path.parent().ok_or_else(|| format!("{} has no parent directory", normalized.path.display()))?;
+    let name = original.file_name().ok_or_else(|| format!("{} has no file name", original.display()))?;
+    let restored = dir.join(name);
+    fs::rename(original, &restored).map_err(|e| format!("Failed to restore {}: {}", restored.display(), e))?;
+    if restored != normalized.path {
+        let _ = fs::remove_file(&normalized.path);
+    }
+
+    let originals = dir.join(ORIGINALS_DIR);
+    let mut records = read_records(&originals);
+    if let Some(output) = normalized.path.file_name() {
+        records.remove(output.to_string_lossy().as_ref());
+    }
+    write_records(&originals, &records)
+}
+
+/// Point event sounds and sound_library entries at the .wav files that
+/// replaced them. Returns the clips the config now refers to by their new
+/// path, which `restore` must undo if the config isn't saved.
+pub fn replace_sound_paths<'a>(config: &mut Config, normalized: &'a [Normalized]) -> Vec<&'a Normalized> {
+    let renamed: Vec<(PathBuf, &Normalized)> = normalized
+        .iter()
+        .filter_map(|n| {
+            let old = n.path.with_file_name(n.original.as_ref()?.file_name()?);
+            (old != n.path).then_some((old, n))
+        })
+        .collect();
+    let mut applied: Vec<&Normalized> = Vec::new();
+    let mut replacement = |sound: &str| {
+        let path = expand_home(sound);
+        let (_, n) = renamed.iter().find(|(old, _)| *old == path)?;
+        if !applied.iter().any(|a| std::ptr::eq(*a, *n)) {
+            applied.push(*n);
+        }
+        Some(n.path.to_string_lossy().to_string())
+    };
+
+    let mut replace = |sounds: &mut EventSounds| {
+        let updates: Vec<_> = sounds.iter().filter_map(|(event, sound)| Some((event, replacement(sound)?))).collect();
+        for (event, new) in updates {
+            sounds.set(event, new);
+        }
+    };
+    replace(&mut config.global_settings.event_sounds);
+    for project in &mut config.projects {
+        replace(&mut project.event_sounds);
+    }
+
+    let mut library: Vec<String> = Vec::with_capacity(config.sound_library.len());
+    for sound in config.sound_library.drain(..) {
+        let sound = replacement(&sound).unwrap_or(sound);
+        // The .wav may already be listed
+        if !library.contains(&sound) {
+            library.push(sound);
+        }
+    }
+    config.sound_library = library;
+    applied
+}
+
+/// Normalize every audio file directly in `dir`; originals/ is left alone.
+pub fn normalize_dir(dir: &Path, target_lufs: f64, report: &mut LibraryReport) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && audio_format::sniff_file(path).is_some())
+        .collect();
+    files.sort();
+
+    for path in files {
+        match normalize_file(&path, target_lufs) {
+            Ok(normalized) if normalized.original.is_some() => report.normalized.push(normalized),
+            Ok(_) => report.unchanged += 1,
+            Err(error) => report.failed.push(NormalizeFailure { path, error }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::HookEvent;
+
+    /// `seconds` of a 997 Hz sine at `amplitude` (1.0 = full scale) in
+    /// `channel` of `channels`, the others silent.
+    fn sine(amplitude: f32, sample_rate: u32, seconds: f32, channels: usize, channel: usize) -> Pcm {
+        let frames = (sample_rate as f32 * seconds) as usize;
+        let mut samples = vec![0.0; frames * channels];
+        for frame in 0..frames {
+            let t = frame as f32 / sample_rate as f32;
+            samples[frame * channels + channel] = amplitude * (2.0 * std::f32::consts::PI * 997.0 * t).sin();
+        }
+        Pcm { sample_rate, channels, samples }
+    }
+
+    fn write_sine(path: &Path, amplitude: f32) {
+        write_wav(&sine(amplitude, 48000, 2.0, 1, 0), 0.0, path).unwrap();
+    }
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 0.05, "{} LUFS, expected {}", actual, expected);
+    }
+
+    #[test]
+    fn full_scale_sine_reads_minus_3_lufs_at_any_rate() {
+        // BS.1770: a 0 dBFS 997 Hz sine in one channel is -3.01 LKFS
+        for rate in [44100, 48000, 96000] {
+            assert_near(integrated_loudness(&sine(1.0, rate, 2.0, 1, 0)).unwrap(), -3.01);
+        }
+        assert_near(integrated_loudness(&sine(0.1, 48000, 2.0, 1, 0)).unwrap(), -23.01);
+        // Both channels of a stereo file count in full
+        let mut stereo = sine(1.0, 48000, 2.0, 2, 0);
+        let right = sine(1.0, 48000, 2.0, 2, 1);
+        stereo.samples.iter_mut().zip(right.samples).for_each(|(l, r)| *l += r);
+        assert_near(integrated_loudness(&stereo).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn surround_channels_are_weighted_up() {
+        let left_surround = sine(1.0, 48000, 2.0, 5, 3);
+        assert_near(integrated_loudness(&left_surround).unwrap(), -3.01 + 10.0 * 1.41f64.log10());
+    }
+
+    #[test]
+    fn gates_drop_silence_and_quiet_passages() {
+        assert_eq!(integrated_loudness(&sine(0.0, 48000, 2.0, 1, 0)), None);
+        // -100 dBFS is below the absolute gate
+        assert_eq!(integrated_loudness(&sine(0.00001, 48000, 2.0, 1, 0)), None);
+        assert_eq!(integrated_loudness(&Pcm { sample_rate: 48000, channels: 1, samples: Vec::new() }), None);
+
+        // A passage 40 dB down falls under the relative gate; only blocks
+        // overlapping the loud passage count. Ungated, this would be -12 LUFS.
+        let mut pcm = sine(0.5, 48000, 3.0, 1, 0);
+        pcm.samples.extend(sine(0.005, 48000, 3.0, 1, 0).samples);
+        let loudness = integrated_loudness(&pcm).unwrap();
+        assert!((-9.5..-9.0).contains(&loudness), "{} LUFS", loudness);
+    }
+
+    #[test]
+    fn shorter_than_a_block_is_still_measured() {
+        assert_near(integrated_loudness(&sine(1.0, 48000, 0.2, 1, 0)).unwrap(), -3.01);
+    }
+
+    #[test]
+    fn normalizes_to_the_target_and_keeps_the_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let clip = dir.path().join("ding.wav");
+        write_sine(&clip, 0.1);
+
+        let normalized = normalize_file(&clip, -16.0).unwrap();
+        assert_eq!(normalized.path, clip);
+        assert_eq!(normalized.original, Some(dir.path().join("originals/ding.wav")));
+        assert_near(normalized.loudness_lufs.unwrap(), -23.01);
+        assert_near(measure(&clip).unwrap().unwrap(), -16.0);
+
+        // Already there: nothing to do
+        assert_eq!(normalize_file(&clip, -16.0).unwrap().original, None);
+        // A new target starts from the original, not the processed copy
+        let again = normalize_file(&clip, -20.0).unwrap();
+        assert_near(again.loudness_lufs.unwrap(), -23.01);
+        assert_near(measure(&clip).unwrap().unwrap(), -20.0);
+    }
+
+    #[test]
+    fn gain_stops_at_the_peak_ceiling() {
+        let dir = tempfile::tempdir().unwrap();
+        let clip = dir.path().join("click.wav");
+        // A quiet tone (-23 LUFS) with one loud click
+        let mut pcm = sine(0.1, 48000, 2.0, 1, 0);
+        pcm.samples[1000] = 0.5;
+        write_wav(&pcm, 0.0, &clip).unwrap();
+
+        let normalized = normalize_file(&clip, -16.0).unwrap();
+        // The click may only rise from -6 to -1 dBFS, short of the 7 dB needed
+        assert!((normalized.gain_db - (PEAK_CEILING_DBFS - 20.0 * 0.5f64.log10())).abs() < 0.01, "{:?}", normalized);
+        let peak = f64::from(decode(&clip).unwrap().peak());
+        assert!((peak - 10f64.powf(PEAK_CEILING_DBFS / 20.0)).abs() < 0.001, "peak {}", peak);
+    }
+
+    #[test]
+    fn silence_and_clips_near_the_target_are_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let quiet = dir.path().join("quiet.wav");
+        write_sine(&quiet, 0.0);
+        assert_eq!(normalize_file(&quiet, -16.0).unwrap(), Normalized {
+            path: quiet.clone(),
+            original: None,
+            loudness_lufs: None,
+            gain_db: 0.0
+        });
+
+        let close = dir.path().join("close.wav");
+        write_sine(&close, 0.1);
+        assert_eq!(normalize_file(&close, -23.2).unwrap().original, None);
+        assert!(!dir.path().join(ORIGINALS_DIR).exists());
+    }
+
+    #[test]
+    fn mp3_becomes_a_wav_that_lookups_prefer() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundled = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/voices/stop.mp3");
+        let mp3 = dir.path().join("stop.mp3");
+        fs::copy(&bundled, &mp3).unwrap();
+        let before = measure(&mp3).unwrap().unwrap();
+
+        let mut report = LibraryReport::default();
+        normalize_dir(dir.path(), -16.0, &mut report);
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        assert_eq!(report.normalized.len(), 1);
+        assert_eq!(report.normalized[0].path, dir.path().join("stop.wav"));
+        assert!((report.normalized[0].loudness_lufs.unwrap() - before).abs() < 0.01);
+        assert!(!mp3.exists() && dir.path().join("originals/stop.mp3").exists());
+
+        // A reinstall copies the mp3 back; the normalized clip still wins
+        fs::copy(&bundled, &mp3).unwrap();
+        assert_eq!(audio_format::find_clip(dir.path(), "stop"), Some(dir.path().join("stop.wav")));
+    }
+
+    #[test]
+    fn never_overwrites_a_wav_or_an_original_it_did_not_make() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundled = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/voices/stop.mp3");
+        let mp3 = dir.path().join("ding.mp3");
+        let wav = dir.path().join("ding.wav");
+        fs::copy(&bundled, &mp3).unwrap();
+        write_sine(&wav, 0.5);
+        let user_wav = fs::read(&wav).unwrap();
+
+        let error = normalize_file(&mp3, -30.0).unwrap_err();
+        assert!(error.contains("ding.wav already exists"), "{}", error);
+        assert!(fs::read(&wav).unwrap() == user_wav && mp3.exists());
+
+        // ding.wav itself is normalized and kept in originals/; a ding.wav
+        // put in its place later gets an original of its own alongside
+        let kept = dir.path().join("originals/ding.wav");
+        assert_eq!(normalize_file(&wav, -30.0).unwrap().original, Some(kept.clone()));
+        write_sine(&wav, 0.02);
+        let replacement = fs::read(&wav).unwrap();
+        let normalized = normalize_file(&wav, -30.0).unwrap();
+        let second = dir.path().join("originals/ding-2.wav");
+        assert_eq!(normalized.original, Some(second.clone()));
+        assert!(fs::read(&kept).unwrap() == user_wav);
+        assert!(fs::read(&second).unwrap() == replacement);
+
+        // Once ding.wav is a file this module made, the mp3 may replace it
+        assert!(normalize_file(&mp3, -30.0).is_ok());
+    }
+
+    #[test]
+    fn restore_undoes_a_normalization() {
+        let dir = tempfile::tempdir().unwrap();
+        let mp3 = dir.path().join("stop.mp3");
+        fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/voices/stop.mp3"), &mp3).unwrap();
+        let original = fs::read(&mp3).unwrap();
+
+        let normalized = normalize_file(&mp3, -30.0).unwrap();
+        restore(&normalized).unwrap();
+        assert_eq!(fs::read(&mp3).unwrap(), original);
+        assert!(!dir.path().join("stop.wav").exists());
+        assert!(read_records(&dir.path().join(ORIGINALS_DIR)).is_empty());
+        // Nothing to undo for an unchanged clip
+        restore(&Normalized { path: mp3.clone(), original: None, loudness_lufs: None, gain_db: 0.0 }).unwrap();
+        assert!(mp3.exists());
+    }
+
+    #[test]
+    fn config_follows_renamed_sounds() {
+        let normalized = [
+            Normalized {
+                path: PathBuf::from("/sounds/ding.wav"),
+                original: Some(PathBuf::from("/sounds/originals/ding.mp3")),
+                loudness_lufs: Some(-30.0),
+                gain_db: 14.0,
+            },
+            // Same name before and after: nothing to repoint
+            Normalized {
+                path: PathBuf::from("/sounds/chime.wav"),
+                original: Some(PathBuf::from("/sounds/originals/chime.wav")),
+                loudness_lufs: Some(-30.0),
+                gain_db: 14.0,
+            },
+        ];
+        let mut config = Config::default();
+        config.global_settings.event_sounds.set(HookEvent::Stop, "/sounds/ding.mp3".to_string());
+        config.global_settings.event_sounds.set(HookEvent::Notification, "/sounds/chime.wav".to_string());
+        config.sound_library =
+            ["/sounds/ding.mp3", "/sounds/ding.wav", "/sounds/chime.wav"].map(str::to_string).to_vec();
+
+        let applied = replace_sound_paths(&mut config, &normalized);
+        assert_eq!(applied, [&normalized[0]]);
+        let sounds = &config.global_settings.event_sounds;
+        assert_eq!(sounds.get(HookEvent::Stop).map(String::as_str), Some("/sounds/ding.wav"));
+        assert_eq!(sounds.get(HookEvent::Notification).map(String::as_str), Some("/sounds/chime.wav"));
+        assert_eq!(config.sound_library, ["/sounds/ding.wav", "/sounds/chime.wav"]);
+
+        // Nothing left to change
+        assert!(replace_sound_paths(&mut config, &normalized).is_empty());
+    }
+}
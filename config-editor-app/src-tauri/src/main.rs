@@ -1,152 +1,41 @@
 // Prevents additional console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use audio_notifier_config_editor::*;
 use serde::{Deserialize, Serialize};
-use sha2::{Sha256, Digest};
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use tauri::Manager;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager};
 use chrono::Utc;
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
-// ===== Config Structures =====
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct EventSounds {
-    notification: String,
-    stop: String,
-    pre_tool_use: String,
-    post_tool_use: String,
-    subagent_stop: String,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct ProjectConfig {
-    path: String,
-    #[serde(default)]
-    display_name: Option<String>,
-    enabled: bool,
-    event_sounds: EventSounds,
-    #[serde(default = "default_event_enabled")]
-    event_enabled: EventEnabled,
-    #[serde(default = "default_event_voice_enabled")]
-    voice_enabled: EventEnabled,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct EventEnabled {
-    notification: bool,
-    stop: bool,
-    pre_tool_use: bool,
-    post_tool_use: bool,
-    subagent_stop: bool,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct GlobalSettings {
-    enabled: bool,
-    event_sounds: EventSounds,
-    #[serde(default = "default_event_enabled")]
-    event_enabled: EventEnabled,
-    #[serde(default = "default_event_voice_enabled")]
-    voice_enabled: EventEnabled,
-    #[serde(default = "default_voice_template")]
-    voice_template: String,
-    #[serde(default = "default_voice_provider")]
-    voice_provider: String,
-    #[serde(default)]
-    voice_id: Option<String>,
-    #[serde(default)]
-    fish_audio_api_key: Option<String>,
-    #[serde(default)]
-    respect_do_not_disturb: bool,
-}
-
-fn default_event_enabled() -> EventEnabled {
-    EventEnabled {
-        notification: true,
-        stop: true,
-        pre_tool_use: false,
-        post_tool_use: false,
-        subagent_stop: true,
-    }
-}
-
-fn default_event_voice_enabled() -> EventEnabled {
-    EventEnabled {
-        notification: true,
-        stop: true,
-        pre_tool_use: false,
-        post_tool_use: false,
-        subagent_stop: true,
-    }
-}
-
-fn default_voice_template() -> String {
-    "{event} event".to_string()
-}
-
-fn default_voice_provider() -> String {
-    "fish_audio".to_string()
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Config {
-    global_mode: bool,
-    global_settings: GlobalSettings,
-    projects: Vec<ProjectConfig>,
-    sound_library: Vec<String>,
-    min_interval: u32,
-    debug: bool,
-}
-
-impl Default for Config {
-    fn default() -> Self {
-        let system_sounds = vec![
-            "/System/Library/Sounds/Ping.aiff".to_string(),
-            "/System/Library/Sounds/Glass.aiff".to_string(),
-            "/System/Library/Sounds/Hero.aiff".to_string(),
-            "/System/Library/Sounds/Submarine.aiff".to_string(),
-            "/System/Library/Sounds/Tink.aiff".to_string(),
-            "/System/Library/Sounds/Pop.aiff".to_string(),
-            "/System/Library/Sounds/Funk.aiff".to_string(),
-            "/System/Library/Sounds/Purr.aiff".to_string(),
-            "/System/Library/Sounds/Blow.aiff".to_string(),
-            "/System/Library/Sounds/Bottle.aiff".to_string(),
-            "/System/Library/Sounds/Frog.aiff".to_string(),
-            "/System/Library/Sounds/Basso.aiff".to_string(),
-        ];
-
-        Config {
-            global_mode: true,
-            global_settings: GlobalSettings {
-                enabled: true,
-                event_sounds: EventSounds {
-                    notification: "voice:simple".to_string(),
-                    stop: "voice:simple".to_string(),
-                    pre_tool_use: "voice:simple".to_string(),
-                    post_tool_use: "voice:simple".to_string(),
-                    subagent_stop: "voice:simple".to_string(),
-                },
-                event_enabled: default_event_enabled(),
-                voice_enabled: default_event_voice_enabled(),
-                voice_template: default_voice_template(),
-                voice_provider: "fish_audio".to_string(),
-                voice_id: None,
-                fish_audio_api_key: None,
-                respect_do_not_disturb: false,
-            },
-            projects: vec![],
-            sound_library: system_sounds,
-            min_interval: 2,
-            debug: false,
-        }
-    }
-}
+mod watcher;
+
+use atomic_write::WriteError;
+use config_history::{ConfigHistory, FieldChange, HistoryEntry};
+use events::{EventInfo, HookEvent};
+use layered_config::EffectiveConfig;
+use loudness::LibraryReport;
+use migration::MovedSecret;
+use profile_archive::{CollisionPolicy, ExportSummary, ImportReport};
+use profiles::{ProfileStore, ProfileSummary};
+use project_match::RuleMatch;
+use schedule::{Delivery, ScheduleRule};
+use secrets::{SecretBackend, SecretRef};
+use speech_summary::SpeakSummarySettings;
+use template::{Template, TemplateError, TemplateVars};
+use validation::ValidationFinding;
+use system_tts::EngineStatus;
+use voice_cache::{CacheStats, GcReport, VoiceCache};
+use voice_jobs::{RunReport, VoiceJob, VoiceQueue, VoiceRunner};
+use fish_audio::FishAudio;
+use voice_provider::{CostEstimate, ProviderError, VoiceInfo, VoiceProvider};
 
 // ===== Installation Manifest =====
 
@@ -165,151 +54,345 @@ struct InstallationChanges {
     existing_hooks_preserved: Vec<String>,
 }
 
-// ===== Helper Functions =====
+// ===== Tauri Commands =====
 
-fn get_home_dir() -> Result<String, String> {
-    std::env::var("HOME").map_err(|_| "Could not determine HOME directory".to_string())
+/// Hash of audio-notifier.yaml as of the last load/save, used to detect
+/// edits made outside the editor before overwriting them.
+#[derive(Default)]
+struct ConfigFileState {
+    loaded_hash: Mutex<Option<String>>,
+    /// Plaintext keys the watcher found in an externally edited file, put in
+    /// the secret store by the next save
+    pending_secrets: Mutex<Vec<MovedSecret>>,
 }
 
-fn get_config_path() -> PathBuf {
-    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
-    PathBuf::from(home).join(".claude/audio-notifier.yaml")
+#[tauri::command]
+async fn load_config(state: tauri::State<'_, ConfigFileState>) -> Result<Config, ConfigLoadError> {
+    let (config, hash) = read_config()?;
+    *state.loaded_hash.lock().unwrap() = Some(hash);
+    // read_config has moved any plaintext key itself
+    state.pending_secrets.lock().unwrap().clear();
+    Ok(config)
 }
 
-fn get_sounds_enabled_path() -> PathBuf {
-    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
-    PathBuf::from(home).join(".claude/.sounds-enabled")
+/// Effective config for sessions started in `directory`, with the matching
+/// project entry and any repo-local .claude/audio-notifier.yaml applied.
+#[tauri::command]
+async fn get_effective_config(directory: String) -> Result<EffectiveConfig, String> {
+    let (global, _) = read_config().map_err(|e| format!("Failed to load global config: {}", e))?;
+    let config_path = get_config_path();
+    let global_path = config_path.exists().then_some(config_path.as_path());
+    let home = PathBuf::from(get_home_dir()?);
+
+    layered_config::resolve(Path::new(&directory), &global, global_path, &home)
 }
 
-fn get_custom_sounds_dir() -> PathBuf {
-    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
-    PathBuf::from(home).join(".claude/sounds")
+/// Project rules that match `directory`, most specific first. The first entry
+/// is the rule that applies.
+#[tauri::command]
+async fn match_project_rule(directory: String) -> Result<Vec<RuleMatch>, String> {
+    let (config, _) = read_config().map_err(|e| format!("Failed to load config: {}", e))?;
+    let home = PathBuf::from(get_home_dir()?);
+    Ok(project_match::matching_rules(&config.projects, Path::new(&directory), &home))
 }
 
-fn get_voice_cache_dir() -> PathBuf {
-    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
-    PathBuf::from(home).join(".claude/voices")
+/// Saved config versions, newest first.
+#[tauri::command]
+async fn list_config_history() -> Result<Vec<HistoryEntry>, String> {
+    ConfigHistory::new(get_history_dir()).list()
 }
 
-fn hash_string(s: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(s.as_bytes());
-    format!("{:x}", hasher.finalize())
+/// Field-level changes from version `from` to version `to`, or to the current
+/// config when `to` is omitted.
+#[tauri::command]
+async fn diff_config_versions(from: String, to: Option<String>) -> Result<Vec<FieldChange>, String> {
+    let history = ConfigHistory::new(get_history_dir());
+    let before = history.load(&from)?;
+    let after = match to {
+        Some(to) => history.load(&to)?,
+        None => read_config().map_err(|e| format!("Failed to load config: {}", e))?.0,
+    };
+    Ok(config_history::diff(&before, &after))
 }
 
-// ===== Voice Generation =====
+/// Make version `id` the current config. The restore is itself recorded, so it
+/// can be undone by restoring the version before it.
+#[tauri::command]
+async fn restore_config_version(
+    id: String,
+    state: tauri::State<'_, ConfigFileState>,
+) -> Result<Config, String> {
+    let config = ConfigHistory::new(get_history_dir()).load(&id)?;
+    // Refuse to replace edits made since the editor loaded the config
+    let expected_hash = match state.loaded_hash.lock().unwrap().clone() {
+        Some(hash) => hash,
+        None => atomic_write::current_hash(&get_config_path()).map_err(|e| format!("Failed to read config: {}", e))?,
+    };
+    let hash = write_config(&config, Some(&expected_hash)).map_err(|e| format!("Failed to restore config: {}", e))?;
+    *state.loaded_hash.lock().unwrap() = Some(hash);
+    Ok(config)
+}
 
-async fn generate_voice_fish_audio(
-    text: &str,
-    api_key: &str,
-    voice_id: Option<&str>,
-) -> Result<Vec<u8>, String> {
-    let client = reqwest::Client::new();
+/// JSON Schema for audio-notifier.yaml.
+#[tauri::command]
+async fn get_config_schema() -> Result<serde_json::Value, String> {
+    Ok(config_schema::config_schema())
+}
 
-    let voice = voice_id.unwrap_or("af_bella"); // Default voice
+/// Registered hook events, in display order, for building the event lists in the UI.
+#[tauri::command]
+async fn list_hook_events() -> Result<&'static [EventInfo], String> {
+    Ok(events::all_event_info())
+}
+
+#[tauri::command]
+async fn validate_config(config: Config) -> Result<Vec<ValidationFinding>, String> {
+    Ok(validation::validate(&config))
+}
+
+/// Write the config to disk. With `strict`, configs that have validation
+/// errors are rejected so the hook scripts never see them. Unless `force` is
+/// set, the save fails with a conflict if the file was edited since it was loaded.
+#[tauri::command]
+async fn save_config(
+    config: Config,
+    strict: Option<bool>,
+    force: Option<bool>,
+    state: tauri::State<'_, ConfigFileState>,
+) -> Result<(), ConfigSaveError> {
+    if strict.unwrap_or(false) {
+        let findings = validation::validate(&config);
+        if validation::has_errors(&findings) {
+            return Err(ConfigSaveError::Invalid { findings });
+        }
+    }
 
-    let response = client
-        .post("https://api.fish.audio/v1/tts")
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&serde_json::json!({
-            "text": text,
-            "reference_id": voice,
-            "format": "mp3",
-            "latency": "normal"
-        }))
-        .send()
-        .await
-        .map_err(|e| format!("Failed to call Fish Audio API: {}", e))?;
+    let expected_hash = if force.unwrap_or(false) {
+        None
+    } else {
+        state.loaded_hash.lock().unwrap().clone()
+    };
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("Fish Audio API error {}: {}", status, error_text));
+    // The key must be in the secret store before the plaintext copy goes away
+    let mut pending = state.pending_secrets.lock().unwrap();
+    for secret in pending.iter() {
+        let reference = SecretRef::default_for(&secret.name);
+        let settings = &config.global_settings;
+        if [&settings.fish_audio_api_key, &settings.openai_api_key].iter().any(|r| r.as_ref() == Some(&reference)) {
+            reference.store(&secret.value).map_err(|e| ConfigSaveError::Io {
+                message: format!("Failed to move {} to the secret store: {}", secret.name, e),
+            })?;
+        }
     }
 
-    response
-        .bytes()
-        .await
-        .map(|b| b.to_vec())
-        .map_err(|e| format!("Failed to read response: {}", e))
+    let hash = write_config(&config, expected_hash.as_deref())?;
+    *state.loaded_hash.lock().unwrap() = Some(hash);
+    pending.clear();
+
+    Ok(())
 }
 
-fn generate_voice_system_tts(text: &str) -> Result<Vec<u8>, String> {
-    #[cfg(target_os = "macos")]
-    {
-        // Use macOS 'say' command to generate audio file
-        let temp_path = std::env::temp_dir().join(format!("tts_{}.aiff", hash_string(text)));
-
-        let output = Command::new("say")
-            .arg("-o")
-            .arg(&temp_path)
-            .arg(text)
-            .output()
-            .map_err(|e| format!("Failed to run 'say' command: {}", e))?;
-
-        if !output.status.success() {
-            return Err(format!("'say' command failed: {}", String::from_utf8_lossy(&output.stderr)));
+/// Bundle the config, custom sounds and generated voices into a .zip at `destination`.
+#[tauri::command]
+async fn export_profile(destination: String) -> Result<ExportSummary, String> {
+    let (config, _) = read_config().map_err(|e| format!("Failed to load config: {}", e))?;
+    let home = get_home_dir()?;
+
+    profile_archive::export(
+        Path::new(&destination),
+        &config,
+        &get_custom_sounds_dir(),
+        &get_voice_cache_dir(),
+        &home,
+    )
+}
+
+/// Unpack a profile archive and replace the current config with the archived
+/// one (the current config is backed up first). Existing sounds and voices
+/// with different contents are kept unless `on_collision` is "replace".
+#[tauri::command]
+async fn import_profile(
+    archive: String,
+    on_collision: Option<CollisionPolicy>,
+    state: tauri::State<'_, ConfigFileState>,
+) -> Result<ImportReport, String> {
+    let home = get_home_dir()?;
+    let mut imported = profile_archive::import(
+        Path::new(&archive),
+        &get_custom_sounds_dir(),
+        &get_voice_cache_dir(),
+        &home,
+        on_collision.unwrap_or_default(),
+    )?;
+
+    // Archives never carry API keys, so keep the ones already configured
+    let config_path = get_config_path();
+    let read_hash = match read_config() {
+        Ok((current, hash)) => {
+            imported.config.global_settings.keep_api_keys(&current.global_settings);
+            hash
         }
+        // A config that can't be loaded is backed up below and replaced
+        Err(_) => atomic_write::current_hash(&config_path).map_err(|e| format!("Failed to read config: {}", e))?,
+    };
+    // Refuse to replace edits made since the editor loaded the config
+    let expected_hash = state.loaded_hash.lock().unwrap().clone().unwrap_or(read_hash);
 
-        let bytes = fs::read(&temp_path)
-            .map_err(|e| format!("Failed to read generated audio: {}", e))?;
+    if config_path.exists() {
+        imported.report.config_backup_path = Some(backup_config_file(&config_path).map_err(|e| e.to_string())?);
+    }
+
+    // Sounds and voices are only unpacked once the config is saved
+    let hash = imported.apply(|config| {
+        write_config(config, Some(&expected_hash)).map_err(|e| format!("Failed to save imported config: {}", e))
+    })?;
+    *state.loaded_hash.lock().unwrap() = Some(hash);
+
+    Ok(imported.report)
+}
+
+// ===== Schedules =====
 
-        // Clean up temp file
-        let _ = fs::remove_file(&temp_path);
+#[tauri::command]
+async fn get_schedules() -> Result<Vec<ScheduleRule>, String> {
+    let (config, _) = read_config().map_err(|e| format!("Failed to load config: {}", e))?;
+    Ok(config.global_settings.schedules)
+}
 
-        Ok(bytes)
+/// Replace the quiet-hours rules in the saved config, leaving the rest of the
+/// file as it is on disk. Returns the saved config.
+#[tauri::command]
+async fn save_schedules(
+    schedules: Vec<ScheduleRule>,
+    state: tauri::State<'_, ConfigFileState>,
+) -> Result<Config, ConfigSaveError> {
+    let (mut config, hash) = read_config().map_err(|e| ConfigSaveError::Io {
+        message: format!("Failed to load config: {}", e),
+    })?;
+    config.global_settings.schedules = schedules;
+
+    // Problems elsewhere in the file aren't this save's to fix
+    let findings: Vec<ValidationFinding> = validation::validate(&config)
+        .into_iter()
+        .filter(|finding| validation::touches(&finding.field, "global_settings.schedules"))
+        .collect();
+    if validation::has_errors(&findings) {
+        return Err(ConfigSaveError::Invalid { findings });
     }
 
-    #[cfg(not(target_os = "macos"))]
-    {
-        Err("System TTS is only supported on macOS".to_string())
+    let new_hash = write_config(&config, Some(&hash))?;
+    // Only move the editor's hash forward if it had the file we just replaced
+    let mut loaded_hash = state.loaded_hash.lock().unwrap();
+    if loaded_hash.as_deref() == Some(hash.as_str()) {
+        *loaded_hash = Some(new_hash);
     }
+    Ok(config)
 }
 
-// ===== Tauri Commands =====
+#[derive(Debug, Serialize)]
+struct DeliveryCheck {
+    #[serde(flatten)]
+    schedule: Delivery,
+    /// False when the event is turned off, whatever the schedule says
+    event_enabled: bool,
+    /// The time that was checked
+    at: String,
+}
 
+/// Would `event` be delivered at `at` (default now)? Checks `schedules` when
+/// given, so unsaved rules can be tried out, otherwise the saved ones.
 #[tauri::command]
-async fn load_config() -> Result<Config, String> {
-    let config_path = get_config_path();
+async fn check_event_delivery(
+    event: String,
+    at: Option<String>,
+    schedules: Option<Vec<ScheduleRule>>,
+) -> Result<DeliveryCheck, String> {
+    let event = HookEvent::from_key(&event).ok_or_else(|| format!("Unknown event {}", event))?;
+    let at = match at.as_deref() {
+        Some(at) if !at.trim().is_empty() => schedule::parse_instant(at)?,
+        _ => Utc::now(),
+    };
+    let (config, _) = read_config().map_err(|e| format!("Failed to load config: {}", e))?;
+    let rules = schedules.unwrap_or_else(|| config.global_settings.schedules.clone());
+
+    Ok(DeliveryCheck {
+        schedule: schedule::delivery(&rules, event, at)?,
+        event_enabled: config.global_settings.event_enabled.is_enabled(event),
+        at: at.to_rfc3339(),
+    })
+}
 
-    if !config_path.exists() {
-        return Ok(Config::default());
-    }
+// ===== Profiles =====
+
+fn load_profiles() -> Result<(ProfileStore, String), String> {
+    profiles::load(&get_profiles_path(), &get_home_dir()?)
+}
 
-    let contents = fs::read_to_string(&config_path)
-        .map_err(|e| format!("Failed to read config file: {}", e))?;
+/// Load the profile store, apply `update` and save it.
+fn update_profiles(
+    update: impl FnOnce(&mut ProfileStore) -> Result<(), String>,
+) -> Result<ProfileSummary, String> {
+    let (mut store, hash) = load_profiles()?;
+    update(&mut store)?;
+    profiles::save(&get_profiles_path(), &store, &hash)?;
+    Ok(store.summary())
+}
 
-    // Try to parse as new config format first
-    match serde_yaml::from_str::<Config>(&contents) {
-        Ok(config) => Ok(config),
-        Err(_) => {
-            // If parsing fails, return default config (migration from old format)
-            let default_config = Config::default();
-            // Save the default config to migrate the file
-            save_config(default_config.clone()).await?;
-            Ok(default_config)
-        }
-    }
+/// Make `name` the live config. Returns the new config and the hash of the written file.
+fn switch_profile(name: &str) -> Result<(Config, String), String> {
+    let (mut store, profiles_hash) = load_profiles()?;
+    let (mut config, config_hash) = read_config().map_err(|e| format!("Failed to load config: {}", e))?;
+
+    store.activate(name, &mut config)?;
+
+    let hash = write_config(&config, Some(&config_hash)).map_err(|e| match e {
+        ConfigSaveError::Conflict { message } | ConfigSaveError::Io { message } => message,
+        ConfigSaveError::Invalid { .. } => "Profile config is invalid".to_string(),
+    })?;
+    profiles::save(&get_profiles_path(), &store, &profiles_hash)?;
+
+    Ok((config, hash))
 }
 
 #[tauri::command]
-async fn save_config(config: Config) -> Result<(), String> {
-    let config_path = get_config_path();
+async fn list_profiles() -> Result<ProfileSummary, String> {
+    Ok(load_profiles()?.0.summary())
+}
 
-    // Ensure directory exists
-    if let Some(parent) = config_path.parent() {
-        fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create config directory: {}", e))?;
-    }
+/// Save the current config on disk as a new profile named `name`.
+#[tauri::command]
+async fn create_profile(name: String, app_handle: tauri::AppHandle) -> Result<ProfileSummary, String> {
+    let (config, _) = read_config().map_err(|e| format!("Failed to load config: {}", e))?;
+    let summary = update_profiles(|store| store.create(&name, &config))?;
+    refresh_tray_menu(&app_handle);
+    Ok(summary)
+}
 
-    let yaml = serde_yaml::to_string(&config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+#[tauri::command]
+async fn clone_profile(source: String, name: String, app_handle: tauri::AppHandle) -> Result<ProfileSummary, String> {
+    let summary = update_profiles(|store| store.clone_profile(&source, &name))?;
+    refresh_tray_menu(&app_handle);
+    Ok(summary)
+}
 
-    fs::write(&config_path, yaml)
-        .map_err(|e| format!("Failed to write config file: {}", e))?;
+#[tauri::command]
+async fn delete_profile(name: String, app_handle: tauri::AppHandle) -> Result<ProfileSummary, String> {
+    let summary = update_profiles(|store| store.delete(&name))?;
+    refresh_tray_menu(&app_handle);
+    Ok(summary)
+}
 
-    Ok(())
+/// Switch to profile `name` and return the resulting config.
+#[tauri::command]
+async fn activate_profile(
+    name: String,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, ConfigFileState>,
+) -> Result<Config, String> {
+    let (config, hash) = switch_profile(&name)?;
+    *state.loaded_hash.lock().unwrap() = Some(hash);
+    refresh_tray_menu(&app_handle);
+    Ok(config)
 }
 
 #[tauri::command]
@@ -363,31 +446,66 @@ async fn preview_sound(sound_path: String) -> Result<(), String> {
     Ok(())
 }
 
+#[derive(Serialize)]
+struct VoiceTemplateCheck {
+    error: Option<TemplateError>,
+    /// Human-readable form of `error`
+    message: Option<String>,
+    /// The template rendered with sample values
+    preview: String,
+    /// Variables only filled in when a hook fires
+    runtime_variables: Vec<String>,
+}
+
+#[tauri::command]
+fn check_voice_template(template: String, event: Option<String>) -> VoiceTemplateCheck {
+    let event = event.as_deref().and_then(HookEvent::from_key).unwrap_or(HookEvent::Stop);
+    match Template::parse(&template) {
+        Ok(parsed) => VoiceTemplateCheck {
+            error: None,
+            message: None,
+            preview: parsed.render(&TemplateVars::sample(event)),
+            runtime_variables: parsed.runtime_variables(),
+        },
+        Err(e) => VoiceTemplateCheck {
+            message: Some(e.to_string()),
+            error: Some(e),
+            preview: String::new(),
+            runtime_variables: vec![],
+        },
+    }
+}
+
+/// The text speak_summary would say for `message` with `settings`.
 #[tauri::command]
-async fn preview_voice(text: String, api_key: Option<String>, app_handle: tauri::AppHandle) -> Result<(), String> {
+fn preview_spoken_summary(message: String, settings: SpeakSummarySettings) -> String {
+    speech_summary::spoken_text(&settings, &message)
+}
+
+#[tauri::command]
+async fn preview_voice(
+    text: String,
+    api_key: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), ProviderError> {
     println!("preview_voice called with text: {}", text);
 
     // Map text to bundled file name for basic events
-    let bundled_file_name = match text.as_str() {
-        "notification event" => Some("notification.mp3"),
-        "stop event" => Some("stop.mp3"),
-        "pre tool use event" => Some("pre_tool_use.mp3"),
-        "post tool use event" => Some("post_tool_use.mp3"),
-        "subagent stop event" => Some("subagent_stop.mp3"),
-        _ => None,
-    };
+    let bundled_clip = HookEvent::ALL
+        .iter()
+        .find(|event| text == format!("{} event", event.info().spoken_name))
+        .map(|event| event.key());
 
     // Check for bundled voice file first (for basic events)
-    if let Some(filename) = bundled_file_name {
-        let resource_path = app_handle
+    if let Some(clip) = bundled_clip {
+        let bundled_dir = app_handle
             .path()
             .resource_dir()
             .map_err(|e| format!("Failed to get resource dir: {}", e))?
             .join("resources")
-            .join("voices")
-            .join(filename);
+            .join("voices");
 
-        if resource_path.exists() {
+        if let Some(resource_path) = audio_format::find_clip(&bundled_dir, clip) {
             println!("Playing bundled voice file: {:?}", resource_path);
             #[cfg(target_os = "macos")]
             {
@@ -398,14 +516,13 @@ async fn preview_voice(text: String, api_key: Option<String>, app_handle: tauri:
             }
             return Ok(());
         } else {
-            println!("Bundled file not found at {:?}", resource_path);
+            println!("Bundled file {} not found in {:?}", clip, bundled_dir);
         }
 
         // Check for installed global voice file (from installation)
-        let voice_cache_dir = get_voice_cache_dir();
-        let global_file = voice_cache_dir.join("global").join(filename);
+        let global_dir = get_voice_cache_dir().join("global");
 
-        if global_file.exists() {
+        if let Some(global_file) = audio_format::find_clip(&global_dir, clip) {
             println!("Playing installed global voice file: {:?}", global_file);
             #[cfg(target_os = "macos")]
             {
@@ -416,49 +533,21 @@ async fn preview_voice(text: String, api_key: Option<String>, app_handle: tauri:
             }
             return Ok(());
         } else {
-            println!("Global voice file not found at {:?}", global_file);
+            println!("Global voice file {} not found in {:?}", clip, global_dir);
         }
     }
 
-    // Check for cached file (for project-specific voices)
-    let text_hash = hash_string(&text);
-    let voice_cache_dir = get_voice_cache_dir();
-    let cached_file = voice_cache_dir.join("previews").join(format!("{}.mp3", text_hash));
-
-    if cached_file.exists() {
-        println!("Playing cached voice file: {:?}", cached_file);
-        #[cfg(target_os = "macos")]
-        {
-            Command::new("afplay")
-                .arg(&cached_file)
-                .spawn()
-                .map_err(|e| format!("Failed to play cached voice: {}", e))?;
-        }
-        return Ok(());
-    }
-
-    println!("No bundled or cached file found, generating new voice");
-
-    // Generate voice file for project-specific text
-    let audio_bytes = if let Some(key) = api_key {
-        generate_voice_fish_audio(&text, &key, Some("af_bella")).await?
-    } else {
-        // Use system TTS as fallback
-        #[cfg(target_os = "macos")]
-        {
-            generate_voice_system_tts(&text)?
-        }
-        #[cfg(not(target_os = "macos"))]
-        {
-            return Err("No API key provided and system TTS not available".to_string());
-        }
-    };
-
-    // Cache the generated file
-    fs::create_dir_all(cached_file.parent().unwrap())
-        .map_err(|e| format!("Failed to create cache directory: {}", e))?;
-    fs::write(&cached_file, &audio_bytes)
-        .map_err(|e| format!("Failed to cache voice file: {}", e))?;
+    // Project-specific text comes from the voice cache, generated on a miss
+    let settings = read_config()
+        .map(|(config, _)| config.global_settings)
+        .unwrap_or_else(|_| Config::default().global_settings);
+    let provider = voice_provider::preview_provider(&settings, api_key)?;
+    let voice = voice_provider::configured_voice(&settings, provider.as_ref());
+    let cached = VoiceCache::for_settings(&settings)
+        .synthesize(provider.as_ref(), &text, voice)
+        .await?;
+    let cached_file = cached.path;
+    println!("Playing {} voice file: {:?}", if cached.hit { "cached" } else { "generated" }, cached_file);
 
     // Play the file
     #[cfg(target_os = "macos")]
@@ -473,6 +562,65 @@ async fn preview_voice(text: String, api_key: Option<String>, app_handle: tauri:
     Ok(())
 }
 
+// ===== API Key =====
+
+#[derive(Debug, Serialize)]
+struct ApiKeyStatus {
+    /// A key is present where the reference points
+    available: bool,
+    /// Why the key couldn't be read, e.g. a locked keyring
+    error: Option<String>,
+}
+
+#[tauri::command]
+async fn get_api_key_status(reference: Option<SecretRef>) -> Result<ApiKeyStatus, String> {
+    let Some(reference) = reference else {
+        return Ok(ApiKeyStatus { available: false, error: None });
+    };
+    Ok(match reference.resolve() {
+        Ok(key) => ApiKeyStatus { available: key.is_some(), error: None },
+        Err(e) => ApiKeyStatus { available: false, error: Some(e.to_string()) },
+    })
+}
+
+/// Store the Fish Audio API key in `backend` and return the reference to save
+/// in the config. The env backend stores nothing; it reads `env_var`.
+#[tauri::command]
+async fn set_api_key(
+    backend: SecretBackend,
+    key: Option<String>,
+    env_var: Option<String>,
+) -> Result<SecretRef, String> {
+    let reference = match backend {
+        SecretBackend::Env => {
+            let var = env_var
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| secrets::FISH_AUDIO_API_KEY_ENV.to_string());
+            return Ok(SecretRef::Env { var });
+        }
+        SecretBackend::File => SecretRef::File { name: secrets::FISH_AUDIO_API_KEY.to_string() },
+        SecretBackend::SecretService => SecretRef::SecretService { name: secrets::FISH_AUDIO_API_KEY.to_string() },
+    };
+
+    let key = key
+        .map(|k| k.trim().to_string())
+        .filter(|k| !k.is_empty())
+        .ok_or_else(|| "API key can't be empty".to_string())?;
+    reference
+        .store(&key)
+        .map_err(|e| format!("Failed to store API key: {}", e))?;
+    Ok(reference)
+}
+
+/// Remove the key `reference` points at from its store.
+#[tauri::command]
+async fn delete_api_key(reference: SecretRef) -> Result<(), String> {
+    reference
+        .delete()
+        .map_err(|e| format!("Failed to remove API key: {}", e))
+}
+
 // ===== Installation Safety Functions =====
 
 fn get_manifest_path() -> PathBuf {
@@ -480,9 +628,37 @@ fn get_manifest_path() -> PathBuf {
     PathBuf::from(home).join(".claude/audio-notifier-install.json")
 }
 
-fn get_backup_dir() -> PathBuf {
-    let home = get_home_dir().unwrap_or_else(|_| "/tmp".to_string());
-    PathBuf::from(home).join(".claude/backups")
+/// Load settings.json (or an empty object if it doesn't exist) together with
+/// the content hash needed by write_settings.
+fn read_settings(settings_file: &Path) -> Result<(serde_json::Value, String), String> {
+    let hash = atomic_write::current_hash(settings_file)
+        .map_err(|e| format!("Failed to read settings.json: {}", e))?;
+
+    if !settings_file.exists() {
+        return Ok((serde_json::json!({}), hash));
+    }
+
+    let contents = fs::read_to_string(settings_file)
+        .map_err(|e| format!("Failed to read settings.json: {}", e))?;
+    let settings = serde_json::from_str(&contents)
+        .map_err(|e| format!("Failed to parse settings.json: {}", e))?;
+
+    Ok((settings, hash))
+}
+
+/// Atomically write settings.json, refusing if it changed since read_settings.
+fn write_settings(settings_file: &Path, settings: &serde_json::Value, expected_hash: &str) -> Result<(), String> {
+    let settings_str = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+    atomic_write::write_if_unchanged(settings_file, Some(expected_hash), settings_str.as_bytes())
+        .map(|_| ())
+        .map_err(|e| match e {
+            WriteError::Conflict => {
+                "settings.json was changed by another process; nothing was written, please try again".to_string()
+            }
+            WriteError::Io(e) => format!("Failed to write settings.json: {}", e),
+        })
 }
 
 fn check_existing_hooks(settings: &serde_json::Value) -> Vec<String> {
@@ -727,14 +903,7 @@ async fn install_hooks(app_handle: tauri::AppHandle) -> Result<String, String> {
     let settings_file = claude_dir.join("settings.json");
 
     // Load existing settings
-    let mut settings: serde_json::Value = if settings_file.exists() {
-        let contents = fs::read_to_string(&settings_file)
-            .map_err(|e| format!("Failed to read settings.json: {}", e))?;
-        serde_json::from_str(&contents)
-            .map_err(|e| format!("Failed to parse settings.json: {}", e))?
-    } else {
-        serde_json::json!({})
-    };
+    let (mut settings, settings_hash) = read_settings(&settings_file)?;
 
     // Check for existing hooks
     let existing_hooks_list = check_existing_hooks(&settings);
@@ -746,50 +915,27 @@ async fn install_hooks(app_handle: tauri::AppHandle) -> Result<String, String> {
     // Store old hooks value for merging
     let old_hooks = settings.get("hooks").cloned().unwrap_or(serde_json::json!({}));
 
-    // Add our hooks configuration
-    let our_hooks_config = serde_json::json!({
-        "Notification": [{
-            "matcher": "",
-            "hooks": [{
-                "type": "command",
-                "command": format!("bash {}/.claude/scripts/smart-notify.sh notification", home)
-            }]
-        }],
-        "Stop": [{
-            "matcher": ".*",
-            "hooks": [
-                {
-                    "type": "command",
-                    "command": format!("jq -c -r '.' >> {}/.claude/stop-input.jsonl || cat >> {}/.claude/stop-input.jsonl", home, home)
-                },
-                {
-                    "type": "command",
-                    "command": format!("bash {}/.claude/scripts/smart-notify.sh stop", home)
-                }
-            ]
-        }],
-        "PreToolUse": [{
-            "matcher": "",
-            "hooks": [{
-                "type": "command",
-                "command": format!("bash {}/.claude/scripts/smart-notify.sh pre_tool_use", home)
-            }]
-        }],
-        "PostToolUse": [{
-            "matcher": "",
-            "hooks": [{
-                "type": "command",
-                "command": format!("bash {}/.claude/scripts/smart-notify.sh post_tool_use", home)
-            }]
-        }],
-        "SubagentStop": [{
-            "matcher": "",
-            "hooks": [{
+    // Add our hooks configuration, one entry per registered event
+    let mut our_hooks_config = serde_json::Map::new();
+    for event in HookEvent::ALL {
+        let info = event.info();
+        let mut commands = vec![];
+        if event == HookEvent::Stop {
+            commands.push(serde_json::json!({
                 "type": "command",
-                "command": format!("bash {}/.claude/scripts/smart-notify.sh subagent_stop", home)
-            }]
-        }]
-    });
+                "command": format!("jq -c -r '.' >> {}/.claude/stop-input.jsonl || cat >> {}/.claude/stop-input.jsonl", home, home)
+            }));
+        }
+        commands.push(serde_json::json!({
+            "type": "command",
+            "command": format!("bash {}/.claude/scripts/smart-notify.sh {}", home, info.key)
+        }));
+        our_hooks_config.insert(
+            info.hook_name.to_string(),
+            serde_json::json!([{ "matcher": info.matcher, "hooks": commands }]),
+        );
+    }
+    let our_hooks_config = serde_json::Value::Object(our_hooks_config);
 
     // Merge with existing hooks (preserve non-smart-notify hooks)
     let merged_hooks = merge_hooks(&old_hooks, our_hooks_config);
@@ -822,13 +968,10 @@ async fn install_hooks(app_handle: tauri::AppHandle) -> Result<String, String> {
     }
 
     // Identify which hooks we added
-    let hooks_added = vec![
-        "Notification".to_string(),
-        "Stop".to_string(),
-        "PreToolUse".to_string(),
-        "PostToolUse".to_string(),
-        "SubagentStop".to_string(),
-    ];
+    let hooks_added: Vec<String> = HookEvent::ALL
+        .iter()
+        .map(|event| event.info().hook_name.to_string())
+        .collect();
 
     // Identify existing hooks we preserved
     let existing_hooks_preserved: Vec<String> = if had_existing_hooks {
@@ -841,10 +984,7 @@ async fn install_hooks(app_handle: tauri::AppHandle) -> Result<String, String> {
     };
 
     // Save updated settings
-    let settings_str = serde_json::to_string_pretty(&settings)
-        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
-    fs::write(&settings_file, settings_str)
-        .map_err(|e| format!("Failed to write settings.json: {}", e))?;
+    write_settings(&settings_file, &settings, &settings_hash)?;
 
     // Enable sounds
     let sounds_enabled_file = claude_dir.join(".sounds-enabled");
@@ -854,12 +994,12 @@ async fn install_hooks(app_handle: tauri::AppHandle) -> Result<String, String> {
     // Create default config if it doesn't exist
     let config_file = claude_dir.join("audio-notifier.yaml");
     if !config_file.exists() {
-        let default_config = Config::default();
-        let yaml = serde_yaml::to_string(&default_config)
-            .map_err(|e| format!("Failed to serialize default config: {}", e))?;
-        fs::write(&config_file, yaml)
-            .map_err(|e| format!("Failed to create default config: {}", e))?;
-        files_created.push(format!("{}/.claude/audio-notifier.yaml", home));
+        // Expecting the empty-file hash means a config created concurrently is left alone
+        match write_config(&Config::default(), Some(&atomic_write::content_hash(b""))) {
+            Ok(_) => files_created.push(format!("{}/.claude/audio-notifier.yaml", home)),
+            Err(ConfigSaveError::Conflict { .. }) => {}
+            Err(e) => return Err(format!("Failed to create default config: {:?}", e)),
+        }
     }
 
     // Create installation manifest
@@ -897,12 +1037,85 @@ async fn upload_sound(source_path: String) -> Result<String, String> {
     fs::copy(&source, &dest_path)
         .map_err(|e| format!("Failed to copy sound file: {}", e))?;
 
+    // Bring it to the configured level; the file as uploaded goes to originals/
+    let settings = read_config()
+        .map(|(config, _)| config.global_settings)
+        .unwrap_or_else(|_| Config::default().global_settings);
+    let dest_path = if settings.loudness.normalize_uploads {
+        match loudness::normalize_file(&dest_path, settings.loudness.target_lufs) {
+            Ok(normalized) => normalized.path,
+            Err(e) => {
+                println!("{}", e);
+                dest_path
+            }
+        }
+    } else {
+        dest_path
+    };
+
     // Return the permanent path
     dest_path.to_str()
         .ok_or("Invalid destination path".to_string())
         .map(|s| s.to_string())
 }
 
+/// Normalize custom sounds and generated voices to `target_lufs`, or the
+/// configured target. Sounds in the config that became .wav files are
+/// pointed at the new files; if the config can't be saved, those files are
+/// restored.
+#[tauri::command]
+async fn normalize_sound_library(
+    target_lufs: Option<f64>,
+    state: tauri::State<'_, ConfigFileState>,
+) -> Result<LibraryReport, String> {
+    let (mut config, hash) = read_config().map_err(|e| format!("Failed to load config: {}", e))?;
+    let target = target_lufs.unwrap_or(config.global_settings.loudness.target_lufs);
+    if !(loudness::MIN_TARGET_LUFS..=loudness::MAX_TARGET_LUFS).contains(&target) {
+        return Err(format!(
+            "Target loudness must be between {} and {} LUFS",
+            loudness::MIN_TARGET_LUFS,
+            loudness::MAX_TARGET_LUFS
+        ));
+    }
+
+    let report = tokio::task::spawn_blocking(move || {
+        let mut report = LibraryReport::default();
+        loudness::normalize_dir(&get_custom_sounds_dir(), target, &mut report);
+        let voices_dir = get_voice_cache_dir();
+        loudness::normalize_dir(&voices_dir.join("global"), target, &mut report);
+        if let Ok(projects) = fs::read_dir(voices_dir.join("projects")) {
+            for project in projects.flatten() {
+                loudness::normalize_dir(&project.path(), target, &mut report);
+            }
+        }
+        report
+    })
+    .await
+    .map_err(|e| format!("Failed to normalize sounds: {}", e))?;
+
+    let applied = loudness::replace_sound_paths(&mut config, &report.normalized);
+    if !applied.is_empty() {
+        let new_hash = match write_config(&config, Some(&hash)) {
+            Ok(new_hash) => new_hash,
+            Err(e) => {
+                // Put back the files the saved config still refers to
+                for normalized in applied {
+                    if let Err(e) = loudness::restore(normalized) {
+                        println!("{}", e);
+                    }
+                }
+                return Err(format!("Failed to point the config at normalized sounds: {}", e));
+            }
+        };
+        let mut loaded_hash = state.loaded_hash.lock().unwrap();
+        if loaded_hash.as_deref() == Some(hash.as_str()) {
+            *loaded_hash = Some(new_hash);
+        }
+    }
+
+    Ok(report)
+}
+
 #[tauri::command]
 async fn get_recent_projects() -> Result<Vec<String>, String> {
     let home = get_home_dir()?;
@@ -954,8 +1167,8 @@ async fn open_log_file() -> Result<(), String> {
     Ok(())
 }
 
-#[tauri::command]
-async fn list_custom_sounds() -> Result<Vec<String>, String> {
+/// Audio files in ~/.claude/sounds, as absolute paths.
+fn custom_sound_paths() -> Result<Vec<String>, String> {
     let sounds_dir = get_custom_sounds_dir();
 
     if !sounds_dir.exists() {
@@ -966,14 +1179,12 @@ async fn list_custom_sounds() -> Result<Vec<String>, String> {
         .map_err(|e| format!("Failed to read sounds directory: {}", e))?;
 
     let mut sounds = vec![];
-    for entry in entries {
-        if let Ok(entry) = entry {
-            let path = entry.path();
-            if let Some(ext) = path.extension() {
-                if ext == "aiff" || ext == "wav" || ext == "mp3" {
-                    if let Some(path_str) = path.to_str() {
-                        sounds.push(path_str.to_string());
-                    }
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if let Some(ext) = path.extension() {
+            if ext == "aiff" || ext == "wav" || ext == "mp3" {
+                if let Some(path_str) = path.to_str() {
+                    sounds.push(path_str.to_string());
                 }
             }
         }
@@ -983,151 +1194,209 @@ async fn list_custom_sounds() -> Result<Vec<String>, String> {
 }
 
 #[tauri::command]
-async fn pregenerate_basic_voices(api_key: String) -> Result<String, String> {
-    let voice_cache_dir = get_voice_cache_dir();
-    let preview_dir = voice_cache_dir.join("previews");
-    fs::create_dir_all(&preview_dir)
-        .map_err(|e| format!("Failed to create preview directory: {}", e))?;
-
-    let basic_texts = vec![
-        "notification event",
-        "stop event",
-        "pre tool use event",
-        "post tool use event",
-        "subagent stop event",
-    ];
-
-    for text in basic_texts {
-        let text_hash = hash_string(text);
-        let cached_file = preview_dir.join(format!("{}.mp3", text_hash));
+async fn list_custom_sounds() -> Result<Vec<String>, String> {
+    custom_sound_paths()
+}
 
-        if cached_file.exists() {
-            println!("Skipping {}, already cached", text);
-            continue;
-        }
+#[tauri::command]
+async fn pregenerate_basic_voices(api_key: String) -> Result<String, String> {
+    let settings = read_config()
+        .map(|(config, _)| config.global_settings)
+        .unwrap_or_else(|_| Config::default().global_settings);
+    let cache = VoiceCache::for_settings(&settings);
+
+    let basic_texts: Vec<String> = HookEvent::ALL
+        .iter()
+        .map(|event| format!("{} event", event.info().spoken_name))
+        .collect();
 
-        println!("Generating voice for: {}", text);
-        let audio_bytes = generate_voice_fish_audio(text, &api_key, Some("af_bella")).await?;
-        fs::write(&cached_file, audio_bytes)
-            .map_err(|e| format!("Failed to write voice file: {}", e))?;
+    let provider = FishAudio::new(api_key, &settings.fish_audio_base_url);
+    for text in &basic_texts {
+        let cached = cache.synthesize(&provider, text, None).await?;
+        println!("{} voice for: {}", if cached.hit { "Cached" } else { "Generated" }, text);
     }
 
-    Ok("Pre-generated 5 basic voice files".to_string())
+    Ok(format!("Pre-generated {} basic voice files", basic_texts.len()))
 }
 
-#[tauri::command]
-async fn generate_voice_notifications(config: Config, api_key: Option<String>) -> Result<String, String> {
+/// Every voice notification `config` uses.
+fn voice_notification_jobs(config: &Config, template: &Template) -> Vec<VoiceJob> {
     let voice_cache_dir = get_voice_cache_dir();
+    let mut jobs = Vec::new();
 
-    // Create cache directories
-    fs::create_dir_all(&voice_cache_dir)
-        .map_err(|e| format!("Failed to create voice cache directory: {}", e))?;
-
-    let mut generated_count = 0;
-
-    // Generate global voice notifications
     if config.global_mode {
         let global_dir = voice_cache_dir.join("global");
-        fs::create_dir_all(&global_dir)
-            .map_err(|e| format!("Failed to create global voice directory: {}", e))?;
-
-        let events = vec![
-            ("notification", "notification"),
-            ("stop", "stop"),
-            ("pre_tool_use", "pre tool use"),
-            ("post_tool_use", "post tool use"),
-            ("subagent_stop", "subagent stop"),
-        ];
-
-        for (event_key, event_name) in events {
-            let voice_enabled = match event_key {
-                "notification" => config.global_settings.voice_enabled.notification,
-                "stop" => config.global_settings.voice_enabled.stop,
-                "pre_tool_use" => config.global_settings.voice_enabled.pre_tool_use,
-                "post_tool_use" => config.global_settings.voice_enabled.post_tool_use,
-                "subagent_stop" => config.global_settings.voice_enabled.subagent_stop,
-                _ => false,
-            };
-
-            if !voice_enabled {
-                continue;
+        for event in HookEvent::ALL {
+            if config.global_settings.voice_enabled.is_enabled(event) {
+                // Hook-time variables render as empty (or their fallbacks) here
+                let text = template.render(&TemplateVars::for_event(event));
+                jobs.push(VoiceJob::new(global_dir.join(event.key()), text));
             }
+        }
+    }
 
-            let text = config.global_settings.voice_template
-                .replace("{event}", event_name)
-                .replace("{project}", "");
-
-            let audio_bytes = match config.global_settings.voice_provider.as_str() {
-                "fish-audio" => {
-                    let api_key = api_key.as_ref().ok_or("Fish Audio API key required")?;
-                    generate_voice_fish_audio(&text, api_key, config.global_settings.voice_id.as_deref()).await?
-                },
-                "system" | _ => {
-                    generate_voice_system_tts(&text)?
-                }
-            };
+    for project in &config.projects {
+        let project_dir = voice_cache_dir.join("projects").join(hash_string(&project.path));
+        let display_name = project.display_name.as_ref().unwrap_or(&project.path);
+        for event in HookEvent::ALL {
+            if project.voice_enabled.is_enabled(event) {
+                let mut vars = TemplateVars::for_event(event);
+                vars.set("project", display_name.as_str());
+                jobs.push(VoiceJob::new(project_dir.join(event.key()), template.render(&vars)));
+            }
+        }
+    }
 
-            let file_path = global_dir.join(format!("{}.mp3", event_key));
-            fs::write(&file_path, audio_bytes)
-                .map_err(|e| format!("Failed to write voice file: {}", e))?;
+    jobs
+}
 
-            generated_count += 1;
+/// Cancel flag of the voice generation run in progress, if any.
+#[derive(Default)]
+struct VoiceGenerationState {
+    cancel: Mutex<Option<Arc<AtomicBool>>>,
+}
+
+/// Run `queue` with the provider `settings` select, emitting
+/// voice-generation-progress after every clip.
+async fn run_voice_queue(
+    mut queue: VoiceQueue,
+    settings: &GlobalSettings,
+    api_key: Option<String>,
+    concurrency: Option<usize>,
+    app_handle: &tauri::AppHandle,
+    state: &VoiceGenerationState,
+) -> Result<RunReport, String> {
+    let provider: Arc<dyn VoiceProvider> = voice_provider::from_settings(settings, api_key)?.into();
+    let voice = voice_provider::configured_voice(settings, provider.as_ref()).map(str::to_string);
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    {
+        let mut current = state.cancel.lock().unwrap();
+        if current.is_some() {
+            return Err("Voice generation is already running".to_string());
         }
+        *current = Some(cancel.clone());
     }
 
-    // Generate project-specific voice notifications
-    for project in &config.projects {
-        let project_hash = hash_string(&project.path);
-        let project_dir = voice_cache_dir.join("projects").join(&project_hash);
-        fs::create_dir_all(&project_dir)
-            .map_err(|e| format!("Failed to create project voice directory: {}", e))?;
-
-        let events = vec![
-            ("notification", "notification"),
-            ("stop", "stop"),
-            ("pre_tool_use", "pre tool use"),
-            ("post_tool_use", "post tool use"),
-            ("subagent_stop", "subagent stop"),
-        ];
-
-        for (event_key, event_name) in events {
-            let voice_enabled = match event_key {
-                "notification" => project.voice_enabled.notification,
-                "stop" => project.voice_enabled.stop,
-                "pre_tool_use" => project.voice_enabled.pre_tool_use,
-                "post_tool_use" => project.voice_enabled.post_tool_use,
-                "subagent_stop" => project.voice_enabled.subagent_stop,
-                _ => false,
-            };
-
-            if !voice_enabled {
-                continue;
-            }
+    let runner = VoiceRunner {
+        provider,
+        voice,
+        cache: Arc::new(VoiceCache::for_settings(settings)),
+        concurrency: concurrency.unwrap_or(voice_jobs::DEFAULT_CONCURRENCY),
+        cancel,
+        queue_path: Some(get_voice_queue_path()),
+        loudness_target: settings.loudness.normalize_voices.then_some(settings.loudness.target_lufs),
+    };
+    let report = runner
+        .run(&mut queue, |progress| {
+            let _ = app_handle.emit("voice-generation-progress", progress);
+        })
+        .await;
 
-            let display_name = project.display_name.as_ref().unwrap_or(&project.path);
-            let text = config.global_settings.voice_template
-                .replace("{event}", event_name)
-                .replace("{project}", display_name);
-
-            let audio_bytes = match config.global_settings.voice_provider.as_str() {
-                "fish-audio" => {
-                    let api_key = api_key.as_ref().ok_or("Fish Audio API key required")?;
-                    generate_voice_fish_audio(&text, api_key, config.global_settings.voice_id.as_deref()).await?
-                },
-                "system" | _ => {
-                    generate_voice_system_tts(&text)?
-                }
-            };
+    *state.cancel.lock().unwrap() = None;
+    Ok(report)
+}
 
-            let file_path = project_dir.join(format!("{}.mp3", event_key));
-            fs::write(&file_path, audio_bytes)
-                .map_err(|e| format!("Failed to write voice file: {}", e))?;
+/// Generate every voice clip `config` uses. Clips that fail don't stop the
+/// others; the report lists each one.
+#[tauri::command]
+async fn generate_voice_notifications(
+    config: Config,
+    api_key: Option<String>,
+    concurrency: Option<usize>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, VoiceGenerationState>,
+) -> Result<RunReport, String> {
+    let template = Template::parse(&config.global_settings.voice_template)
+        .map_err(|e| format!("Invalid voice template: {}", e))?;
+    let queue = VoiceQueue::new(voice_notification_jobs(&config, &template));
+    run_voice_queue(queue, &config.global_settings, api_key, concurrency, &app_handle, &state).await
+}
+
+/// Continue the last run that failed or was cancelled, generating only the
+/// clips it didn't finish.
+#[tauri::command]
+async fn resume_voice_generation(
+    api_key: Option<String>,
+    concurrency: Option<usize>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, VoiceGenerationState>,
+) -> Result<RunReport, String> {
+    let queue = VoiceQueue::load(&get_voice_queue_path())?
+        .ok_or_else(|| "No unfinished voice generation to resume".to_string())?;
+    let (config, _) = read_config().map_err(|e| format!("Failed to load config: {}", e))?;
+    run_voice_queue(queue, &config.global_settings, api_key, concurrency, &app_handle, &state).await
+}
 
-            generated_count += 1;
+/// The queue left by an unfinished run, for offering to resume it.
+#[tauri::command]
+fn get_voice_generation_queue() -> Result<Option<VoiceQueue>, String> {
+    VoiceQueue::load(&get_voice_queue_path())
+}
+
+/// Stop starting new clips; ones already being generated finish. Returns
+/// whether a run was in progress.
+#[tauri::command]
+fn cancel_voice_generation(state: tauri::State<'_, VoiceGenerationState>) -> bool {
+    match state.cancel.lock().unwrap().as_ref() {
+        Some(cancel) => {
+            cancel.store(true, Ordering::SeqCst);
+            true
         }
+        None => false,
     }
+}
+
+/// Voices offered by the provider `config` selects.
+#[tauri::command]
+async fn list_voices(config: Config, api_key: Option<String>) -> Result<Vec<VoiceInfo>, ProviderError> {
+    let provider = voice_provider::from_settings(&config.global_settings, api_key)?;
+    provider.list_voices().await
+}
+
+fn voice_cache() -> VoiceCache {
+    let settings = read_config()
+        .map(|(config, _)| config.global_settings)
+        .unwrap_or_else(|_| Config::default().global_settings);
+    VoiceCache::for_settings(&settings)
+}
+
+#[tauri::command]
+fn voice_cache_stats() -> CacheStats {
+    voice_cache().stats()
+}
+
+/// Trim the voice cache to its size cap and drop files it no longer tracks.
+#[tauri::command]
+fn voice_cache_gc() -> Result<GcReport, String> {
+    // Older versions cached previews by text alone
+    let _ = fs::remove_dir_all(get_voice_cache_dir().join("previews"));
+    voice_cache().gc()
+}
 
-    Ok(format!("Generated {} voice notifications", generated_count))
+#[tauri::command]
+fn voice_cache_clear() -> Result<GcReport, String> {
+    voice_cache().clear()
+}
+
+/// Offline speech engines found on this machine, in the order the system
+/// provider tries them.
+#[tauri::command]
+fn detect_tts_engines(config: Config) -> Vec<EngineStatus> {
+    system_tts::detect(&config.global_settings.system_tts)
+}
+
+/// What generate_voice_notifications would cost with the provider `config` selects.
+#[tauri::command]
+fn estimate_voice_cost(config: Config) -> Result<CostEstimate, String> {
+    let template = Template::parse(&config.global_settings.voice_template)
+        .map_err(|e| format!("Invalid voice template: {}", e))?;
+    let provider = voice_provider::from_settings(&config.global_settings, None)?;
+    let texts: Vec<String> = voice_notification_jobs(&config, &template)
+        .into_iter()
+        .map(|job| job.text)
+        .collect();
+    Ok(provider.estimate_cost(&texts))
 }
 
 // ===== Uninstall Functions =====
@@ -1166,10 +1435,7 @@ async fn uninstall_hooks() -> Result<String, String> {
         return Err("settings.json not found".to_string());
     }
 
-    let contents = fs::read_to_string(&settings_file)
-        .map_err(|e| format!("Failed to read settings.json: {}", e))?;
-    let mut settings: serde_json::Value = serde_json::from_str(&contents)
-        .map_err(|e| format!("Failed to parse settings.json: {}", e))?;
+    let (mut settings, settings_hash) = read_settings(&settings_file)?;
 
     // Remove only our hooks (those containing smart-notify.sh)
     if let Some(hooks) = settings.get_mut("hooks").and_then(|h| h.as_object_mut()) {
@@ -1200,10 +1466,7 @@ async fn uninstall_hooks() -> Result<String, String> {
     }
 
     // Save updated settings
-    let settings_str = serde_json::to_string_pretty(&settings)
-        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
-    fs::write(&settings_file, settings_str)
-        .map_err(|e| format!("Failed to write settings.json: {}", e))?;
+    write_settings(&settings_file, &settings, &settings_hash)?;
 
     // Remove scripts
     let scripts_to_remove = vec![
@@ -1235,6 +1498,13 @@ async fn uninstall_hooks() -> Result<String, String> {
             .map_err(|e| format!("Failed to remove global voices: {}", e))?;
     }
 
+    // Remove the voice cache
+    let speech_cache_dir = claude_dir.join("voice-cache");
+    if speech_cache_dir.exists() {
+        fs::remove_dir_all(&speech_cache_dir)
+            .map_err(|e| format!("Failed to remove voice cache: {}", e))?;
+    }
+
     // Remove terminal-notifier.app
     let terminal_notifier_dir = claude_dir.join("terminal-notifier.app");
     if terminal_notifier_dir.exists() {
@@ -1353,22 +1623,7 @@ async fn export_diagnostics() -> Result<String, String> {
     let config_file = claude_dir.join("audio-notifier.yaml");
     if config_file.exists() {
         if let Ok(contents) = fs::read_to_string(&config_file) {
-            // Simple sanitization - replace API key values
-            let sanitized = contents.lines()
-                .map(|line| {
-                    if line.contains("api_key:") || line.contains("fish_audio_api_key:") {
-                        if let Some(pos) = line.find(':') {
-                            format!("{}:  [REDACTED]", &line[..pos])
-                        } else {
-                            line.to_string()
-                        }
-                    } else {
-                        line.to_string()
-                    }
-                })
-                .collect::<Vec<_>>()
-                .join("\n");
-            diagnostics["config_yaml"] = serde_json::json!(sanitized);
+            diagnostics["config_yaml"] = serde_json::json!(redacted_config(&contents));
         }
     }
 
@@ -1385,6 +1640,12 @@ struct ActivityEvent {
     message: Option<String>,
     full_message: Option<String>,
     project: Option<String>,
+    /// AUDIO_NOTIFIER_* overrides active in the hook's shell, e.g. "mute"
+    #[serde(default)]
+    overrides: Vec<String>,
+    /// Display name from the event registry, filled in when the log is read
+    #[serde(default)]
+    label: Option<String>,
 }
 
 #[tauri::command]
@@ -1407,6 +1668,10 @@ async fn get_activity_log() -> Result<Vec<ActivityEvent>, String> {
     let mut reversed_events = events;
     reversed_events.reverse();
 
+    for event in &mut reversed_events {
+        event.label = HookEvent::from_key(&event.event).map(|e| e.info().display_name.to_string());
+    }
+
     Ok(reversed_events)
 }
 
@@ -1452,6 +1717,13 @@ async fn dev_reset_install() -> Result<(), String> {
             .map_err(|e| format!("Failed to remove global voices: {}", e))?;
     }
 
+    // Delete the voice cache
+    let speech_cache_dir = claude_dir.join("voice-cache");
+    if speech_cache_dir.exists() {
+        fs::remove_dir_all(&speech_cache_dir)
+            .map_err(|e| format!("Failed to remove voice cache: {}", e))?;
+    }
+
     // Delete terminal-notifier.app
     let terminal_notifier_dir = claude_dir.join("terminal-notifier.app");
     if terminal_notifier_dir.exists() {
@@ -1462,18 +1734,12 @@ async fn dev_reset_install() -> Result<(), String> {
     // Remove hooks from settings.json
     let settings_file = claude_dir.join("settings.json");
     if settings_file.exists() {
-        let contents = fs::read_to_string(&settings_file)
-            .map_err(|e| format!("Failed to read settings.json: {}", e))?;
-
-        if let Ok(mut settings) = serde_json::from_str::<serde_json::Value>(&contents) {
+        if let Ok((mut settings, settings_hash)) = read_settings(&settings_file) {
             if settings.get("hooks").is_some() {
                 if let Some(obj) = settings.as_object_mut() {
                     obj.remove("hooks");
                 }
-                let settings_str = serde_json::to_string_pretty(&settings)
-                    .map_err(|e| format!("Failed to serialize settings: {}", e))?;
-                fs::write(&settings_file, settings_str)
-                    .map_err(|e| format!("Failed to write settings.json: {}", e))?;
+                write_settings(&settings_file, &settings, &settings_hash)?;
             }
         }
     }
@@ -1483,19 +1749,60 @@ async fn dev_reset_install() -> Result<(), String> {
 
 // ===== System Tray =====
 
-fn create_tray(app: &tauri::AppHandle) -> Result<(), Box<dyn std::error::Error>> {
-    use tauri::{
-        menu::{Menu, MenuItem},
-        tray::{TrayIconBuilder, TrayIconEvent},
-    };
+const TRAY_ID: &str = "main";
+const PROFILE_MENU_PREFIX: &str = "profile:";
+
+fn build_tray_menu(app: &tauri::AppHandle) -> tauri::Result<tauri::menu::Menu<tauri::Wry>> {
+    use tauri::menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
 
     let toggle_i = MenuItem::with_id(app, "toggle", "Toggle Sounds", true, None::<&str>)?;
     let settings_i = MenuItem::with_id(app, "settings", "Settings", true, None::<&str>)?;
     let quit_i = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
 
-    let menu = Menu::with_items(app, &[&toggle_i, &settings_i, &quit_i])?;
+    // A broken profiles file shouldn't take the tray down with it
+    let summary = load_profiles().map(|(store, _)| store.summary()).unwrap_or_else(|e| {
+        println!("Failed to load profiles for tray: {}", e);
+        ProfileSummary { names: vec![], active: None }
+    });
+
+    let profile_items = summary
+        .names
+        .iter()
+        .map(|name| {
+            let active = summary.active.as_deref() == Some(name.as_str());
+            CheckMenuItem::with_id(app, format!("{}{}", PROFILE_MENU_PREFIX, name), name, true, active, None::<&str>)
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let no_profiles_i = MenuItem::with_id(app, "no_profiles", "No profiles yet", false, None::<&str>)?;
+    let profile_refs: Vec<&dyn IsMenuItem<tauri::Wry>> = if profile_items.is_empty() {
+        vec![&no_profiles_i]
+    } else {
+        profile_items.iter().map(|item| item as &dyn IsMenuItem<tauri::Wry>).collect()
+    };
+    let profiles_menu = Submenu::with_items(app, "Profile", true, &profile_refs)?;
 
-    let _tray = TrayIconBuilder::new()
+    let separator = PredefinedMenuItem::separator(app)?;
+    Menu::with_items(app, &[&toggle_i, &profiles_menu, &separator, &settings_i, &quit_i])
+}
+
+/// Rebuild the tray menu after profiles change.
+fn refresh_tray_menu(app: &tauri::AppHandle) {
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        match build_tray_menu(app) {
+            Ok(menu) => {
+                let _ = tray.set_menu(Some(menu));
+            }
+            Err(e) => println!("Failed to rebuild tray menu: {}", e),
+        }
+    }
+}
+
+fn create_tray(app: &tauri::AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+    use tauri::tray::{TrayIconBuilder, TrayIconEvent};
+
+    let menu = build_tray_menu(app)?;
+
+    let _tray = TrayIconBuilder::with_id(TRAY_ID)
         .menu(&menu)
         .on_menu_event(|app, event| match event.id.as_ref() {
             "toggle" => {
@@ -1513,7 +1820,15 @@ fn create_tray(app: &tauri::AppHandle) -> Result<(), Box<dyn std::error::Error>>
             "quit" => {
                 std::process::exit(0);
             }
-            _ => {}
+            id => {
+                // The config watcher tells an open editor about the switch
+                if let Some(name) = id.strip_prefix(PROFILE_MENU_PREFIX) {
+                    if let Err(e) = switch_profile(name) {
+                        println!("Failed to switch profile: {}", e);
+                    }
+                    refresh_tray_menu(app);
+                }
+            }
         })
         .on_tray_icon_event(|tray, event| {
             if let TrayIconEvent::Click { .. } = event {
@@ -1565,21 +1880,70 @@ fn main() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .manage(ConfigFileState::default())
+        .manage(VoiceGenerationState::default())
         .setup(|app| {
             create_tray(app.handle())?;
+
+            // Earlier versions saved every generated voice as .mp3, whatever its format
+            let relabelled = audio_format::relabel_dir(&get_voice_cache_dir());
+            if relabelled > 0 {
+                println!("Renamed {} voice files to match their audio format", relabelled);
+            }
+
+            // Push edits made outside the editor to the frontend
+            match watcher::start(app.handle().clone()) {
+                Ok(config_watcher) => {
+                    app.manage(config_watcher);
+                }
+                Err(e) => println!("Config watcher disabled: {}", e),
+            }
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             load_config,
             save_config,
+            validate_config,
+            get_effective_config,
+            match_project_rule,
+            get_schedules,
+            save_schedules,
+            check_event_delivery,
+            list_hook_events,
+            get_config_schema,
+            list_config_history,
+            diff_config_versions,
+            restore_config_version,
+            export_profile,
+            import_profile,
+            list_profiles,
+            create_profile,
+            clone_profile,
+            delete_profile,
+            activate_profile,
+            get_api_key_status,
+            set_api_key,
+            delete_api_key,
             get_sounds_enabled,
             set_sounds_enabled,
             was_uninstalled,
             preview_sound,
+            check_voice_template,
+            preview_spoken_summary,
+            list_voices,
+            resume_voice_generation,
+            cancel_voice_generation,
+            get_voice_generation_queue,
+            detect_tts_engines,
+            voice_cache_stats,
+            voice_cache_gc,
+            voice_cache_clear,
+            estimate_voice_cost,
             preview_voice,
             pregenerate_basic_voices,
             install_hooks,
             upload_sound,
+            normalize_sound_library,
             get_recent_projects,
             open_log_file,
             list_custom_sounds,
@@ -0,0 +1,596 @@
+// Config schema versioning and migrations for ~/.claude/audio-notifier.yaml
+//
+// Each migration takes the raw YAML document at version N and returns the
+// document at version N + 1. Migrations run in order until the document
+// reaches CURRENT_SCHEMA_VERSION, and only then is it deserialized into Config.
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use crate::events::HookEvent;
+use crate::secrets::{self, SecretRef};
+use crate::Config;
+
+/// Version written to `schema_version` by this build.
+pub const CURRENT_SCHEMA_VERSION: u32 = 3;
+
+/// Version 0 is the legacy `sound:`/`notifications:`/`logging:` layout from
+/// config/audio-notifier.yaml.example, used before the config editor existed.
+const LEGACY_SCHEMA_VERSION: u64 = 0;
+
+type Migration = fn(Value, &MigrationContext) -> Result<Value, String>;
+
+/// Ordered migration chain. Entry `i` upgrades a document from version `i`.
+const MIGRATIONS: &[Migration] = &[
+    migrate_v0_legacy_layout,
+    migrate_v1_add_hook_events,
+    migrate_v2_move_api_key,
+];
+
+/// Inputs that live outside the YAML document but are folded in during migration.
+#[derive(Debug, Default)]
+pub struct MigrationContext {
+    /// Contents of ~/.claude/project-sounds.conf, if present
+    pub project_sounds_conf: Option<String>,
+    pub home: String,
+    /// Plaintext secrets taken out of the document, for the caller to put in
+    /// the secret store before saving the migrated config
+    pub moved_secrets: RefCell<Vec<MovedSecret>>,
+}
+
+pub struct MovedSecret {
+    pub name: String,
+    pub value: String,
+}
+
+impl std::fmt::Debug for MovedSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MovedSecret").field("name", &self.name).finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// Document doesn't look like any known config layout
+    Unrecognized,
+    /// Document was written by a newer version of the app
+    UnsupportedVersion(u64),
+    /// A migration step rejected the document
+    StepFailed { from_version: u32, message: String },
+}
+
+impl std::fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationError::Unrecognized => write!(f, "document does not match any known config layout"),
+            MigrationError::UnsupportedVersion(version) => write!(
+                f,
+                "schema version {} is newer than this app supports ({})",
+                version, CURRENT_SCHEMA_VERSION
+            ),
+            MigrationError::StepFailed { from_version, message } => {
+                write!(f, "migration from schema version {} failed: {}", from_version, message)
+            }
+        }
+    }
+}
+
+/// Determine the schema version of a raw config document.
+pub fn detect_version(doc: &Value) -> u64 {
+    if let Some(version) = doc.get("schema_version").and_then(|v| v.as_u64()) {
+        return version;
+    }
+
+    // Unversioned documents with global_settings are the first editor format
+    if doc.get("global_settings").is_some() {
+        return 1;
+    }
+
+    LEGACY_SCHEMA_VERSION
+}
+
+/// Run every migration needed to bring `doc` up to CURRENT_SCHEMA_VERSION.
+/// Returns the migrated document and the version it started at.
+pub fn migrate(mut doc: Value, ctx: &MigrationContext) -> Result<(Value, u32), MigrationError> {
+    if !is_config_document(&doc) {
+        return Err(MigrationError::Unrecognized);
+    }
+
+    let detected = detect_version(&doc);
+    let original_version = u32::try_from(detected)
+        .ok()
+        .filter(|version| *version <= CURRENT_SCHEMA_VERSION)
+        .ok_or(MigrationError::UnsupportedVersion(detected))?;
+
+    for version in original_version..CURRENT_SCHEMA_VERSION {
+        let step = MIGRATIONS[version as usize];
+        doc = step(doc, ctx).map_err(|message| MigrationError::StepFailed {
+            from_version: version,
+            message,
+        })?;
+        set_version(&mut doc, version + 1);
+    }
+
+    Ok((doc, original_version))
+}
+
+fn set_version(doc: &mut Value, version: u32) {
+    if let Value::Mapping(map) = doc {
+        map.insert(Value::from("schema_version"), Value::from(version));
+    }
+}
+
+// ===== v0 -> v1: legacy sound/notifications/logging layout =====
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct LegacyConfig {
+    #[serde(default)]
+    sound: LegacySound,
+    #[serde(default)]
+    notifications: LegacyNotifications,
+    #[serde(default)]
+    logging: LegacyLogging,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct LegacySound {
+    enabled: Option<bool>,
+    file: Option<String>,
+    #[serde(default)]
+    available_sounds: Vec<String>,
+    #[serde(default)]
+    project_sounds: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    event_sounds: Option<BTreeMap<String, String>>,
+    min_interval: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct LegacyNotifications {
+    audio: Option<LegacyToggle>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct LegacyToggle {
+    enabled: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct LegacyLogging {
+    debug: Option<bool>,
+}
+
+/// The first editor format, which the v1 -> v2 step expects: the original
+/// five hook events, and the API key as a plain string.
+#[derive(Debug, Serialize)]
+struct V1Config {
+    global_mode: bool,
+    global_settings: V1GlobalSettings,
+    projects: Vec<V1Project>,
+    sound_library: Vec<String>,
+    min_interval: u32,
+    debug: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct V1GlobalSettings {
+    enabled: bool,
+    event_sounds: V1Events<String>,
+    event_enabled: V1Events<bool>,
+    voice_enabled: V1Events<bool>,
+    voice_template: String,
+    voice_provider: String,
+    voice_id: Option<String>,
+    fish_audio_api_key: Option<String>,
+    respect_do_not_disturb: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct V1Project {
+    path: String,
+    display_name: Option<String>,
+    enabled: bool,
+    event_sounds: V1Events<String>,
+    event_enabled: V1Events<bool>,
+    voice_enabled: V1Events<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct V1Events<T> {
+    notification: T,
+    stop: T,
+    pre_tool_use: T,
+    post_tool_use: T,
+    subagent_stop: T,
+}
+
+impl<T: Clone> V1Events<T> {
+    fn uniform(value: T) -> Self {
+        V1Events {
+            notification: value.clone(),
+            stop: value.clone(),
+            pre_tool_use: value.clone(),
+            post_tool_use: value.clone(),
+            subagent_stop: value,
+        }
+    }
+
+    fn get_mut(&mut self, key: &str) -> Option<&mut T> {
+        match key {
+            "notification" => Some(&mut self.notification),
+            "stop" => Some(&mut self.stop),
+            "pre_tool_use" => Some(&mut self.pre_tool_use),
+            "post_tool_use" => Some(&mut self.post_tool_use),
+            "subagent_stop" => Some(&mut self.subagent_stop),
+            _ => None,
+        }
+    }
+}
+
+/// Version 1 defaults: tool events off, the rest on.
+fn v1_event_enabled() -> V1Events<bool> {
+    V1Events { notification: true, stop: true, pre_tool_use: false, post_tool_use: false, subagent_stop: true }
+}
+
+fn migrate_v0_legacy_layout(doc: Value, ctx: &MigrationContext) -> Result<Value, String> {
+    // An empty file parses as null; treat it like an empty legacy document
+    let legacy: LegacyConfig = if doc.is_null() {
+        LegacyConfig::default()
+    } else {
+        serde_yaml::from_value(doc)
+            .map_err(|e| format!("Not a recognised legacy config: {}", e))?
+    };
+
+    let expand = |path: &str| expand_home(path, &ctx.home);
+
+    let audio_enabled = legacy.notifications.audio.and_then(|a| a.enabled).unwrap_or(true);
+
+    // The legacy default sound applies to every event unless overridden below
+    let mut event_sounds =
+        V1Events::uniform(legacy.sound.file.as_deref().map(expand).unwrap_or_else(|| "voice:simple".to_string()));
+    for (event, sound) in legacy.sound.event_sounds.unwrap_or_default() {
+        // Legacy names such as `permission` map onto the registry keys
+        if let Some(slot) = HookEvent::from_key(&event).and_then(|event| event_sounds.get_mut(event.key())) {
+            *slot = expand(&sound);
+        }
+    }
+
+    let sound_library = if legacy.sound.available_sounds.is_empty() {
+        Config::default().sound_library
+    } else {
+        legacy.sound.available_sounds.iter().map(|s| expand(s)).collect()
+    };
+
+    // project_sounds in the YAML wins over project-sounds.conf for the same name
+    let mut project_sounds = ctx
+        .project_sounds_conf
+        .as_deref()
+        .map(parse_project_sounds_conf)
+        .unwrap_or_default();
+    project_sounds.extend(legacy.sound.project_sounds.unwrap_or_default());
+
+    // Legacy mappings matched on the directory name, which is what we keep as the path
+    let projects = project_sounds
+        .into_iter()
+        .map(|(name, sound)| V1Project {
+            path: name.clone(),
+            display_name: Some(name),
+            enabled: true,
+            event_sounds: V1Events::uniform(expand(&sound)),
+            event_enabled: v1_event_enabled(),
+            voice_enabled: v1_event_enabled(),
+        })
+        .collect();
+
+    let config = V1Config {
+        global_mode: true,
+        global_settings: V1GlobalSettings {
+            enabled: legacy.sound.enabled.unwrap_or(true) && audio_enabled,
+            event_sounds,
+            event_enabled: v1_event_enabled(),
+            voice_enabled: v1_event_enabled(),
+            voice_template: "{event} event".to_string(),
+            voice_provider: "fish_audio".to_string(),
+            voice_id: None,
+            fish_audio_api_key: None,
+            respect_do_not_disturb: false,
+        },
+        projects,
+        sound_library,
+        min_interval: legacy.sound.min_interval.unwrap_or(2),
+        debug: legacy.logging.debug.unwrap_or(false),
+    };
+
+    to_value(&config)
+}
+
+/// Parse `project_name=sound_file_path` lines from project-sounds.conf.
+fn parse_project_sounds_conf(contents: &str) -> BTreeMap<String, String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(name, sound)| (name.trim().to_string(), sound.trim().to_string()))
+        .filter(|(name, sound)| !name.is_empty() && !sound.is_empty())
+        .collect()
+}
+
+/// Version 1 only knew the original five hook events. Fill in the events added
+/// in version 2 with that version's defaults, so the hook scripts (which treat
+/// a missing key as enabled) see them as off.
+fn migrate_v1_add_hook_events(mut doc: Value, _ctx: &MigrationContext) -> Result<Value, String> {
+    let fill_section = |section: &mut Value| {
+        for field in ["event_sounds", "event_enabled", "voice_enabled"] {
+            let Some(Value::Mapping(map)) = section.get_mut(field) else { continue };
+            for &(event, enabled) in V2_EVENTS {
+                let default = match field {
+                    "event_sounds" => Value::from("voice:simple"),
+                    _ => Value::from(enabled),
+                };
+                map.entry(Value::from(event)).or_insert(default);
+            }
+        }
+    };
+
+    if let Some(settings) = doc.get_mut("global_settings") {
+        fill_section(settings);
+    }
+    if let Some(Value::Sequence(projects)) = doc.get_mut("projects") {
+        projects.iter_mut().for_each(fill_section);
+    }
+
+    Ok(doc)
+}
+
+/// The events version 2 knew and whether each was on by default. Kept as it
+/// was, whatever the registry says now; later events need their own step.
+const V2_EVENTS: &[(&str, bool)] = &[
+    ("notification", true),
+    ("stop", true),
+    ("pre_tool_use", false),
+    ("post_tool_use", false),
+    ("subagent_stop", true),
+    ("user_prompt_submit", false),
+    ("session_start", false),
+    ("session_end", false),
+    ("pre_compact", false),
+];
+
+/// Version 2 stored the Fish Audio API key in plaintext. Replace it with a
+/// reference to the credentials file and hand the key to the caller.
+fn migrate_v2_move_api_key(mut doc: Value, ctx: &MigrationContext) -> Result<Value, String> {
+    let Some(Value::Mapping(settings)) = doc.get_mut("global_settings") else {
+        return Ok(doc);
+    };
+
+    match settings.remove(secrets::FISH_AUDIO_API_KEY) {
+        Some(Value::String(key)) if !key.trim().is_empty() => {
+            ctx.moved_secrets.borrow_mut().push(MovedSecret {
+                name: secrets::FISH_AUDIO_API_KEY.to_string(),
+                value: key.trim().to_string(),
+            });
+            let reference = SecretRef::default_for(secrets::FISH_AUDIO_API_KEY);
+            settings.insert(secrets::FISH_AUDIO_API_KEY.into(), to_value(&reference)?);
+        }
+        // Already a reference (hand-edited), or nothing worth keeping
+        Some(other @ Value::Mapping(_)) => {
+            settings.insert(secrets::FISH_AUDIO_API_KEY.into(), other);
+        }
+        _ => {}
+    }
+
+    Ok(doc)
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<Value, String> {
+    serde_yaml::to_value(value).map_err(|e| format!("Failed to serialize migrated value: {}", e))
+}
+
+fn expand_home(path: &str, home: &str) -> String {
+    match path.strip_prefix("~/") {
+        Some(rest) if !home.is_empty() => format!("{}/{}", home, rest),
+        _ => path.to_string(),
+    }
+}
+
+/// True if the document looks like something we know how to migrate.
+fn is_config_document(doc: &Value) -> bool {
+    match doc {
+        Value::Null => true,
+        Value::Mapping(map) => map_has_known_key(map),
+        _ => false,
+    }
+}
+
+fn map_has_known_key(map: &Mapping) -> bool {
+    ["schema_version", "global_settings", "sound", "notifications", "logging"]
+        .iter()
+        .any(|key| map.contains_key(Value::from(*key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::HookEvent;
+
+    fn fixture(name: &str) -> Value {
+        let path = format!("{}/tests/fixtures/config/{}", env!("CARGO_MANIFEST_DIR"), name);
+        serde_yaml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap()
+    }
+
+    fn context() -> MigrationContext {
+        MigrationContext { home: "/home/me".to_string(), ..Default::default() }
+    }
+
+    fn migrate_fixture(name: &str, ctx: &MigrationContext) -> (Config, u32) {
+        let (doc, from) = migrate(fixture(name), ctx).unwrap();
+        (serde_yaml::from_value(doc).unwrap(), from)
+    }
+
+    #[test]
+    fn v0_step_emits_the_v1_layout() {
+        let doc = migrate_v0_legacy_layout(fixture("v0-legacy.yaml"), &context()).unwrap();
+
+        assert_eq!(detect_version(&doc), 1);
+        let settings = doc.get("global_settings").unwrap().as_mapping().unwrap();
+        let keys: Vec<&str> = settings.keys().filter_map(Value::as_str).collect();
+        assert_eq!(
+            keys,
+            [
+                "enabled",
+                "event_sounds",
+                "event_enabled",
+                "voice_enabled",
+                "voice_template",
+                "voice_provider",
+                "voice_id",
+                "fish_audio_api_key",
+                "respect_do_not_disturb",
+            ]
+        );
+        assert_eq!(settings.get("event_sounds").unwrap().as_mapping().unwrap().len(), 5);
+        assert!(doc.get("schema_version").is_none());
+    }
+
+    #[test]
+    fn migrates_v0_legacy_layout() {
+        let ctx = MigrationContext {
+            project_sounds_conf: Some("# name=sound\nblog=~/Music/blog.wav\nmy-portfolio=/ignored.wav\n".to_string()),
+            ..context()
+        };
+        let (config, from) = migrate_fixture("v0-legacy.yaml", &ctx);
+
+        assert_eq!(from, 0);
+        assert_eq!(config.schema_version, CURRENT_SCHEMA_VERSION);
+        let sounds = &config.global_settings.event_sounds;
+        assert_eq!(sounds.get(HookEvent::Notification).unwrap(), "/System/Library/Sounds/Ping.aiff");
+        assert_eq!(sounds.get(HookEvent::Stop).unwrap(), "/System/Library/Sounds/Glass.aiff");
+        assert_eq!(sounds.get(HookEvent::PreToolUse).unwrap(), "/System/Library/Sounds/Submarine.aiff");
+        // Events added after v1 come from the v1 -> v2 step
+        assert_eq!(sounds.get(HookEvent::SessionStart).unwrap(), "voice:simple");
+        assert_eq!(config.sound_library[1], "/home/me/Music/alert-sound.wav");
+        assert_eq!(config.min_interval, 5);
+        assert!(config.debug);
+
+        let projects: Vec<(&str, &str)> = config
+            .projects
+            .iter()
+            .map(|p| (p.path.as_str(), p.event_sounds.get(HookEvent::Stop).unwrap().as_str()))
+            .collect();
+        assert_eq!(projects, [("blog", "/home/me/Music/blog.wav"), ("my-portfolio", "/home/me/Music/portfolio.wav")]);
+    }
+
+    #[test]
+    fn migrates_empty_file_to_defaults() {
+        let (doc, from) = migrate(Value::Null, &context()).unwrap();
+        let config: Config = serde_yaml::from_value(doc).unwrap();
+
+        assert_eq!(from, 0);
+        assert!(config.global_settings.enabled);
+        assert_eq!(config.sound_library, Config::default().sound_library);
+        assert!(config.projects.is_empty());
+    }
+
+    #[test]
+    fn migrates_v1_editor_format() {
+        let ctx = context();
+        let (config, from) = migrate_fixture("v1-editor.yaml", &ctx);
+
+        assert_eq!(from, 1);
+        let settings = &config.global_settings;
+        assert_eq!(settings.event_sounds.get(HookEvent::Stop).unwrap(), "/System/Library/Sounds/Hero.aiff");
+        assert_eq!(settings.voice_enabled.get(HookEvent::Stop), Some(&false));
+        assert_eq!(settings.event_enabled.get(HookEvent::UserPromptSubmit), Some(&false));
+        assert_eq!(settings.event_sounds.get(HookEvent::PreCompact).unwrap(), "voice:simple");
+        assert!(settings.respect_do_not_disturb);
+        assert_eq!(
+            config.projects[0].event_sounds.get(HookEvent::SessionEnd).unwrap(),
+            "voice:simple"
+        );
+
+        assert_eq!(settings.fish_audio_api_key, Some(SecretRef::default_for(secrets::FISH_AUDIO_API_KEY)));
+        let moved = ctx.moved_secrets.take();
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].value, "v1-test-key");
+    }
+
+    #[test]
+    fn v1_gains_the_events_version_2_knew() {
+        let doc: Value = serde_yaml::from_str(
+            "global_settings:\n  event_sounds: { stop: /a.wav }\n  event_enabled: { stop: false }\n\
+             projects:\n  - path: /src\n    voice_enabled: {}\n",
+        )
+        .unwrap();
+        let doc = migrate_v1_add_hook_events(doc, &context()).unwrap();
+
+        let settings = &doc["global_settings"];
+        let sounds = settings["event_sounds"].as_mapping().unwrap();
+        assert_eq!(sounds.len(), V2_EVENTS.len());
+        assert_eq!(sounds["stop"], "/a.wav");
+        assert_eq!(sounds["pre_compact"], "voice:simple");
+        let enabled: Vec<(&str, bool)> = settings["event_enabled"]
+            .as_mapping()
+            .unwrap()
+            .iter()
+            .map(|(key, value)| (key.as_str().unwrap(), value.as_bool().unwrap()))
+            .collect();
+        assert_eq!(
+            enabled,
+            [
+                ("stop", false),
+                ("notification", true),
+                ("pre_tool_use", false),
+                ("post_tool_use", false),
+                ("subagent_stop", true),
+                ("user_prompt_submit", false),
+                ("session_start", false),
+                ("session_end", false),
+                ("pre_compact", false),
+            ]
+        );
+        assert_eq!(doc["projects"][0]["voice_enabled"]["subagent_stop"], true);
+        assert!(settings.get("voice_enabled").is_none());
+    }
+
+    #[test]
+    fn migrates_v2_plaintext_key() {
+        let ctx = context();
+        let (config, from) = migrate_fixture("v2.yaml", &ctx);
+
+        assert_eq!(from, 2);
+        assert_eq!(config.global_settings.event_enabled.get(HookEvent::UserPromptSubmit), Some(&true));
+        assert_eq!(
+            config.global_settings.fish_audio_api_key,
+            Some(SecretRef::default_for(secrets::FISH_AUDIO_API_KEY))
+        );
+        assert_eq!(ctx.moved_secrets.take()[0].value, "v2-test-key");
+    }
+
+    #[test]
+    fn keeps_current_documents() {
+        let doc = serde_yaml::to_value(Config::default()).unwrap();
+        let (migrated, from) = migrate(doc.clone(), &context()).unwrap();
+
+        assert_eq!(from, CURRENT_SCHEMA_VERSION);
+        assert_eq!(migrated, doc);
+    }
+
+    #[test]
+    fn rejects_unknown_and_newer_documents() {
+        let parse = |yaml: &str| serde_yaml::from_str::<Value>(yaml).unwrap();
+
+        assert!(matches!(migrate(parse("foo: 1"), &context()), Err(MigrationError::Unrecognized)));
+        assert!(matches!(
+            migrate(parse("schema_version: 9"), &context()),
+            Err(MigrationError::UnsupportedVersion(9))
+        ));
+        // Would wrap to 1 if truncated to u32
+        assert!(matches!(
+            migrate(parse("schema_version: 4294967297"), &context()),
+            Err(MigrationError::UnsupportedVersion(4294967297))
+        ));
+    }
+}
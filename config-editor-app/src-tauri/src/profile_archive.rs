@@ -0,0 +1,359 @@
+// Export/import of a complete notifier profile as a single .zip archive
+//
+// Archive layout:
+//   manifest.json         ArchiveManifest (format version, source HOME, ...)
+//   audio-notifier.yaml   the config, without the Fish Audio API key
+//   sounds/<file>         custom sounds from ~/.claude/sounds/
+//   voices/<path>         generated voices from ~/.claude/voices/
+//
+// On import, absolute paths under the exporting machine's HOME are rewritten
+// to the importing machine's HOME, and project voice directories (named after
+// a hash of the project path) are renamed to match the rewritten paths.
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::migration::{self, MigrationContext, CURRENT_SCHEMA_VERSION};
+use crate::{atomic_write, hash_string, Config};
+
+/// Bumped when the archive layout changes incompatibly.
+const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_NAME: &str = "manifest.json";
+const CONFIG_NAME: &str = "audio-notifier.yaml";
+const SOUNDS_PREFIX: &str = "sounds";
+const VOICES_PREFIX: &str = "voices";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchiveManifest {
+    format_version: u32,
+    schema_version: u32,
+    exported_at: String,
+    /// HOME on the exporting machine, used to rewrite paths on import
+    source_home: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportSummary {
+    pub path: String,
+    pub sounds: usize,
+    pub voices: usize,
+}
+
+/// What to do when an archived file already exists with different contents.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionPolicy {
+    #[default]
+    Skip,
+    Replace,
+}
+
+/// Files touched by an import, as archive paths (e.g. "sounds/ding.mp3").
+#[derive(Debug, Serialize, Default)]
+pub struct ImportReport {
+    pub added: Vec<String>,
+    /// Already present, either identical or kept because of CollisionPolicy::Skip
+    pub skipped: Vec<String>,
+    pub replaced: Vec<String>,
+    /// Backup of the config that the import replaced, if there was one
+    pub config_backup_path: Option<String>,
+}
+
+pub struct ImportedProfile {
+    pub config: Config,
+    pub report: ImportReport,
+    /// Sounds and voices to write once the config is saved
+    files: Vec<(PathBuf, Vec<u8>)>,
+}
+
+impl ImportedProfile {
+    /// Save the config with `save`, then write the archive's sounds and
+    /// voices, so an import whose config can't be saved changes nothing.
+    pub fn apply<T>(&self, save: impl FnOnce(&Config) -> Result<T, String>) -> Result<T, String> {
+        let saved = save(&self.config)?;
+        for (path, bytes) in &self.files {
+            write_file(path, bytes)?;
+        }
+        Ok(saved)
+    }
+}
+
+/// Write `config` plus everything in `sounds_dir` and `voices_dir` to `dest`.
+pub fn export(
+    dest: &Path,
+    config: &Config,
+    sounds_dir: &Path,
+    voices_dir: &Path,
+    home: &str,
+) -> Result<ExportSummary, String> {
+    // API keys are personal; the importer keeps their own
+    let mut config = config.clone();
+    config.global_settings.clear_api_keys();
+
+    let manifest = ArchiveManifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        schema_version: CURRENT_SCHEMA_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        source_home: home.to_string(),
+    };
+
+    let mut sounds = Vec::new();
+    collect_files(sounds_dir, SOUNDS_PREFIX, &mut sounds)?;
+    let mut voices = Vec::new();
+    collect_files(voices_dir, VOICES_PREFIX, &mut voices)?;
+
+    let file = File::create(dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let manifest_json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    let config_yaml = serde_yaml::to_string(&config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+    let mut add = |name: &str, bytes: &[u8]| -> Result<(), String> {
+        zip.start_file(name, options)
+            .and_then(|_| zip.write_all(bytes).map_err(Into::into))
+            .map_err(|e| format!("Failed to add {} to archive: {}", name, e))
+    };
+
+    add(MANIFEST_NAME, &manifest_json)?;
+    add(CONFIG_NAME, config_yaml.as_bytes())?;
+    for (name, path) in sounds.iter().chain(voices.iter()) {
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        add(name, &bytes)?;
+    }
+
+    zip.finish().map_err(|e| format!("Failed to finish archive: {}", e))?;
+
+    Ok(ExportSummary {
+        path: dest.to_string_lossy().to_string(),
+        sounds: sounds.len(),
+        voices: voices.len(),
+    })
+}
+
+/// Read `archive` and plan where its sounds and voices go in
+/// `sounds_dir`/`voices_dir`; its config is rewritten for `home`. Nothing is
+/// written until `ImportedProfile::apply`.
+pub fn import(
+    archive: &Path,
+    sounds_dir: &Path,
+    voices_dir: &Path,
+    home: &str,
+    policy: CollisionPolicy,
+) -> Result<ImportedProfile, String> {
+    let file = File::open(archive).map_err(|e| format!("Failed to open {}: {}", archive.display(), e))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("Not a profile archive: {}", e))?;
+
+    let manifest: ArchiveManifest = serde_json::from_slice(&read_entry(&mut zip, MANIFEST_NAME)?)
+        .map_err(|e| format!("Invalid archive manifest: {}", e))?;
+    if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(format!(
+            "Archive format {} is newer than this app supports ({})",
+            manifest.format_version, ARCHIVE_FORMAT_VERSION
+        ));
+    }
+
+    let config_bytes = read_entry(&mut zip, CONFIG_NAME)?;
+    let mut doc: Value = serde_yaml::from_slice(&config_bytes)
+        .map_err(|e| format!("Archived config is not valid YAML: {}", e))?;
+    let old_project_paths = project_paths(&doc);
+
+    rewrite_home(&mut doc, &manifest.source_home, home);
+
+    let ctx = MigrationContext {
+        home: home.to_string(),
+        ..Default::default()
+    };
+    let (doc, _) = migration::migrate(doc, &ctx)
+        .map_err(|e| format!("Archived config can't be migrated: {}", e))?;
+    let config: Config = serde_yaml::from_value(doc)
+        .map_err(|e| format!("Archived config is invalid: {}", e))?;
+
+    // Project voices live under voices/projects/<hash of project path>
+    let project_dir_renames: HashMap<String, String> = old_project_paths
+        .iter()
+        .zip(config.projects.iter())
+        .map(|(old, project)| (hash_string(old), hash_string(&project.path)))
+        .filter(|(old, new)| old != new)
+        .collect();
+
+    let mut report = ImportReport::default();
+    let mut files = Vec::new();
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).map_err(|e| format!("Failed to read archive: {}", e))?;
+        if entry.is_dir() {
+            continue;
+        }
+        let Some(name) = entry.enclosed_name() else {
+            return Err(format!("Archive entry {} has an unsafe path", entry.name()));
+        };
+        let parts: Vec<String> = name
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect();
+
+        if parts.len() < 2 {
+            continue;
+        }
+        let target = match parts[0].as_str() {
+            SOUNDS_PREFIX => sounds_dir.join(parts[1..].iter().collect::<PathBuf>()),
+            VOICES_PREFIX => {
+                let mut rest = parts[1..].to_vec();
+                if rest.len() > 2 && rest[0] == "projects" {
+                    if let Some(renamed) = project_dir_renames.get(&rest[1]) {
+                        rest[1] = renamed.clone();
+                    }
+                }
+                voices_dir.join(rest.iter().collect::<PathBuf>())
+            }
+            _ => continue,
+        };
+
+        let mut bytes = Vec::new();
+        entry
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to extract {}: {}", name.display(), e))?;
+
+        let label = parts.join("/");
+        let existing = fs::read(&target).ok();
+        match existing {
+            Some(current) if current == bytes => report.skipped.push(label),
+            Some(_) if policy == CollisionPolicy::Skip => report.skipped.push(label),
+            Some(_) => {
+                files.push((target, bytes));
+                report.replaced.push(label);
+            }
+            None => {
+                files.push((target, bytes));
+                report.added.push(label);
+            }
+        }
+    }
+
+    Ok(ImportedProfile { config, report, files })
+}
+
+fn read_entry(zip: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>, String> {
+    let mut entry = zip
+        .by_name(name)
+        .map_err(|_| format!("Archive has no {}", name))?;
+    let mut bytes = Vec::new();
+    entry
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read {} from archive: {}", name, e))?;
+    Ok(bytes)
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    atomic_write::write_atomic(path, bytes).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Collect regular files under `dir` as (archive name, path) pairs, skipping
+/// hidden files such as in-progress temp files.
+fn collect_files(dir: &Path, prefix: &str, out: &mut Vec<(String, PathBuf)>) -> Result<(), String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if file_name.starts_with('.') {
+            continue;
+        }
+        let name = format!("{}/{}", prefix, file_name);
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, &name, out)?;
+        } else if path.is_file() {
+            out.push((name, path));
+        }
+    }
+    Ok(())
+}
+
+fn project_paths(doc: &Value) -> Vec<String> {
+    doc.get("projects")
+        .and_then(Value::as_sequence)
+        .map(|projects| {
+            projects
+                .iter()
+                .map(|p| p.get("path").and_then(Value::as_str).unwrap_or_default().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Replace a leading `from_home` with `to_home` in every string in `doc`.
+fn rewrite_home(doc: &mut Value, from_home: &str, to_home: &str) {
+    if from_home.is_empty() || from_home == to_home {
+        return;
+    }
+    match doc {
+        Value::String(s) => {
+            let rest = s.strip_prefix(from_home).filter(|rest| rest.is_empty() || rest.starts_with('/'));
+            if let Some(rest) = rest {
+                *s = format!("{}{}", to_home, rest);
+            }
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(|v| rewrite_home(v, from_home, to_home)),
+        Value::Mapping(map) => map.iter_mut().for_each(|(_, v)| rewrite_home(v, from_home, to_home)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::HookEvent;
+
+    #[test]
+    fn a_refused_save_leaves_sounds_and_voices_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let (sounds, voices) = (dir.path().join("a/sounds"), dir.path().join("a/voices"));
+        fs::create_dir_all(&sounds).unwrap();
+        fs::create_dir_all(&voices).unwrap();
+        fs::write(sounds.join("ding.wav"), b"archived").unwrap();
+        fs::write(voices.join("stop.mp3"), b"voice").unwrap();
+        let mut config = Config::default();
+        config.global_settings.event_sounds.set(HookEvent::Stop, "/home/alice/.claude/sounds/ding.wav".to_string());
+        let archive = dir.path().join("profile.zip");
+        export(&archive, &config, &sounds, &voices, "/home/alice").unwrap();
+
+        let (sounds, voices) = (dir.path().join("b/sounds"), dir.path().join("b/voices"));
+        fs::create_dir_all(&sounds).unwrap();
+        fs::write(sounds.join("ding.wav"), b"mine").unwrap();
+        let imported = import(&archive, &sounds, &voices, "/home/bob", CollisionPolicy::Replace).unwrap();
+        assert_eq!(imported.report.replaced, vec!["sounds/ding.wav"]);
+        assert_eq!(imported.report.added, vec!["voices/stop.mp3"]);
+        assert!(fs::read(sounds.join("ding.wav")).unwrap() == b"mine" && !voices.exists());
+
+        // The config changed on disk since the editor loaded it
+        let error = imported.apply(|_| Err::<(), _>("changed on disk since it was loaded".to_string())).unwrap_err();
+        assert!(error.contains("changed on disk"));
+        assert!(fs::read(sounds.join("ding.wav")).unwrap() == b"mine" && !voices.exists());
+
+        let mut saved = None;
+        imported
+            .apply(|config| {
+                saved = config.global_settings.event_sounds.get(HookEvent::Stop).cloned();
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(saved.as_deref(), Some("/home/bob/.claude/sounds/ding.wav"));
+        assert!(fs::read(sounds.join("ding.wav")).unwrap() == b"archived" && voices.join("stop.mp3").is_file());
+    }
+}
@@ -0,0 +1,211 @@
+// Named configuration profiles, e.g. "office", "home", "pairing"
+//
+// A profile is a complete GlobalSettings plus project overrides, stored in
+// ~/.claude/audio-notifier-profiles.yaml. The hook scripts only read
+// audio-notifier.yaml, so activating a profile copies it into the live config.
+// Before switching, the live config is saved back into the profile that was
+// active, so edits made since the last switch are kept.
+//
+// The Fish Audio API key is not part of a profile and survives switching.
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::migration::{self, MigrationContext, CURRENT_SCHEMA_VERSION};
+use crate::{atomic_write, Config, GlobalSettings, ProjectConfig};
+
+const MAX_NAME_LEN: usize = 64;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Profile {
+    pub global_settings: GlobalSettings,
+    #[serde(default)]
+    pub projects: Vec<ProjectConfig>,
+}
+
+impl Profile {
+    fn from_config(config: &Config) -> Self {
+        let mut global_settings = config.global_settings.clone();
+        global_settings.clear_api_keys();
+        Profile {
+            global_settings,
+            projects: config.projects.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileStore {
+    pub schema_version: u32,
+    #[serde(default)]
+    pub active: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Default for ProfileStore {
+    fn default() -> Self {
+        ProfileStore {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            active: None,
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileSummary {
+    pub names: Vec<String>,
+    pub active: Option<String>,
+}
+
+impl ProfileStore {
+    pub fn summary(&self) -> ProfileSummary {
+        ProfileSummary {
+            names: self.profiles.keys().cloned().collect(),
+            active: self.active.clone(),
+        }
+    }
+
+    /// Save the current settings in `config` as a new profile.
+    pub fn create(&mut self, name: &str, config: &Config) -> Result<(), String> {
+        let name = self.new_name(name)?;
+        self.profiles.insert(name, Profile::from_config(config));
+        Ok(())
+    }
+
+    pub fn clone_profile(&mut self, source: &str, name: &str) -> Result<(), String> {
+        let profile = self.get(source)?.clone();
+        let name = self.new_name(name)?;
+        self.profiles.insert(name, profile);
+        Ok(())
+    }
+
+    pub fn delete(&mut self, name: &str) -> Result<(), String> {
+        self.get(name)?;
+        if self.active.as_deref() == Some(name) {
+            return Err(format!("Profile \"{}\" is active; activate another profile first", name));
+        }
+        self.profiles.remove(name);
+        Ok(())
+    }
+
+    /// Switch `config` to the profile `name`, first saving the live settings
+    /// into the profile that was active.
+    pub fn activate(&mut self, name: &str, config: &mut Config) -> Result<(), String> {
+        self.get(name)?;
+
+        if let Some(active) = self.active.clone() {
+            if let Some(profile) = self.profiles.get_mut(&active) {
+                *profile = Profile::from_config(config);
+            }
+        }
+
+        self.apply(name, config)?;
+        self.active = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Replace the settings in `config` with profile `name` without switching
+    /// the active profile.
+    pub fn apply(&self, name: &str, config: &mut Config) -> Result<(), String> {
+        let target = self.get(name)?.clone();
+        let current = std::mem::replace(&mut config.global_settings, target.global_settings);
+        config.global_settings.keep_api_keys(&current);
+        config.projects = target.projects;
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<&Profile, String> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| format!("No profile named \"{}\"", name))
+    }
+
+    fn new_name(&self, name: &str) -> Result<String, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Profile name can't be empty".to_string());
+        }
+        if name.chars().count() > MAX_NAME_LEN || name.chars().any(char::is_control) {
+            return Err(format!(
+                "Profile names must be at most {} printable characters",
+                MAX_NAME_LEN
+            ));
+        }
+        if self.profiles.contains_key(name) {
+            return Err(format!("A profile named \"{}\" already exists", name));
+        }
+        Ok(name.to_string())
+    }
+}
+
+/// Load the profile store and the hash of the file. A missing file is an empty store.
+pub fn load(path: &Path, home: &str) -> Result<(ProfileStore, String), String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok((ProfileStore::default(), atomic_write::content_hash(b"")));
+        }
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let hash = atomic_write::content_hash(contents.as_bytes());
+
+    let mut doc: Value = serde_yaml::from_str(&contents)
+        .map_err(|e| format!("{} is not valid YAML: {}", path.display(), e))?;
+    let version = doc
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .map(|v| v as u32)
+        .unwrap_or(CURRENT_SCHEMA_VERSION);
+
+    // Profiles hold config sections, so they go through the config migrations
+    if let Some(Value::Mapping(profiles)) = doc.get_mut("profiles") {
+        for (name, profile) in profiles.iter_mut() {
+            *profile = migrate_profile(std::mem::take(profile), version, home).map_err(|e| {
+                format!("Profile {:?} can't be migrated: {}", name.as_str().unwrap_or_default(), e)
+            })?;
+        }
+    }
+    if let Value::Mapping(map) = &mut doc {
+        map.insert("schema_version".into(), CURRENT_SCHEMA_VERSION.into());
+    }
+
+    let store = serde_yaml::from_value(doc)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    Ok((store, hash))
+}
+
+fn migrate_profile(profile: Value, version: u32, home: &str) -> Result<Value, String> {
+    let Value::Mapping(mut doc) = profile else {
+        return Err("expected a mapping".to_string());
+    };
+    doc.insert("schema_version".into(), version.into());
+
+    let ctx = MigrationContext {
+        home: home.to_string(),
+        ..Default::default()
+    };
+    let (migrated, _) = migration::migrate(Value::Mapping(doc), &ctx).map_err(|e| e.to_string())?;
+
+    let mut profile = Mapping::new();
+    for key in ["global_settings", "projects"] {
+        if let Some(value) = migrated.get(key) {
+            profile.insert(key.into(), value.clone());
+        }
+    }
+    Ok(Value::Mapping(profile))
+}
+
+/// Write the store, refusing if the file changed since `expected_hash` was read.
+pub fn save(path: &Path, store: &ProfileStore, expected_hash: &str) -> Result<(), String> {
+    let yaml = serde_yaml::to_string(store).map_err(|e| format!("Failed to serialize profiles: {}", e))?;
+    atomic_write::write_if_unchanged(path, Some(expected_hash), yaml.as_bytes()).map_err(|e| match e {
+        atomic_write::WriteError::Conflict => format!("{} changed on disk; try again", path.display()),
+        atomic_write::WriteError::Io(e) => format!("Failed to write {}: {}", path.display(), e),
+    })?;
+    Ok(())
+}
@@ -0,0 +1,209 @@
+// Matching working directories against project rules
+//
+// Each ProjectConfig has a `match` kind that says how its `path` is read:
+//
+//   exact   the directory itself. Bare names without a slash (migrated from
+//           project-sounds.conf) match the directory's basename.
+//   prefix  the directory or anything inside it, e.g. a monorepo subfolder
+//   glob    a glob such as ~/src/client-*, matched against the directory and
+//           each of its ancestors, so subfolders of a match match too
+//   regex   a regular expression, matched against the directory and each of
+//           its ancestors like glob
+//
+// When several rules match, the most specific wins: exact before prefix before
+// glob before regex, then the one with more literal characters, then the
+// earlier entry.
+
+use glob::{MatchOptions, Pattern};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::ProjectConfig;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    #[default]
+    Exact,
+    Prefix,
+    Glob,
+    Regex,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RuleMatch {
+    /// Index into `projects`
+    pub index: usize,
+    pub pattern: String,
+    pub kind: MatchKind,
+    /// The directory (or ancestor of it) that the rule matched
+    pub matched_path: String,
+    /// Number of literal characters in the pattern; higher is more specific
+    pub literal_len: usize,
+}
+
+/// Expand a leading `~/` in a rule to `home`.
+pub fn expand_pattern(pattern: &str, home: &Path) -> String {
+    match pattern.strip_prefix("~/") {
+        Some(rest) => home.join(rest).to_string_lossy().to_string(),
+        None => pattern.to_string(),
+    }
+}
+
+/// Check that a rule's pattern compiles. Returns the error message if not.
+pub fn pattern_error(kind: MatchKind, pattern: &str) -> Option<String> {
+    match kind {
+        MatchKind::Exact | MatchKind::Prefix => None,
+        MatchKind::Glob => Pattern::new(pattern).err().map(|e| e.to_string()),
+        MatchKind::Regex => Regex::new(pattern).err().map(|e| e.to_string()),
+    }
+}
+
+/// Every enabled rule that matches `dir`, most specific first.
+pub fn matching_rules(projects: &[ProjectConfig], dir: &Path, home: &Path) -> Vec<RuleMatch> {
+    let mut matches: Vec<RuleMatch> = projects
+        .iter()
+        .enumerate()
+        .filter(|(_, project)| project.enabled)
+        .filter_map(|(index, project)| {
+            let pattern = expand_pattern(&project.path, home);
+            let matched = match_rule(project.match_kind, &pattern, dir)?;
+            Some(RuleMatch {
+                index,
+                pattern: project.path.clone(),
+                kind: project.match_kind,
+                matched_path: matched.to_string_lossy().to_string(),
+                literal_len: literal_len(project.match_kind, &pattern),
+            })
+        })
+        .collect();
+
+    // Stable sort keeps earlier entries first among equally specific rules
+    matches.sort_by(|a, b| a.kind.cmp(&b.kind).then(b.literal_len.cmp(&a.literal_len)));
+    matches
+}
+
+/// The project rule that applies to `dir`, if any.
+pub fn best_match<'a>(projects: &'a [ProjectConfig], dir: &Path, home: &Path) -> Option<&'a ProjectConfig> {
+    matching_rules(projects, dir, home)
+        .first()
+        .map(|m| &projects[m.index])
+}
+
+fn match_rule<'a>(kind: MatchKind, pattern: &str, dir: &'a Path) -> Option<&'a Path> {
+    match kind {
+        MatchKind::Exact => {
+            let matches = if pattern.contains('/') {
+                dir == Path::new(pattern)
+            } else {
+                dir.file_name().is_some_and(|name| name == pattern)
+            };
+            matches.then_some(dir)
+        }
+        MatchKind::Prefix => dir.ancestors().find(|a| *a == Path::new(pattern)),
+        MatchKind::Glob => {
+            let pattern = Pattern::new(pattern).ok()?;
+            let options = MatchOptions {
+                require_literal_separator: true,
+                ..MatchOptions::new()
+            };
+            dir.ancestors().find(|a| pattern.matches_path_with(a, options))
+        }
+        MatchKind::Regex => {
+            let regex = Regex::new(pattern).ok()?;
+            dir.ancestors().find(|a| regex.is_match(&a.to_string_lossy()))
+        }
+    }
+}
+
+fn literal_len(kind: MatchKind, pattern: &str) -> usize {
+    match kind {
+        MatchKind::Exact | MatchKind::Prefix => pattern.len(),
+        MatchKind::Glob => pattern.chars().filter(|c| !"*?[]!".contains(*c)).count(),
+        MatchKind::Regex => pattern.chars().filter(|c| c.is_alphanumeric() || *c == '/').count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{default_event_enabled, default_event_sounds, default_event_voice_enabled};
+
+    fn rule(path: &str, match_kind: MatchKind) -> ProjectConfig {
+        ProjectConfig {
+            path: path.to_string(),
+            match_kind,
+            display_name: None,
+            enabled: true,
+            event_sounds: default_event_sounds(),
+            event_enabled: default_event_enabled(),
+            voice_enabled: default_event_voice_enabled(),
+            event_cooldowns: Default::default(),
+        }
+    }
+
+    fn matched(projects: &[ProjectConfig], dir: &str) -> Vec<usize> {
+        matching_rules(projects, Path::new(dir), Path::new("/home/me")).iter().map(|m| m.index).collect()
+    }
+
+    #[test]
+    fn exact_rules_match_the_directory_or_a_bare_name() {
+        let projects = [rule("/home/me/src/api", MatchKind::Exact), rule("api", MatchKind::Exact)];
+
+        assert_eq!(matched(&projects, "/home/me/src/api"), [0, 1]);
+        assert_eq!(matched(&projects, "/work/api"), [1]);
+        assert!(matched(&projects, "/home/me/src/api/cmd").is_empty());
+    }
+
+    #[test]
+    fn prefix_glob_and_regex_match_subdirectories() {
+        let projects = [
+            rule("~/src/mono", MatchKind::Prefix),
+            rule("~/src/client-*", MatchKind::Glob),
+            rule(r"^/home/me/src/[a-z]+-\d+$", MatchKind::Regex),
+        ];
+
+        assert_eq!(matched(&projects, "/home/me/src/mono/packages/web"), [0]);
+        assert!(matched(&projects, "/home/me/src/monorepo").is_empty());
+        assert_eq!(matched(&projects, "/home/me/src/client-b/lib"), [1]);
+        // * doesn't cross a path separator
+        assert!(matched(&projects, "/home/me/src/client/x").is_empty());
+        assert_eq!(matched(&projects, "/home/me/src/ticket-42/docs"), [2]);
+
+        let found = matching_rules(&projects, Path::new("/home/me/src/client-b/lib"), Path::new("/home/me"));
+        assert_eq!(found[0].matched_path, "/home/me/src/client-b");
+    }
+
+    #[test]
+    fn most_specific_rule_wins() {
+        let projects = [
+            rule(".*/client-.*", MatchKind::Regex),
+            rule("~/src/client-*", MatchKind::Glob),
+            rule("~/src", MatchKind::Prefix),
+            rule("~/src/client-a", MatchKind::Prefix),
+            rule("/home/me/*/client-a", MatchKind::Glob),
+            rule("client-a", MatchKind::Exact),
+        ];
+
+        // Kind first, then literal characters, then position
+        assert_eq!(matched(&projects, "/home/me/src/client-a"), [5, 3, 2, 1, 4, 0]);
+        assert_eq!(
+            best_match(&projects, Path::new("/home/me/src/client-a/pkg"), Path::new("/home/me")).map(|p| &p.path),
+            Some(&"~/src/client-a".to_string())
+        );
+    }
+
+    #[test]
+    fn disabled_and_invalid_rules_never_match() {
+        let mut disabled = rule("~/src", MatchKind::Prefix);
+        disabled.enabled = false;
+        let projects = [disabled, rule("[bad", MatchKind::Glob), rule("(bad", MatchKind::Regex)];
+
+        assert!(matched(&projects, "/home/me/src/[bad").is_empty());
+        assert!(pattern_error(MatchKind::Glob, "[bad").is_some());
+        assert!(pattern_error(MatchKind::Regex, "(bad").is_some());
+        assert!(pattern_error(MatchKind::Exact, "(bad").is_none());
+    }
+}
@@ -0,0 +1,309 @@
+// Quiet hours and weekly schedules (global_settings.schedules)
+//
+// Each rule is a daily time window on some weekdays, in its own time zone.
+// While a window is open, events are suppressed, shown as a banner only, or
+// played at a lower volume. An end time earlier than the start runs past
+// midnight and belongs to the day it starts on, so "fri 22:00-07:00" covers
+// Friday night until Saturday morning. When rules overlap, the most
+// restrictive action wins.
+//
+// Permission prompts can be exempted per rule, since a session waiting on
+// one makes no progress until someone answers.
+
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{EventMap, HookEvent};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Day {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl From<Weekday> for Day {
+    fn from(day: Weekday) -> Self {
+        match day {
+            Weekday::Mon => Day::Mon,
+            Weekday::Tue => Day::Tue,
+            Weekday::Wed => Day::Wed,
+            Weekday::Thu => Day::Thu,
+            Weekday::Fri => Day::Fri,
+            Weekday::Sat => Day::Sat,
+            Weekday::Sun => Day::Sun,
+        }
+    }
+}
+
+/// What happens to an event inside a window, least restrictive first.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum QuietAction {
+    Deliver,
+    LowerVolume,
+    VisualOnly,
+    #[default]
+    Suppress,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct ScheduleRule {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Days the window starts on
+    #[serde(default = "every_day")]
+    pub days: Vec<Day>,
+    /// "HH:MM"
+    pub start: String,
+    /// "HH:MM"; earlier than `start` for windows that run past midnight
+    pub end: String,
+    /// IANA time zone such as "Europe/Berlin"; the system time zone when unset
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Applies to events without an entry in `event_actions`
+    #[serde(default)]
+    pub action: QuietAction,
+    #[serde(default)]
+    pub event_actions: EventMap<QuietAction>,
+    /// Playback volume in percent for lower_volume
+    #[serde(default = "default_quiet_volume")]
+    pub volume: u8,
+    /// Deliver permission prompts normally during the window
+    #[serde(default = "default_true")]
+    pub allow_permission_requests: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn every_day() -> Vec<Day> {
+    vec![Day::Mon, Day::Tue, Day::Wed, Day::Thu, Day::Fri, Day::Sat, Day::Sun]
+}
+
+fn default_quiet_volume() -> u8 {
+    30
+}
+
+/// Outcome of the schedule for one event at one time.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Delivery {
+    pub action: QuietAction,
+    /// Percent, when the action is lower_volume
+    pub volume: Option<u8>,
+    /// Rules whose window was open, by name or `schedules[i]`
+    pub rules: Vec<String>,
+    /// A permission prompt let through by allow_permission_requests
+    pub permission_exception: bool,
+}
+
+impl ScheduleRule {
+    /// Problems with the rule's fields, as (field, message) pairs relative to the rule.
+    pub fn errors(&self) -> Vec<(&'static str, String)> {
+        let mut errors = Vec::new();
+        if let Err(e) = parse_time(&self.start) {
+            errors.push(("start", e));
+        }
+        if let Err(e) = parse_time(&self.end) {
+            errors.push(("end", e));
+        }
+        if let Some(timezone) = &self.timezone {
+            if let Err(e) = parse_timezone(timezone) {
+                errors.push(("timezone", e));
+            }
+        }
+        if self.days.is_empty() {
+            errors.push(("days", "Pick at least one day".to_string()));
+        }
+        if self.volume > 100 {
+            errors.push(("volume", format!("Volume is a percentage, got {}", self.volume)));
+        }
+        errors
+    }
+
+    fn label(&self, index: usize) -> String {
+        if self.name.trim().is_empty() {
+            format!("schedules[{}]", index)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// Whether the window is open at `at`.
+    pub fn is_open(&self, at: DateTime<Utc>) -> Result<bool, String> {
+        let start = parse_time(&self.start)?;
+        let end = parse_time(&self.end)?;
+        let (day, time) = match &self.timezone {
+            Some(timezone) => local_parts(&parse_timezone(timezone)?, at),
+            None => local_parts(&Local, at),
+        };
+        let starts_on = |day: Weekday| self.days.contains(&Day::from(day));
+
+        Ok(if start < end {
+            starts_on(day) && start <= time && time < end
+        } else {
+            // Past midnight (or all day when start == end): the evening part
+            // belongs to today, the early-morning part to yesterday's window
+            (starts_on(day) && time >= start) || (starts_on(day.pred()) && time < end)
+        })
+    }
+}
+
+/// Combine every open window for `event` at `at`.
+pub fn delivery(rules: &[ScheduleRule], event: HookEvent, at: DateTime<Utc>) -> Result<Delivery, String> {
+    let mut result = Delivery {
+        action: QuietAction::Deliver,
+        volume: None,
+        rules: Vec::new(),
+        permission_exception: false,
+    };
+
+    for (i, rule) in rules.iter().enumerate() {
+        if !rule.enabled || !rule.is_open(at).map_err(|e| format!("{}: {}", rule.label(i), e))? {
+            continue;
+        }
+        result.rules.push(rule.label(i));
+
+        let mut action = rule.event_actions.get(event).copied().unwrap_or(rule.action);
+        if event.info().permission_request && rule.allow_permission_requests && action != QuietAction::Deliver {
+            action = QuietAction::Deliver;
+            result.permission_exception = true;
+        }
+        if action == QuietAction::LowerVolume {
+            result.volume = Some(result.volume.map_or(rule.volume, |v| v.min(rule.volume)));
+        }
+        result.action = result.action.max(action);
+    }
+
+    if result.action != QuietAction::LowerVolume {
+        result.volume = None;
+    }
+    // Another rule without the exception can still hold the prompt back
+    result.permission_exception &= result.action == QuietAction::Deliver;
+    Ok(result)
+}
+
+/// Parse the time to check: RFC 3339, or "YYYY-MM-DDTHH:MM" in the system
+/// time zone as sent by a datetime-local input.
+pub fn parse_instant(value: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+        .ok()
+        .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+        .map(|at| at.with_timezone(&Utc))
+        .ok_or_else(|| format!("\"{}\" is not a date and time", value))
+}
+
+fn local_parts<Tz: TimeZone>(tz: &Tz, at: DateTime<Utc>) -> (Weekday, NaiveTime) {
+    let local = at.with_timezone(tz);
+    (local.weekday(), local.time())
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .map_err(|_| format!("\"{}\" is not a time of day (expected HH:MM)", value))
+}
+
+fn parse_timezone(value: &str) -> Result<chrono_tz::Tz, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Unknown time zone \"{}\" (expected a name like Europe/Berlin)", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(yaml: &str) -> ScheduleRule {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn at(value: &str) -> DateTime<Utc> {
+        parse_instant(value).unwrap()
+    }
+
+    #[test]
+    fn overnight_windows_belong_to_the_day_they_start() {
+        // 2026-01-09 is a Friday; Berlin is UTC+1 in January
+        let night = rule("name: night\ndays: [fri]\nstart: '22:00'\nend: '07:00'\ntimezone: Europe/Berlin\n");
+
+        assert!(night.is_open(at("2026-01-09T21:00:00Z")).unwrap()); // fri 22:00
+        assert!(night.is_open(at("2026-01-10T05:59:00Z")).unwrap()); // sat 06:59
+        assert!(!night.is_open(at("2026-01-10T06:00:00Z")).unwrap()); // sat 07:00
+        assert!(!night.is_open(at("2026-01-09T20:59:00Z")).unwrap()); // fri 21:59
+        assert!(!night.is_open(at("2026-01-09T05:00:00Z")).unwrap()); // fri 06:00, thursday's window
+        assert!(!night.is_open(at("2026-01-10T21:30:00Z")).unwrap()); // sat 22:30
+    }
+
+    #[test]
+    fn overnight_windows_wrap_around_the_week() {
+        let sunday = rule("days: [sun]\nstart: '23:00'\nend: '01:00'\ntimezone: UTC\n");
+
+        assert!(sunday.is_open(at("2026-01-11T23:30:00Z")).unwrap()); // sun 23:30
+        assert!(sunday.is_open(at("2026-01-12T00:30:00Z")).unwrap()); // mon 00:30
+        assert!(!sunday.is_open(at("2026-01-12T01:00:00Z")).unwrap()); // mon 01:00
+        assert!(!sunday.is_open(at("2026-01-11T00:30:00Z")).unwrap()); // sun 00:30, saturday's window
+    }
+
+    #[test]
+    fn same_day_and_all_day_windows() {
+        let lunch = rule("days: [mon, tue, wed, thu, fri]\nstart: '12:00'\nend: '13:00'\ntimezone: UTC\n");
+        assert!(lunch.is_open(at("2026-01-09T12:00:00Z")).unwrap());
+        assert!(!lunch.is_open(at("2026-01-09T13:00:00Z")).unwrap());
+        assert!(!lunch.is_open(at("2026-01-10T12:30:00Z")).unwrap()); // saturday
+
+        let all_day = rule("days: [sat]\nstart: '00:00'\nend: '00:00'\ntimezone: UTC\n");
+        assert!(all_day.is_open(at("2026-01-10T00:00:00Z")).unwrap());
+        assert!(all_day.is_open(at("2026-01-10T23:59:00Z")).unwrap());
+        assert!(!all_day.is_open(at("2026-01-11T00:00:00Z")).unwrap());
+    }
+
+    #[test]
+    fn permission_prompts_get_through_unless_another_rule_holds_them() {
+        let night = rule("name: night\nstart: '22:00'\nend: '07:00'\ntimezone: UTC\n");
+        let quieter = rule(
+            "start: '00:00'\nend: '00:00'\naction: lower_volume\nvolume: 20\ntimezone: UTC\n\
+             allow_permission_requests: false\nevent_actions: { stop: visual_only }\n",
+        );
+        let late = at("2026-01-09T23:00:00Z");
+
+        let stop = delivery(std::slice::from_ref(&night), HookEvent::Stop, late).unwrap();
+        assert_eq!((stop.action, stop.rules), (QuietAction::Suppress, vec!["night".to_string()]));
+        let prompt = delivery(std::slice::from_ref(&night), HookEvent::Notification, late).unwrap();
+        assert_eq!((prompt.action, prompt.permission_exception), (QuietAction::Deliver, true));
+
+        let noon = at("2026-01-09T12:00:00Z");
+        let prompt = delivery(std::slice::from_ref(&quieter), HookEvent::Notification, noon).unwrap();
+        assert_eq!((prompt.action, prompt.volume), (QuietAction::LowerVolume, Some(20)));
+        let stop = delivery(std::slice::from_ref(&quieter), HookEvent::Stop, noon).unwrap();
+        assert_eq!((stop.action, stop.volume), (QuietAction::VisualOnly, None));
+
+        let both = delivery(&[night, quieter], HookEvent::Notification, late).unwrap();
+        assert_eq!(both.action, QuietAction::LowerVolume);
+        assert!(!both.permission_exception);
+        assert_eq!(both.rules, vec!["night".to_string(), "schedules[1]".to_string()]);
+    }
+
+    #[test]
+    fn reports_invalid_fields() {
+        let broken = rule("start: '25:00'\nend: '07:00'\ntimezone: Mars/Olympus\ndays: []\nvolume: 150\n");
+        let fields: Vec<&str> = broken.errors().iter().map(|(field, _)| *field).collect();
+
+        assert_eq!(fields, ["start", "timezone", "days", "volume"]);
+        assert!(delivery(&[broken], HookEvent::Stop, Utc::now()).is_err());
+        assert!(parse_instant("2026-01-09T22:30").is_ok());
+        assert!(parse_instant("tomorrow").is_err());
+    }
+}
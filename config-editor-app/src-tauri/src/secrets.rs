@@ -0,0 +1,461 @@
+// Secret storage for provider API keys (fish_audio_api_key, openai_api_key)
+//
+// The config only holds a SecretRef saying where each key lives:
+//
+//   fish_audio_api_key:
+//     backend: file              # ~/.claude/audio-notifier-credentials.json (0600)
+//     name: fish_audio_api_key
+//
+//   fish_audio_api_key:
+//     backend: env               # read-only, e.g. exported from a shell profile
+//     var: FISH_AUDIO_API_KEY
+//
+//   fish_audio_api_key:
+//     backend: secret_service    # freedesktop Secret Service (GNOME Keyring, KWallet)
+//     name: fish_audio_api_key
+//
+// Keys written in plaintext by older versions are moved to the file backend by
+// the schema v2 -> v3 migration.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{atomic_write, get_credentials_path};
+
+/// Name the Fish Audio API key is stored under in the file and Secret Service backends.
+pub const FISH_AUDIO_API_KEY: &str = "fish_audio_api_key";
+
+/// Variable the env backend reads unless another one is chosen.
+pub const FISH_AUDIO_API_KEY_ENV: &str = "FISH_AUDIO_API_KEY";
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum SecretRef {
+    File { name: String },
+    Env { var: String },
+    SecretService { name: String },
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretBackend {
+    File,
+    Env,
+    SecretService,
+}
+
+#[derive(Debug)]
+pub enum SecretError {
+    /// The backend can't be used on this machine (no D-Bus session, unsupported OS, ...)
+    Unavailable(String),
+    /// The backend can't store values, e.g. environment variables
+    ReadOnly(String),
+    /// The keyring is locked and unlocking needs user interaction
+    Locked,
+    Io(String),
+}
+
+impl fmt::Display for SecretError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretError::Unavailable(message) => write!(f, "secret store unavailable: {}", message),
+            SecretError::ReadOnly(message) => write!(f, "{}", message),
+            SecretError::Locked => write!(f, "the keyring is locked; unlock it and try again"),
+            SecretError::Io(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+pub trait SecretStore {
+    fn get(&self, name: &str) -> Result<Option<String>, SecretError>;
+    fn set(&self, name: &str, value: &str) -> Result<(), SecretError>;
+    fn delete(&self, name: &str) -> Result<(), SecretError>;
+}
+
+impl SecretRef {
+    /// Where newly stored and migrated secrets go.
+    pub fn default_for(name: &str) -> SecretRef {
+        SecretRef::File { name: name.to_string() }
+    }
+
+    fn open(&self) -> Result<(Box<dyn SecretStore>, &str), SecretError> {
+        match self {
+            SecretRef::File { name } => Ok((Box::new(FileStore::new(get_credentials_path())), name)),
+            SecretRef::Env { var } => Ok((Box::new(EnvStore), var)),
+            SecretRef::SecretService { name } => Ok((Box::new(SecretServiceStore::connect()?), name)),
+        }
+    }
+
+    pub fn resolve(&self) -> Result<Option<String>, SecretError> {
+        let (store, name) = self.open()?;
+        store.get(name)
+    }
+
+    pub fn store(&self, value: &str) -> Result<(), SecretError> {
+        let (store, name) = self.open()?;
+        store.set(name, value)
+    }
+
+    pub fn delete(&self) -> Result<(), SecretError> {
+        let (store, name) = self.open()?;
+        store.delete(name)
+    }
+}
+
+/// Replace plaintext values of any `*api_key` field in a raw config document,
+/// for output that may be shared (diagnostics). References are kept.
+pub fn redact_plaintext_keys(doc: &mut Value) {
+    match doc {
+        Value::Mapping(map) => {
+            for (key, value) in map.iter_mut() {
+                let is_key_field = key.as_str().is_some_and(|k| k.ends_with("api_key"));
+                if is_key_field && value.is_string() {
+                    *value = Value::from("[REDACTED]");
+                } else {
+                    redact_plaintext_keys(value);
+                }
+            }
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(redact_plaintext_keys),
+        _ => {}
+    }
+}
+
+// ===== File backend =====
+
+/// JSON object of name -> secret in a file only the user can read.
+pub struct FileStore {
+    path: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(path: PathBuf) -> Self {
+        FileStore { path }
+    }
+
+    fn read_all(&self) -> Result<BTreeMap<String, String>, SecretError> {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| SecretError::Io(format!("{} is not valid JSON: {}", self.path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(SecretError::Io(format!("Failed to read {}: {}", self.path.display(), e))),
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut BTreeMap<String, String>)) -> Result<(), SecretError> {
+        let io_err = |e: std::io::Error| SecretError::Io(format!("Failed to write {}: {}", self.path.display(), e));
+        let _lock = atomic_write::lock(&self.path).map_err(io_err)?;
+
+        let mut secrets = self.read_all()?;
+        f(&mut secrets);
+        let json = serde_json::to_vec_pretty(&secrets)
+            .map_err(|e| SecretError::Io(format!("Failed to serialize secrets: {}", e)))?;
+
+        // Create the file 0600 up front; write_atomic keeps the existing permissions
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .mode(0o600)
+                .open(&self.path)
+                .map_err(io_err)?;
+            fs::set_permissions(&self.path, fs::Permissions::from_mode(0o600)).map_err(io_err)?;
+        }
+
+        atomic_write::write_atomic(&self.path, &json).map_err(io_err)
+    }
+}
+
+impl SecretStore for FileStore {
+    fn get(&self, name: &str) -> Result<Option<String>, SecretError> {
+        Ok(self.read_all()?.remove(name))
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<(), SecretError> {
+        self.update(|secrets| {
+            secrets.insert(name.to_string(), value.to_string());
+        })
+    }
+
+    fn delete(&self, name: &str) -> Result<(), SecretError> {
+        if !self.path.exists() {
+            return Ok(());
+        }
+        self.update(|secrets| {
+            secrets.remove(name);
+        })
+    }
+}
+
+// ===== Environment backend =====
+
+pub struct EnvStore;
+
+impl SecretStore for EnvStore {
+    fn get(&self, var: &str) -> Result<Option<String>, SecretError> {
+        Ok(std::env::var(var).ok().filter(|v| !v.is_empty()))
+    }
+
+    fn set(&self, var: &str, _value: &str) -> Result<(), SecretError> {
+        Err(SecretError::ReadOnly(format!(
+            "Environment variables can't be set from the app; export {} in your shell profile",
+            var
+        )))
+    }
+
+    /// Nothing to remove; dropping the reference from the config is enough
+    fn delete(&self, _var: &str) -> Result<(), SecretError> {
+        Ok(())
+    }
+}
+
+// ===== Secret Service backend =====
+
+#[cfg(target_os = "linux")]
+pub use secret_service::SecretServiceStore;
+
+#[cfg(not(target_os = "linux"))]
+pub struct SecretServiceStore;
+
+#[cfg(not(target_os = "linux"))]
+impl SecretServiceStore {
+    pub fn connect() -> Result<Self, SecretError> {
+        Err(SecretError::Unavailable("the Secret Service is only available on Linux".to_string()))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl SecretStore for SecretServiceStore {
+    fn get(&self, _name: &str) -> Result<Option<String>, SecretError> {
+        unreachable!("SecretServiceStore can't be constructed on this platform")
+    }
+
+    fn set(&self, _name: &str, _value: &str) -> Result<(), SecretError> {
+        unreachable!("SecretServiceStore can't be constructed on this platform")
+    }
+
+    fn delete(&self, _name: &str) -> Result<(), SecretError> {
+        unreachable!("SecretServiceStore can't be constructed on this platform")
+    }
+}
+
+/// Minimal org.freedesktop.Secret.Service client over the session bus, using
+/// a "plain" session (the secret travels unencrypted over the local bus, as
+/// with secret-tool). Items are found by {application, name} attributes.
+#[cfg(target_os = "linux")]
+mod secret_service {
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use std::collections::HashMap;
+    use zbus::blocking::Connection;
+    use zbus::zvariant::{DynamicType, ObjectPath, OwnedObjectPath, OwnedValue, Type, Value};
+
+    use super::{SecretError, SecretStore};
+
+    const DESTINATION: &str = "org.freedesktop.secrets";
+    const SERVICE_PATH: &str = "/org/freedesktop/secrets";
+    const SERVICE_INTERFACE: &str = "org.freedesktop.Secret.Service";
+    const COLLECTION_INTERFACE: &str = "org.freedesktop.Secret.Collection";
+    const ITEM_INTERFACE: &str = "org.freedesktop.Secret.Item";
+    const SESSION_INTERFACE: &str = "org.freedesktop.Secret.Session";
+    const APPLICATION: &str = "audio-notifier";
+
+    /// (session, parameters, value, content type)
+    type Secret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+
+    pub struct SecretServiceStore {
+        connection: Connection,
+    }
+
+    impl From<zbus::Error> for SecretError {
+        fn from(e: zbus::Error) -> Self {
+            SecretError::Unavailable(e.to_string())
+        }
+    }
+
+    fn no_prompt(prompt: &OwnedObjectPath) -> Result<(), SecretError> {
+        if prompt.as_str() == "/" {
+            Ok(())
+        } else {
+            Err(SecretError::Locked)
+        }
+    }
+
+    impl SecretServiceStore {
+        /// Connect over the session bus (DBUS_SESSION_BUS_ADDRESS).
+        pub fn connect() -> Result<Self, SecretError> {
+            Ok(SecretServiceStore { connection: Connection::session()? })
+        }
+
+        fn call<B, R>(&self, path: &str, interface: &str, method: &str, body: &B) -> Result<R, SecretError>
+        where
+            B: Serialize + DynamicType,
+            R: DeserializeOwned + Type,
+        {
+            let reply = self
+                .connection
+                .call_method(Some(DESTINATION), path, Some(interface), method, body)?;
+            let body = reply.body();
+            Ok(body.deserialize::<R>()?)
+        }
+
+        fn attributes(name: &str) -> HashMap<&str, &str> {
+            HashMap::from([("application", APPLICATION), ("name", name)])
+        }
+
+        /// Run `f` with an open plain-text session, closing it afterwards.
+        fn with_session<T>(&self, f: impl FnOnce(&OwnedObjectPath) -> Result<T, SecretError>) -> Result<T, SecretError> {
+            let (_, session): (OwnedValue, OwnedObjectPath) =
+                self.call(SERVICE_PATH, SERVICE_INTERFACE, "OpenSession", &("plain", Value::from("")))?;
+            let result = f(&session);
+            let _: Result<(), _> = self.call(session.as_str(), SESSION_INTERFACE, "Close", &());
+            result
+        }
+
+        /// Items for `name`, unlocking them if that needs no prompt.
+        fn find_items(&self, name: &str) -> Result<Vec<OwnedObjectPath>, SecretError> {
+            let (mut unlocked, locked): (Vec<OwnedObjectPath>, Vec<OwnedObjectPath>) =
+                self.call(SERVICE_PATH, SERVICE_INTERFACE, "SearchItems", &Self::attributes(name))?;
+
+            if !locked.is_empty() {
+                let paths: Vec<ObjectPath> = locked.iter().map(|p| p.as_ref()).collect();
+                let (newly_unlocked, prompt): (Vec<OwnedObjectPath>, OwnedObjectPath) =
+                    self.call(SERVICE_PATH, SERVICE_INTERFACE, "Unlock", &paths)?;
+                no_prompt(&prompt)?;
+                unlocked.extend(newly_unlocked);
+            }
+            Ok(unlocked)
+        }
+    }
+
+    impl SecretStore for SecretServiceStore {
+        fn get(&self, name: &str) -> Result<Option<String>, SecretError> {
+            let Some(item) = self.find_items(name)?.into_iter().next() else {
+                return Ok(None);
+            };
+            self.with_session(|session| {
+                let (_, _, value, _): Secret =
+                    self.call(item.as_str(), ITEM_INTERFACE, "GetSecret", &session.as_ref())?;
+                String::from_utf8(value)
+                    .map(Some)
+                    .map_err(|_| SecretError::Io(format!("Secret {} is not valid UTF-8", name)))
+            })
+        }
+
+        fn set(&self, name: &str, value: &str) -> Result<(), SecretError> {
+            let collection: OwnedObjectPath = self.call(SERVICE_PATH, SERVICE_INTERFACE, "ReadAlias", &"default")?;
+            if collection.as_str() == "/" {
+                return Err(SecretError::Unavailable("no default keyring collection".to_string()));
+            }
+
+            let label = format!("Audio Notifier: {}", name);
+            let attributes: HashMap<String, String> = Self::attributes(name)
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            let properties = HashMap::from([
+                ("org.freedesktop.Secret.Item.Label", Value::from(label)),
+                ("org.freedesktop.Secret.Item.Attributes", Value::from(attributes)),
+            ]);
+
+            self.with_session(|session| {
+                let secret = (
+                    session.as_ref(),
+                    Vec::<u8>::new(),
+                    value.as_bytes().to_vec(),
+                    "text/plain",
+                );
+                let (_, prompt): (OwnedObjectPath, OwnedObjectPath) = self.call(
+                    collection.as_str(),
+                    COLLECTION_INTERFACE,
+                    "CreateItem",
+                    &(properties, secret, true),
+                )?;
+                no_prompt(&prompt)
+            })
+        }
+
+        fn delete(&self, name: &str) -> Result<(), SecretError> {
+            for item in self.find_items(name)? {
+                let prompt: OwnedObjectPath = self.call(item.as_str(), ITEM_INTERFACE, "Delete", &())?;
+                no_prompt(&prompt)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn file_store_round_trip_keeps_the_file_private() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.json");
+        let store = FileStore::new(path.clone());
+        let mode = || fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+
+        assert_eq!(store.get(FISH_AUDIO_API_KEY).unwrap(), None);
+        store.delete(FISH_AUDIO_API_KEY).unwrap();
+        assert!(!path.exists());
+
+        store.set(FISH_AUDIO_API_KEY, "fish-key").unwrap();
+        store.set("openai_api_key", "openai-key").unwrap();
+        assert_eq!(mode(), 0o600);
+        assert_eq!(store.get(FISH_AUDIO_API_KEY).unwrap().as_deref(), Some("fish-key"));
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        store.delete(FISH_AUDIO_API_KEY).unwrap();
+        assert_eq!(mode(), 0o600);
+        assert_eq!(store.get(FISH_AUDIO_API_KEY).unwrap(), None);
+        assert_eq!(store.get("openai_api_key").unwrap().as_deref(), Some("openai-key"));
+    }
+
+    #[test]
+    fn file_store_reports_corrupt_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.json");
+        fs::write(&path, "not json").unwrap();
+
+        assert!(matches!(FileStore::new(path).get(FISH_AUDIO_API_KEY), Err(SecretError::Io(_))));
+    }
+
+    #[test]
+    fn env_store_is_read_only() {
+        let var = "AUDIO_NOTIFIER_TEST_SECRET_ENV";
+        std::env::set_var(var, "env-key");
+
+        assert_eq!(EnvStore.get(var).unwrap().as_deref(), Some("env-key"));
+        assert!(matches!(EnvStore.set(var, "other"), Err(SecretError::ReadOnly(_))));
+        EnvStore.delete(var).unwrap();
+        assert_eq!(std::env::var(var).as_deref(), Ok("env-key"));
+
+        std::env::set_var(var, "");
+        assert_eq!(EnvStore.get(var).unwrap(), None);
+        std::env::remove_var(var);
+        assert_eq!(EnvStore.get(var).unwrap(), None);
+    }
+
+    #[test]
+    fn redacts_plaintext_keys_only() {
+        let mut doc: Value = serde_yaml::from_str(
+            "global_settings:\n  fish_audio_api_key: plain\n  openai_api_key:\n    backend: env\n    var: OPENAI_API_KEY\n",
+        )
+        .unwrap();
+        redact_plaintext_keys(&mut doc);
+
+        let yaml = serde_yaml::to_string(&doc).unwrap();
+        assert!(yaml.contains("fish_audio_api_key: '[REDACTED]'"), "{}", yaml);
+        assert!(yaml.contains("var: OPENAI_API_KEY"), "{}", yaml);
+    }
+}
@@ -0,0 +1,346 @@
+// Spoken summaries (global_settings.speak_summary)
+//
+// Instead of the clip generated ahead of time, stop and notification events
+// can speak the message itself: the last assistant message, or the tool
+// description of a permission request, as smart-notify.sh pulls them from the
+// transcript. Markdown and code are stripped, the text is cut to max_words and
+// it is spoken through the configured voice provider and the voice cache, so
+// a repeated phrase costs nothing the second time. The clip is written to
+// voices/rendered/summary-<event> and normalized like generated voices.
+//
+// smart-notify.sh passes the message to `audio-notifier speak-summary` on
+// stdin, once the event is going to be heard, and plays the pre-generated
+// clip instead when that fails or takes longer than timeout_ms.
+
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::events::HookEvent;
+use crate::voice_cache::VoiceCache;
+use crate::voice_jobs::{self, VoiceJob};
+use crate::voice_provider::{self, VoiceProvider};
+use crate::{get_voice_cache_dir, GlobalSettings};
+
+fn default_max_words() -> u32 {
+    25
+}
+
+fn default_timeout_ms() -> u64 {
+    4000
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+pub struct SpeakSummarySettings {
+    /// Speak the last assistant message when Claude stops
+    #[serde(default)]
+    pub stop: bool,
+    /// Speak what a notification is about, e.g. the command waiting for permission
+    #[serde(default)]
+    pub notification: bool,
+    /// Longer messages are cut, at a sentence end where possible
+    #[serde(default = "default_max_words")]
+    pub max_words: u32,
+    /// How long to wait for the voice before playing the usual clip; also
+    /// limits voice_templates rendered when a hook fires
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for SpeakSummarySettings {
+    fn default() -> Self {
+        SpeakSummarySettings {
+            stop: false,
+            notification: false,
+            max_words: default_max_words(),
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+impl SpeakSummarySettings {
+    /// Only stop and notification come with a message to speak.
+    pub fn enabled_for(&self, event: HookEvent) -> bool {
+        match event {
+            HookEvent::Stop => self.stop,
+            HookEvent::Notification => self.notification,
+            _ => false,
+        }
+    }
+}
+
+struct Patterns {
+    fenced_code: Regex,
+    inline_code: Regex,
+    image: Regex,
+    link: Regex,
+    url: Regex,
+    html_tag: Regex,
+    rule: Regex,
+    table_row: Regex,
+    table_cell: Regex,
+    line_marker: Regex,
+    emphasis: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        // An unclosed fence runs to the end, as it does when rendered
+        fenced_code: Regex::new(r"(?s)(```|~~~).*?(```|~~~|\z)").unwrap(),
+        inline_code: Regex::new(r"`([^`]*)`").unwrap(),
+        image: Regex::new(r"!\[[^\]]*\]\([^)]*\)").unwrap(),
+        link: Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap(),
+        url: Regex::new(r"https?://\S+").unwrap(),
+        html_tag: Regex::new(r"</?[A-Za-z][^>]*>").unwrap(),
+        // Horizontal rules and table separator rows
+        rule: Regex::new(r"(?m)^[ \t|:]*-{3,}[ \t|:-]*$").unwrap(),
+        // A table row reads as one sentence, its cells separated by commas
+        table_row: Regex::new(r"(?m)^[ \t]*\|[ \t]*(.*?)[ \t]*\|[ \t]*$").unwrap(),
+        table_cell: Regex::new(r"[ \t]*\|[ \t]*").unwrap(),
+        line_marker: Regex::new(r"(?m)^[ \t]*(#{1,6}[ \t]+|>[ \t]?|[-*+][ \t]+|\d+[.)][ \t]+)").unwrap(),
+        emphasis: Regex::new(r"\*{1,3}|_{2,3}|~~").unwrap(),
+    })
+}
+
+/// `message` as plain speakable text: code blocks, links, images, HTML and
+/// markdown markers removed, whitespace collapsed.
+pub fn strip_markdown(message: &str) -> String {
+    let p = patterns();
+    let text = p.fenced_code.replace_all(message, " ");
+    // A short span is usually a name or command worth saying; anything longer
+    // or with code punctuation is code
+    let text = p.inline_code.replace_all(&text, |caps: &regex::Captures| {
+        let code = &caps[1];
+        let speakable = !code.contains(['(', ')', '{', '}', ';', '=', '<', '>', '[', ']']);
+        if speakable && code.split_whitespace().count() <= 3 && code.len() <= 30 {
+            code.to_string()
+        } else {
+            " ".to_string()
+        }
+    });
+    let text = p.image.replace_all(&text, " ");
+    let text = p.link.replace_all(&text, "$1");
+    let text = p.url.replace_all(&text, " ");
+    let text = p.html_tag.replace_all(&text, " ");
+    let text = p.rule.replace_all(&text, " ");
+    let text = p.table_row.replace_all(&text, "$1.");
+    let text = p.table_cell.replace_all(&text, ", ");
+    let text = p.line_marker.replace_all(&text, "");
+    let text = p.emphasis.replace_all(&text, "");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The first `max_words` words of `text`, cut back to the last sentence end
+/// if that keeps at least half of them.
+pub fn trim_to_words(text: &str, max_words: usize) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() <= max_words {
+        return words.join(" ");
+    }
+    let kept = &words[..max_words];
+    let sentence_end = kept.iter().rposition(|word| word.ends_with(['.', '!', '?']));
+    match sentence_end {
+        Some(last) if last + 1 >= max_words.div_ceil(2) => kept[..=last].join(" "),
+        _ => kept.join(" ").trim_end_matches([',', ';', ':', '-']).to_string(),
+    }
+}
+
+/// What is spoken for `message`; empty when nothing speakable is left.
+pub fn spoken_text(settings: &SpeakSummarySettings, message: &str) -> String {
+    trim_to_words(&strip_markdown(message), settings.max_words as usize)
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Spoken {
+    pub text: String,
+    /// voices/rendered/summary-<event>, with the clip's extension
+    pub path: PathBuf,
+    /// Copied from the voice cache rather than generated
+    pub cached: bool,
+}
+
+/// Speak `message` for `event` through the configured provider, from the
+/// voice cache when the same text was spoken before. Fails when the mode is
+/// off for `event`, nothing is left to say, or the voice takes longer than
+/// timeout_ms.
+pub async fn speak(settings: &GlobalSettings, event: HookEvent, message: &str) -> Result<Spoken, String> {
+    let text = summary_text(settings, event, message)?;
+    let provider = voice_provider::from_settings(settings, None)?;
+    let cache = VoiceCache::for_settings(settings);
+    speak_text(settings, event, text, provider.as_ref(), &cache, &get_voice_cache_dir().join("rendered")).await
+}
+
+fn summary_text(settings: &GlobalSettings, event: HookEvent, message: &str) -> Result<String, String> {
+    if !settings.speak_summary.enabled_for(event) {
+        return Err(format!("speak_summary is off for {}", event.key()));
+    }
+    let text = spoken_text(&settings.speak_summary, message);
+    if text.is_empty() {
+        return Err("Nothing to speak after removing markdown and code".to_string());
+    }
+    Ok(text)
+}
+
+/// `speak` once the text is known, writing to `rendered`/summary-EVENT.
+async fn speak_text(
+    settings: &GlobalSettings,
+    event: HookEvent,
+    text: String,
+    provider: &dyn VoiceProvider,
+    cache: &VoiceCache,
+    rendered: &Path,
+) -> Result<Spoken, String> {
+    let voice = voice_provider::configured_voice(settings, provider);
+    let job = VoiceJob::new(rendered.join(format!("summary-{}", event.key())), text);
+    let (path, cached) = generate_in_time(settings, &job, provider, cache, voice).await?;
+    Ok(Spoken { text: job.text, path, cached })
+}
+
+/// `voice_jobs::generate` for a clip spoken when a hook fires: normalized
+/// like generated voices, and given up on after speak_summary.timeout_ms.
+pub async fn generate_in_time(
+    settings: &GlobalSettings,
+    job: &VoiceJob,
+    provider: &dyn VoiceProvider,
+    cache: &VoiceCache,
+    voice: Option<&str>,
+) -> Result<(PathBuf, bool), String> {
+    let timeout_ms = settings.speak_summary.timeout_ms;
+    let loudness_target = settings.loudness.normalize_voices.then_some(settings.loudness.target_lufs);
+    tokio::time::timeout(Duration::from_millis(timeout_ms), voice_jobs::generate(job, provider, cache, voice, loudness_target))
+        .await
+        .map_err(|_| format!("No voice from {} after {} ms", provider.id(), timeout_ms))?
+        .map_err(String::from)
+}
+
+/// Run `future` on a runtime of its own, for the CLI. Blocking work left
+/// behind by a timeout (a system voice still synthesizing) is abandoned
+/// rather than waited for, so the hook that asked isn't held up by it.
+pub fn block_on_detached<F: Future>(future: F) -> Result<F::Output, String> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("Failed to start runtime: {}", e))?;
+    let output = runtime.block_on(future);
+    runtime.shutdown_background();
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice_provider::fake::FakeProvider;
+    use crate::Config;
+    use std::fs;
+    use std::time::Instant;
+
+    #[test]
+    fn slow_blocking_voices_are_abandoned_at_the_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut settings = Config::default().global_settings;
+        settings.speak_summary.timeout_ms = 100;
+        settings.loudness.normalize_voices = false;
+        // Synthesizes on a blocking thread, like the system voice
+        let provider = FakeProvider { delay: Duration::from_secs(5), blocking: true, ..Default::default() };
+        let cache = VoiceCache::new(dir.path().join("cache"), 10);
+
+        let started = Instant::now();
+        let result = block_on_detached(speak_text(
+            &settings,
+            HookEvent::Stop,
+            "Build finished.".to_string(),
+            &provider,
+            &cache,
+            dir.path(),
+        ))
+        .unwrap();
+        assert!(result.unwrap_err().contains("after 100 ms"));
+        assert!(started.elapsed() < Duration::from_secs(2), "waited {:?}", started.elapsed());
+    }
+
+    #[test]
+    fn quick_voices_are_written_and_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut settings = Config::default().global_settings;
+        settings.loudness.normalize_voices = false;
+        let provider = FakeProvider::default();
+        let cache = VoiceCache::new(dir.path().join("cache"), 10);
+        let speak = || speak_text(&settings, HookEvent::Stop, "Done.".to_string(), &provider, &cache, dir.path());
+
+        let spoken = block_on_detached(speak()).unwrap().unwrap();
+        assert_eq!(spoken.path, dir.path().join("summary-stop.wav"));
+        assert_eq!(fs::read(&spoken.path).unwrap(), b"Done.");
+        assert!(!spoken.cached);
+        assert!(block_on_detached(speak()).unwrap().unwrap().cached);
+    }
+
+    #[test]
+    fn nothing_is_spoken_when_off_or_empty() {
+        let mut settings = Config::default().global_settings;
+        assert!(summary_text(&settings, HookEvent::Stop, "Done.").unwrap_err().contains("off"));
+        settings.speak_summary.stop = true;
+        assert!(summary_text(&settings, HookEvent::Stop, "```\ncode\n```").is_err());
+        assert_eq!(summary_text(&settings, HookEvent::Stop, "**Done.**").unwrap(), "Done.");
+    }
+
+    #[test]
+    fn strips_markdown_to_speakable_text() {
+        let message = "## Done\n\nI fixed the **bug** in `parser.rs` and [updated the docs](https://example.com/docs).\n\n\
+                       ```rust\nfn main() {}\n```\n\
+                       - Added tests\n- See https://example.com/long/url\n\n\
+                       > Quoted _note_\n\n\
+                       | File | Lines |\n|---|---|\n| lib.rs | 12 |\n<br>Run `cargo test --workspace` next.";
+        assert_eq!(
+            strip_markdown(message),
+            "Done I fixed the bug in parser.rs and updated the docs. Added tests See Quoted _note_ \
+             File, Lines. lib.rs, 12. Run cargo test --workspace next."
+        );
+    }
+
+    #[test]
+    fn code_is_dropped_unless_it_reads_as_a_name() {
+        assert_eq!(strip_markdown("Call `foo(bar)` or `make build`"), "Call or make build");
+        assert_eq!(strip_markdown("Set `a = 1` in `a very long configuration key name`"), "Set in");
+        // An unclosed fence hides the rest, as when rendered
+        assert_eq!(strip_markdown("Before ```let x = 1;``` after ```unclosed\nmore"), "Before after");
+        assert_eq!(strip_markdown("![diagram](d.png) <b>Bold</b> ***very*** ~~old~~"), "Bold very old");
+        assert_eq!(strip_markdown("```\nonly code\n```"), "");
+    }
+
+    #[test]
+    fn list_and_heading_markers_only_go_at_line_starts() {
+        assert_eq!(strip_markdown("1. First\n2) Second\n* Third\n### Fourth"), "First Second Third Fourth");
+        assert_eq!(strip_markdown("Costs 3 - 2 = 1 #tag"), "Costs 3 - 2 = 1 #tag");
+    }
+
+    #[test]
+    fn trims_at_a_sentence_end_when_it_keeps_enough() {
+        assert_eq!(trim_to_words("One two three. Four five six seven", 5), "One two three.");
+        // Cutting at "One." would drop more than half
+        assert_eq!(trim_to_words("One. Two three four five six seven", 5), "One. Two three four five");
+        assert_eq!(trim_to_words("Is it done? Yes it is, mostly", 4), "Is it done?");
+    }
+
+    #[test]
+    fn trims_dangling_punctuation_and_whitespace() {
+        assert_eq!(trim_to_words("a b c, d e", 3), "a b c");
+        assert_eq!(trim_to_words("a b c; d", 3), "a b c");
+        assert_eq!(trim_to_words("  short \n text ", 5), "short text");
+        assert_eq!(trim_to_words("", 5), "");
+        assert_eq!(trim_to_words("exactly three words", 3), "exactly three words");
+    }
+
+    #[test]
+    fn spoken_text_strips_then_trims() {
+        let settings = SpeakSummarySettings { stop: true, max_words: 4, ..Default::default() };
+        assert_eq!(spoken_text(&settings, "**All** tests pass now, shipping it"), "All tests pass now");
+        assert!(settings.enabled_for(HookEvent::Stop));
+        assert!(!settings.enabled_for(HookEvent::Notification));
+        assert!(!settings.enabled_for(HookEvent::PreToolUse));
+    }
+}
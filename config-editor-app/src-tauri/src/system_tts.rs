@@ -0,0 +1,369 @@
+// Offline speech engines behind the "system" voice provider
+//
+//   say        macOS built-in; AIFF
+//   piper      neural voices from a local .onnx model; WAV
+//   espeak_ng  small and robotic, but installed almost everywhere; WAV
+//   pico2wave  SVOX Pico (libttspico-utils); WAV
+//
+// global_settings.system_tts.engine picks one; unset takes the first that is
+// installed, in the order above (Piper only once a model is configured).
+// Each engine has its own voice and rate settings; voice_id, when set,
+// replaces the engine's voice.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::audio_format::AudioFormat;
+use crate::voice_provider::{CostEstimate, ProviderError, VoiceInfo, VoiceProvider};
+use crate::{expand_home, hash_string};
+
+/// Languages pico2wave ships voices for.
+const PICO_LANGUAGES: &[&str] = &["en-US", "en-GB", "de-DE", "es-ES", "fr-FR", "it-IT"];
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TtsEngine {
+    Say,
+    Piper,
+    EspeakNg,
+    Pico2wave,
+}
+
+impl TtsEngine {
+    /// Preference order when no engine is configured.
+    pub const ALL: [TtsEngine; 4] = [TtsEngine::Say, TtsEngine::Piper, TtsEngine::EspeakNg, TtsEngine::Pico2wave];
+
+    /// Executable names, most common first.
+    fn programs(self) -> &'static [&'static str] {
+        match self {
+            TtsEngine::Say => &["say"],
+            TtsEngine::Piper => &["piper", "piper-tts"],
+            TtsEngine::EspeakNg => &["espeak-ng", "espeak"],
+            TtsEngine::Pico2wave => &["pico2wave"],
+        }
+    }
+
+    pub fn output_format(self) -> AudioFormat {
+        match self {
+            TtsEngine::Say => AudioFormat::Aiff,
+            _ => AudioFormat::Wav,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default, PartialEq)]
+pub struct SystemTtsSettings {
+    /// Unset uses the first installed engine
+    #[serde(default)]
+    pub engine: Option<TtsEngine>,
+    #[serde(default)]
+    pub say: SaySettings,
+    #[serde(default)]
+    pub piper: PiperSettings,
+    #[serde(default)]
+    pub espeak_ng: EspeakSettings,
+    #[serde(default)]
+    pub pico2wave: PicoSettings,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default, PartialEq)]
+pub struct SaySettings {
+    /// Name from `say -v '?'`, e.g. Samantha
+    #[serde(default)]
+    pub voice: Option<String>,
+    /// Words per minute
+    #[serde(default)]
+    pub rate: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default, PartialEq)]
+pub struct PiperSettings {
+    /// Voice model (.onnx, with its .onnx.json next to it)
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Speaker number, for models with several voices
+    #[serde(default)]
+    pub speaker: Option<u32>,
+    /// Above 1 speaks slower, below 1 faster
+    #[serde(default)]
+    pub length_scale: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default, PartialEq)]
+pub struct EspeakSettings {
+    /// Voice from `espeak-ng --voices`, optionally with a variant, e.g. en-us+f3
+    #[serde(default)]
+    pub voice: Option<String>,
+    /// Words per minute (espeak-ng defaults to 175)
+    #[serde(default)]
+    pub rate: Option<u32>,
+    /// 0-99 (default 50)
+    #[serde(default)]
+    pub pitch: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default, PartialEq)]
+pub struct PicoSettings {
+    /// en-US, en-GB, de-DE, es-ES, fr-FR or it-IT
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+/// Whether an engine can be used, for the editor and `audio-notifier tts engines`.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct EngineStatus {
+    pub engine: TtsEngine,
+    /// Where the executable was found
+    pub program: Option<PathBuf>,
+    /// Why the engine can't be used, e.g. Piper without a model
+    pub problem: Option<String>,
+}
+
+impl EngineStatus {
+    pub fn ready(&self) -> bool {
+        self.program.is_some() && self.problem.is_none()
+    }
+}
+
+/// Look `name` up on PATH.
+fn find_program(name: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+pub fn engine_status(engine: TtsEngine, settings: &SystemTtsSettings) -> EngineStatus {
+    let program = engine.programs().iter().find_map(|name| find_program(name));
+    let problem = match engine {
+        _ if program.is_none() => Some(format!("{} is not installed", engine.programs()[0])),
+        TtsEngine::Say if !cfg!(target_os = "macos") => Some("say is only available on macOS".to_string()),
+        TtsEngine::Piper => match &settings.piper.model {
+            None => Some("no model set in system_tts.piper.model".to_string()),
+            Some(model) if !expand_home(model).is_file() => Some(format!("model {} not found", model)),
+            Some(_) => None,
+        },
+        _ => None,
+    };
+    EngineStatus { engine, program, problem }
+}
+
+/// Every engine, in preference order.
+pub fn detect(settings: &SystemTtsSettings) -> Vec<EngineStatus> {
+    TtsEngine::ALL.iter().map(|&engine| engine_status(engine, settings)).collect()
+}
+
+/// The engine `settings` selects, or why there is none.
+pub fn select(settings: &SystemTtsSettings) -> Result<EngineStatus, String> {
+    if let Some(engine) = settings.engine {
+        let status = engine_status(engine, settings);
+        return match &status.problem {
+            Some(problem) => Err(format!("System TTS engine unavailable: {}", problem)),
+            None => Ok(status),
+        };
+    }
+    detect(settings)
+        .into_iter()
+        .find(EngineStatus::ready)
+        .ok_or_else(|| "No speech engine found; install espeak-ng, Piper or pico2wave".to_string())
+}
+
+/// Speak `text` with the selected engine. `voice` replaces the engine's
+/// configured voice (a Piper voice is a model path).
+pub fn synthesize(settings: &SystemTtsSettings, text: &str, voice: Option<&str>) -> Result<(Vec<u8>, AudioFormat), String> {
+    let status = select(settings)?;
+    let engine = status.engine;
+    let program = status.program.unwrap_or_default();
+    let format = engine.output_format();
+    let temp_path = env::temp_dir().join(format!(
+        "tts_{}_{}.{}",
+        std::process::id(),
+        hash_string(&format!("{}\n{}", voice.unwrap_or(""), text)),
+        format.extension()
+    ));
+
+    let mut command = Command::new(&program);
+    // Piper and espeak-ng read the text from stdin; say and pico2wave take it as an argument
+    let mut stdin_text = None;
+    match engine {
+        TtsEngine::Say => {
+            if let Some(voice) = voice.or(settings.say.voice.as_deref()) {
+                command.arg("-v").arg(voice);
+            }
+            if let Some(rate) = settings.say.rate {
+                command.arg("-r").arg(rate.to_string());
+            }
+            command.arg("-o").arg(&temp_path).arg(text);
+        }
+        TtsEngine::Piper => {
+            let model = voice.or(settings.piper.model.as_deref()).unwrap_or_default();
+            command.arg("--model").arg(expand_home(model)).arg("--output_file").arg(&temp_path);
+            if let Some(speaker) = settings.piper.speaker {
+                command.arg("--speaker").arg(speaker.to_string());
+            }
+            if let Some(length_scale) = settings.piper.length_scale {
+                command.arg("--length_scale").arg(length_scale.to_string());
+            }
+            stdin_text = Some(text);
+        }
+        TtsEngine::EspeakNg => {
+            if let Some(voice) = voice.or(settings.espeak_ng.voice.as_deref()) {
+                command.arg("-v").arg(voice);
+            }
+            if let Some(rate) = settings.espeak_ng.rate {
+                command.arg("-s").arg(rate.to_string());
+            }
+            if let Some(pitch) = settings.espeak_ng.pitch {
+                command.arg("-p").arg(pitch.to_string());
+            }
+            command.arg("-w").arg(&temp_path).arg("--stdin");
+            stdin_text = Some(text);
+        }
+        TtsEngine::Pico2wave => {
+            let language = voice.or(settings.pico2wave.language.as_deref()).unwrap_or("en-US");
+            command.arg("-l").arg(language).arg("-w").arg(&temp_path).arg("--").arg(text);
+        }
+    }
+
+    let name = engine.programs()[0];
+    let mut child = command
+        .stdin(if stdin_text.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run '{}': {}", name, e))?;
+    if let (Some(text), Some(mut stdin)) = (stdin_text, child.stdin.take()) {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|e| format!("Failed to send text to '{}': {}", name, e))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run '{}': {}", name, e))?;
+
+    if !output.status.success() {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("'{}' failed: {}", name, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    let bytes = fs::read(&temp_path).map_err(|e| format!("Failed to read generated audio: {}", e))?;
+    let _ = fs::remove_file(&temp_path);
+    Ok((bytes, format))
+}
+
+/// Voices the selected engine offers.
+pub fn list_voices(settings: &SystemTtsSettings) -> Result<Vec<VoiceInfo>, String> {
+    let status = select(settings)?;
+    let run = |args: &[&str]| {
+        Command::new(status.program.as_deref().unwrap_or(Path::new("")))
+            .args(args)
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+            .map_err(|e| format!("Failed to run '{}': {}", status.engine.programs()[0], e))
+    };
+
+    let voices = match status.engine {
+        // "Samantha    en_US    # Hello! My name is Samantha."
+        TtsEngine::Say => run(&["-v", "?"])?
+            .lines()
+            .filter_map(|line| {
+                let (name_and_language, _) = line.split_once('#')?;
+                let mut parts = name_and_language.trim_end().rsplitn(2, char::is_whitespace);
+                let language = parts.next()?.to_string();
+                let name = parts.next()?.trim().to_string();
+                Some(VoiceInfo { id: name.clone(), name, language: Some(language) })
+            })
+            .collect(),
+        // "Pty Language Age/Gender VoiceName File Other Languages", then one voice per line
+        TtsEngine::EspeakNg => run(&["--voices"])?
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let (language, name) = (fields.get(1)?, fields.get(3)?);
+                Some(VoiceInfo { id: language.to_string(), name: name.replace('_', " "), language: Some(language.to_string()) })
+            })
+            .collect(),
+        // Other models next to the configured one
+        TtsEngine::Piper => {
+            let model = expand_home(settings.piper.model.as_deref().unwrap_or_default());
+            let dir = model.parent().unwrap_or(Path::new("."));
+            let mut models: Vec<PathBuf> = fs::read_dir(dir)
+                .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "onnx"))
+                .collect();
+            models.sort();
+            models
+                .into_iter()
+                .map(|path| VoiceInfo {
+                    name: path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+                    id: path.to_string_lossy().to_string(),
+                    language: None,
+                })
+                .collect()
+        }
+        TtsEngine::Pico2wave => PICO_LANGUAGES
+            .iter()
+            .map(|language| VoiceInfo { id: language.to_string(), name: language.to_string(), language: Some(language.to_string()) })
+            .collect(),
+    };
+    Ok(voices)
+}
+
+// ===== Provider =====
+
+pub struct SystemTts {
+    settings: SystemTtsSettings,
+}
+
+impl SystemTts {
+    pub fn new(settings: &SystemTtsSettings) -> Self {
+        SystemTts { settings: settings.clone() }
+    }
+}
+
+#[async_trait::async_trait]
+impl VoiceProvider for SystemTts {
+    fn id(&self) -> &'static str {
+        "system"
+    }
+
+    fn output_format(&self) -> AudioFormat {
+        select(&self.settings)
+            .map(|status| status.engine.output_format())
+            .unwrap_or(AudioFormat::Wav)
+    }
+
+    async fn synthesize(&self, text: &str, voice: Option<&str>) -> Result<Vec<u8>, ProviderError> {
+        let settings = self.settings.clone();
+        let (text, voice) = (text.to_string(), voice.map(str::to_string));
+        let (bytes, _) = tokio::task::spawn_blocking(move || synthesize(&settings, &text, voice.as_deref()))
+            .await
+            .map_err(|e| format!("Speech engine crashed: {}", e))??;
+        Ok(bytes)
+    }
+
+    async fn list_voices(&self) -> Result<Vec<VoiceInfo>, ProviderError> {
+        let settings = self.settings.clone();
+        let voices = tokio::task::spawn_blocking(move || list_voices(&settings))
+            .await
+            .map_err(|e| format!("Speech engine crashed: {}", e))??;
+        Ok(voices)
+    }
+
+    fn estimate_cost(&self, texts: &[String]) -> CostEstimate {
+        CostEstimate { characters: texts.iter().map(|t| t.chars().count()).sum(), usd: Some(0.0) }
+    }
+
+    /// The engine in use and its settings
+    fn cache_options(&self) -> String {
+        let engine = select(&self.settings).ok().map(|status| status.engine);
+        serde_json::json!({ "engine": engine, "settings": self.settings }).to_string()
+    }
+}
@@ -0,0 +1,378 @@
+// Voice templates (global_settings.voice_template)
+//
+// The text spoken by generated voices, with variables filled in per event:
+//
+//   {event}     spoken event name, e.g. "subagent stop"
+//   {project}   project rule name, or the repository folder at hook time
+//   {tool}      tool from the hook payload, e.g. "Bash"
+//   {branch}    current git branch
+//   {repo}      git repository folder name
+//   {summary}   first sentence of the last assistant message
+//   {duration}  how long the turn took, e.g. "2 minutes"
+//   {subagent}  subagent type, for subagent_stop
+//
+// `{name|fallback}` speaks the fallback when the variable is empty, and
+// `{#name}...{/name}` keeps its contents only when the variable has a value
+// (`{^name}...{/name}` only when it doesn't). `{{` and `}}` are literal braces.
+//
+// Voices using only {event} and {project} are generated ahead of time. The
+// other variables only exist when a hook fires, so templates using them are
+// rendered and spoken with the configured provider by smart-notify.sh through
+// `audio-notifier template render --speak`; pre-generated clips treat them as
+// empty.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+
+use crate::events::HookEvent;
+
+pub const VARIABLES: &[&str] = &["event", "project", "tool", "branch", "repo", "summary", "duration", "subagent"];
+
+/// Variables known before any hook fires.
+pub const STATIC_VARIABLES: &[&str] = &["event", "project"];
+
+/// Longest {summary}, in words.
+const SUMMARY_WORDS: usize = 25;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Var { name: String, fallback: Option<String> },
+    Section { name: String, inverted: bool, body: Vec<Node> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+/// Offsets count characters from the start of the template.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TemplateError {
+    UnknownVariable { name: String, offset: usize },
+    Syntax { message: String, offset: usize },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::UnknownVariable { name, .. } => write!(
+                f,
+                "Unknown variable {{{}}} (available: {})",
+                name,
+                VARIABLES.iter().map(|v| format!("{{{}}}", v)).collect::<Vec<_>>().join(", ")
+            ),
+            TemplateError::Syntax { message, offset } => write!(f, "{} at character {}", message, offset + 1),
+        }
+    }
+}
+
+struct OpenSection {
+    name: String,
+    inverted: bool,
+    offset: usize,
+    outer: Vec<Node>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let chars: Vec<char> = source.chars().collect();
+        let mut nodes = Vec::new();
+        let mut open: Vec<OpenSection> = Vec::new();
+        let mut text = String::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            match chars[i] {
+                '{' if chars.get(i + 1) == Some(&'{') => {
+                    text.push('{');
+                    i += 2;
+                }
+                '}' if chars.get(i + 1) == Some(&'}') => {
+                    text.push('}');
+                    i += 2;
+                }
+                '}' => {
+                    return Err(syntax("Unmatched \"}\" (write \"}}\" for a literal brace)", i));
+                }
+                '{' => {
+                    let close = chars[i + 1..]
+                        .iter()
+                        .position(|&c| c == '}')
+                        .map(|p| i + 1 + p)
+                        .ok_or_else(|| syntax("Unclosed \"{\"", i))?;
+                    let tag: String = chars[i + 1..close].iter().collect();
+                    if !text.is_empty() {
+                        nodes.push(Node::Text(std::mem::take(&mut text)));
+                    }
+
+                    if let Some(name) = tag.strip_prefix('#').or_else(|| tag.strip_prefix('^')) {
+                        open.push(OpenSection {
+                            name: variable(name, i)?,
+                            inverted: tag.starts_with('^'),
+                            offset: i,
+                            outer: std::mem::take(&mut nodes),
+                        });
+                    } else if let Some(name) = tag.strip_prefix('/') {
+                        let name = name.trim();
+                        let section = match open.pop() {
+                            Some(section) if section.name == name => section,
+                            Some(section) => {
+                                return Err(syntax(&format!("{{/{}}} closes {{#{}}}", name, section.name), i));
+                            }
+                            None => return Err(syntax(&format!("{{/{}}} has no matching {{#{}}}", name, name), i)),
+                        };
+                        let body = std::mem::replace(&mut nodes, section.outer);
+                        nodes.push(Node::Section { name: section.name, inverted: section.inverted, body });
+                    } else {
+                        let (name, fallback) = match tag.split_once('|') {
+                            Some((name, fallback)) => (name, Some(fallback.to_string())),
+                            None => (tag.as_str(), None),
+                        };
+                        nodes.push(Node::Var { name: variable(name, i)?, fallback });
+                    }
+                    i = close + 1;
+                }
+                c => {
+                    text.push(c);
+                    i += 1;
+                }
+            }
+        }
+
+        if let Some(section) = open.pop() {
+            return Err(syntax(&format!("{{#{}}} is never closed", section.name), section.offset));
+        }
+        if !text.is_empty() {
+            nodes.push(Node::Text(text));
+        }
+        Ok(Template { nodes })
+    }
+
+    /// Variables the template refers to, in order of first use.
+    pub fn variables(&self) -> Vec<String> {
+        fn walk(nodes: &[Node], names: &mut Vec<String>) {
+            for node in nodes {
+                let name = match node {
+                    Node::Text(_) => continue,
+                    Node::Var { name, .. } => name,
+                    Node::Section { name, body, .. } => {
+                        if !names.contains(name) {
+                            names.push(name.clone());
+                        }
+                        walk(body, names);
+                        continue;
+                    }
+                };
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+        let mut names = Vec::new();
+        walk(&self.nodes, &mut names);
+        names
+    }
+
+    /// Variables that are only known when a hook fires.
+    pub fn runtime_variables(&self) -> Vec<String> {
+        self.variables()
+            .into_iter()
+            .filter(|name| !STATIC_VARIABLES.contains(&name.as_str()))
+            .collect()
+    }
+
+    /// Fill in the variables, collapsing the whitespace left by empty ones.
+    pub fn render(&self, vars: &TemplateVars) -> String {
+        fn walk(nodes: &[Node], vars: &TemplateVars, out: &mut String) {
+            for node in nodes {
+                match node {
+                    Node::Text(text) => out.push_str(text),
+                    Node::Var { name, fallback } => {
+                        let value = vars.get(name);
+                        out.push_str(if value.is_empty() { fallback.as_deref().unwrap_or("") } else { value });
+                    }
+                    Node::Section { name, inverted, body } => {
+                        if vars.get(name).is_empty() == *inverted {
+                            walk(body, vars, out);
+                        }
+                    }
+                }
+            }
+        }
+        let mut out = String::new();
+        walk(&self.nodes, vars, &mut out);
+        out.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+fn syntax(message: &str, offset: usize) -> TemplateError {
+    TemplateError::Syntax { message: message.to_string(), offset }
+}
+
+fn variable(name: &str, offset: usize) -> Result<String, TemplateError> {
+    let name = name.trim();
+    if VARIABLES.contains(&name) {
+        Ok(name.to_string())
+    } else {
+        Err(TemplateError::UnknownVariable { name: name.to_string(), offset })
+    }
+}
+
+/// Values for one rendering; unset variables are empty.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TemplateVars(BTreeMap<String, String>);
+
+impl TemplateVars {
+    pub fn for_event(event: HookEvent) -> Self {
+        let mut vars = TemplateVars::default();
+        vars.set("event", event.info().spoken_name);
+        vars
+    }
+
+    /// Made-up values for previews in the editor.
+    pub fn sample(event: HookEvent) -> Self {
+        let mut vars = TemplateVars::for_event(event);
+        vars.set("project", "my app");
+        vars.set("tool", "Bash");
+        vars.set("branch", "main");
+        vars.set("repo", "my-app");
+        vars.set("summary", "I fixed the failing test.");
+        vars.set("duration", "2 minutes");
+        if event == HookEvent::SubagentStop {
+            vars.set("subagent", "code reviewer");
+        }
+        vars
+    }
+
+    pub fn get(&self, name: &str) -> &str {
+        self.0.get(name).map(String::as_str).unwrap_or("")
+    }
+
+    pub fn set(&mut self, name: &str, value: impl Into<String>) {
+        self.0.insert(name.to_string(), value.into().trim().to_string());
+    }
+
+    /// {branch} and {repo} for the repository containing `dir`, if any.
+    pub fn set_git(&mut self, dir: &Path) {
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(args)
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        };
+        if let Some(root) = git(&["rev-parse", "--show-toplevel"]) {
+            if let Some(name) = Path::new(&root).file_name() {
+                self.set("repo", name.to_string_lossy());
+            }
+            // A detached HEAD reports "HEAD", which isn't worth saying
+            if let Some(branch) = git(&["rev-parse", "--abbrev-ref", "HEAD"]).filter(|b| b != "HEAD") {
+                self.set("branch", branch);
+            }
+        }
+    }
+}
+
+/// First sentence of `message`, cut to a length that is quick to listen to.
+pub fn summarize(message: &str) -> String {
+    let message = message.split_whitespace().collect::<Vec<_>>().join(" ");
+    let sentence = message
+        .char_indices()
+        .find(|&(i, c)| matches!(c, '.' | '!' | '?') && message[i + 1..].starts_with(' '))
+        .map_or(message.as_str(), |(i, _)| &message[..=i]);
+
+    let words: Vec<&str> = sentence.split(' ').collect();
+    if words.len() > SUMMARY_WORDS {
+        words[..SUMMARY_WORDS].join(" ")
+    } else {
+        sentence.to_string()
+    }
+}
+
+/// "45 seconds", "1 minute", "1 hour 5 minutes".
+pub fn spoken_duration(seconds: u64) -> String {
+    let unit = |n: u64, name: &str| format!("{} {}{}", n, name, if n == 1 { "" } else { "s" });
+    match seconds {
+        0..=59 => unit(seconds, "second"),
+        60..=3599 => unit(seconds / 60, "minute"),
+        _ if seconds % 3600 < 60 => unit(seconds / 3600, "hour"),
+        _ => format!("{} {}", unit(seconds / 3600, "hour"), unit(seconds % 3600 / 60, "minute")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str, vars: &[(&str, &str)]) -> String {
+        let mut values = TemplateVars::for_event(HookEvent::Stop);
+        for (name, value) in vars {
+            values.set(name, *value);
+        }
+        Template::parse(source).unwrap().render(&values)
+    }
+
+    #[test]
+    fn fills_in_variables_and_fallbacks() {
+        assert_eq!(render("{event} in {project}", &[("project", "api")]), "stop in api");
+        assert_eq!(render("{ tool | a tool } finished", &[]), "a tool finished");
+        assert_eq!(render("{tool|a tool} finished", &[("tool", "Bash")]), "Bash finished");
+        assert_eq!(render("{{literal}} {event}", &[]), "{literal} stop");
+    }
+
+    #[test]
+    fn sections_depend_on_whether_a_variable_is_set() {
+        let source = "Done{#branch} on {branch}{/branch}{^duration}, quickly{/duration}.";
+
+        assert_eq!(render(source, &[]), "Done, quickly.");
+        assert_eq!(render(source, &[("branch", "main"), ("duration", "2 minutes")]), "Done on main.");
+        // Empty variables don't leave double spaces behind
+        assert_eq!(render("{project} {tool} done", &[("tool", "Bash")]), "Bash done");
+    }
+
+    #[test]
+    fn lists_runtime_variables_in_order_of_use() {
+        let template = Template::parse("{#tool}{tool} {repo}{/tool} in {project} {repo} {summary}").unwrap();
+
+        assert_eq!(template.variables(), ["tool", "repo", "project", "summary"]);
+        assert_eq!(template.runtime_variables(), ["tool", "repo", "summary"]);
+        assert!(Template::parse("{event} in {project}").unwrap().runtime_variables().is_empty());
+    }
+
+    #[test]
+    fn reports_errors_with_their_offset() {
+        let error = |source: &str| Template::parse(source).unwrap_err();
+
+        assert_eq!(error("hi {nope}"), TemplateError::UnknownVariable { name: "nope".to_string(), offset: 3 });
+        assert!(matches!(error("{event"), TemplateError::Syntax { offset: 0, .. }));
+        assert!(matches!(error("x}"), TemplateError::Syntax { offset: 1, .. }));
+        assert!(matches!(error("{#tool}x{/branch}"), TemplateError::Syntax { .. }));
+        assert!(matches!(error("{#tool}x"), TemplateError::Syntax { .. }));
+    }
+
+    #[test]
+    fn summarizes_to_the_first_sentence() {
+        assert_eq!(summarize("Fixed the bug.  Also tidied\nthe docs."), "Fixed the bug.");
+        assert_eq!(summarize("Version 1.2 is out"), "Version 1.2 is out");
+        let long = "word ".repeat(40);
+        assert_eq!(summarize(&long).split(' ').count(), SUMMARY_WORDS);
+    }
+
+    #[test]
+    fn speaks_durations() {
+        assert_eq!(spoken_duration(1), "1 second");
+        assert_eq!(spoken_duration(45), "45 seconds");
+        assert_eq!(spoken_duration(61), "1 minute");
+        assert_eq!(spoken_duration(3600), "1 hour");
+        assert_eq!(spoken_duration(3900), "1 hour 5 minutes");
+        assert_eq!(spoken_duration(7260), "2 hours 1 minute");
+    }
+}
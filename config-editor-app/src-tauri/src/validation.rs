@@ -0,0 +1,413 @@
+// Config validation with per-field diagnostics
+//
+// Findings are returned to the editor as a flat list; `field` is a dotted path
+// into the Config (e.g. "projects[2].event_sounds.stop") so the UI can point at
+// the offending control.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::events::{EventEnabled, EventSounds};
+use crate::loudness::{MAX_TARGET_LUFS, MIN_TARGET_LUFS};
+use crate::project_match::{self, MatchKind};
+use crate::template::{Template, TemplateError};
+use crate::{audio_format, loudness, system_tts, voice_provider};
+use crate::{get_home_dir, get_voice_cache_dir, hash_string, Config};
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingCode {
+    SoundNotFound,
+    SoundNotAudio,
+    SoundNotDecodable,
+    ProjectPathNotFound,
+    DuplicateProjectPath,
+    InvalidProjectPattern,
+    UnknownVoiceProvider,
+    NonCanonicalVoiceProvider,
+    VoiceFileMissing,
+    UnknownTemplatePlaceholder,
+    InvalidTemplate,
+    InvalidSchedule,
+    InvalidVoiceServer,
+    FishAudioKeyMissing,
+    NoSpeechEngine,
+    InvalidLoudnessTarget,
+    InvalidSpeakSummary,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ValidationFinding {
+    pub severity: Severity,
+    pub code: FindingCode,
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationFinding {
+    fn error(code: FindingCode, field: String, message: String) -> Self {
+        ValidationFinding { severity: Severity::Error, code, field, message }
+    }
+
+    fn warning(code: FindingCode, field: String, message: String) -> Self {
+        ValidationFinding { severity: Severity::Warning, code, field, message }
+    }
+}
+
+/// Map a voice_provider value onto its canonical spelling.
+/// Older builds wrote "fish-audio", which is still accepted.
+pub fn normalize_voice_provider(provider: &str) -> Option<&'static str> {
+    match provider {
+        "fish_audio" | "fish-audio" => Some("fish_audio"),
+        "openai_compatible" => Some("openai_compatible"),
+        "system" => Some("system"),
+        _ => None,
+    }
+}
+
+pub fn validate(config: &Config) -> Vec<ValidationFinding> {
+    let mut findings = Vec::new();
+    let voice_dir = get_voice_cache_dir();
+    let home = PathBuf::from(get_home_dir().unwrap_or_default());
+
+    check_event_sounds(
+        &config.global_settings.event_sounds,
+        &config.global_settings.event_enabled,
+        "global_settings.event_sounds",
+        &[voice_dir.join("global")],
+        &home,
+        &mut findings,
+    );
+
+    for (i, sound) in config.sound_library.iter().enumerate() {
+        // Library entries are only candidates, so problems there are warnings
+        if let Some(mut finding) = check_sound_file(sound, format!("sound_library[{}]", i), &home) {
+            finding.severity = Severity::Warning;
+            findings.push(finding);
+        }
+    }
+
+    let mut seen_paths: HashMap<&str, usize> = HashMap::new();
+    for (i, project) in config.projects.iter().enumerate() {
+        let field = format!("projects[{}]", i);
+
+        if let Some(first) = seen_paths.insert(project.path.as_str(), i) {
+            findings.push(ValidationFinding::error(
+                FindingCode::DuplicateProjectPath,
+                format!("{}.path", field),
+                format!("{} is already configured as projects[{}]", project.path, first),
+            ));
+        }
+
+        if let Some(error) = project_match::pattern_error(project.match_kind, &project.path) {
+            findings.push(ValidationFinding::error(
+                FindingCode::InvalidProjectPattern,
+                format!("{}.path", field),
+                format!("{} is not a valid pattern: {}", project.path, error),
+            ));
+        }
+
+        // Legacy entries migrated from project-sounds.conf are bare directory names
+        let path = Path::new(&project.path);
+        let is_directory_rule = matches!(project.match_kind, MatchKind::Exact | MatchKind::Prefix);
+        if is_directory_rule && path.is_absolute() && !path.is_dir() {
+            findings.push(ValidationFinding::warning(
+                FindingCode::ProjectPathNotFound,
+                format!("{}.path", field),
+                format!("Project directory {} no longer exists", project.path),
+            ));
+        }
+
+        let project_voice_dir = voice_dir.join("projects").join(hash_string(&project.path));
+        check_event_sounds(
+            &project.event_sounds,
+            &project.event_enabled,
+            &format!("{}.event_sounds", field),
+            &[project_voice_dir, voice_dir.join("global")],
+            &home,
+            &mut findings,
+        );
+    }
+
+    let provider = &config.global_settings.voice_provider;
+    match normalize_voice_provider(provider) {
+        None => findings.push(ValidationFinding::error(
+            FindingCode::UnknownVoiceProvider,
+            "global_settings.voice_provider".to_string(),
+            format!(
+                "Unknown voice provider \"{}\" (expected \"fish_audio\", \"openai_compatible\" or \"system\")",
+                provider
+            ),
+        )),
+        Some(canonical) if canonical != provider => findings.push(ValidationFinding::warning(
+            FindingCode::NonCanonicalVoiceProvider,
+            "global_settings.voice_provider".to_string(),
+            format!("\"{}\" is a deprecated spelling of \"{}\"", provider, canonical),
+        )),
+        Some(_) => {}
+    }
+
+    if normalize_voice_provider(provider) == Some("system") {
+        if let Err(e) = system_tts::select(&config.global_settings.system_tts) {
+            findings.push(ValidationFinding::warning(
+                FindingCode::NoSpeechEngine,
+                "global_settings.system_tts".to_string(),
+                e,
+            ));
+        }
+    }
+
+    if normalize_voice_provider(provider) == Some("fish_audio") {
+        if let Err(e) = voice_provider::normalize_base_url(&config.global_settings.fish_audio_base_url) {
+            findings.push(ValidationFinding::error(
+                FindingCode::InvalidVoiceServer,
+                "global_settings.fish_audio_base_url".to_string(),
+                e,
+            ));
+        }
+        // Only whether a key is configured; reading it may need the keyring
+        let generates_voices = std::iter::once(&config.global_settings.event_sounds)
+            .chain(config.projects.iter().map(|project| &project.event_sounds))
+            .any(|sounds| sounds.iter().any(|(_, sound)| sound == "voice:project"));
+        if generates_voices && config.global_settings.fish_audio_api_key.is_none() {
+            findings.push(ValidationFinding::warning(
+                FindingCode::FishAudioKeyMissing,
+                "global_settings.fish_audio_api_key".to_string(),
+                "No Fish Audio API key; voices can't be generated until one is added (previews use the system voice)"
+                    .to_string(),
+            ));
+        }
+    }
+
+    if normalize_voice_provider(provider) == Some("openai_compatible") {
+        if let Err(e) = voice_provider::normalize_base_url(&config.global_settings.openai_tts.base_url) {
+            findings.push(ValidationFinding::error(
+                FindingCode::InvalidVoiceServer,
+                "global_settings.openai_tts.base_url".to_string(),
+                e,
+            ));
+        }
+    }
+
+    let target_lufs = config.global_settings.loudness.target_lufs;
+    if !(MIN_TARGET_LUFS..=MAX_TARGET_LUFS).contains(&target_lufs) {
+        findings.push(ValidationFinding::error(
+            FindingCode::InvalidLoudnessTarget,
+            "global_settings.loudness.target_lufs".to_string(),
+            format!("Target loudness must be between {} and {} LUFS", MIN_TARGET_LUFS, MAX_TARGET_LUFS),
+        ));
+    }
+
+    let speak_summary = &config.global_settings.speak_summary;
+    if speak_summary.max_words == 0 {
+        findings.push(ValidationFinding::error(
+            FindingCode::InvalidSpeakSummary,
+            "global_settings.speak_summary.max_words".to_string(),
+            "Spoken summaries need at least one word".to_string(),
+        ));
+    }
+    if speak_summary.timeout_ms == 0 {
+        findings.push(ValidationFinding::error(
+            FindingCode::InvalidSpeakSummary,
+            "global_settings.speak_summary.timeout_ms".to_string(),
+            "A timeout of 0 ms would never speak the summary".to_string(),
+        ));
+    }
+
+    if let Err(e) = Template::parse(&config.global_settings.voice_template) {
+        let code = match e {
+            TemplateError::UnknownVariable { .. } => FindingCode::UnknownTemplatePlaceholder,
+            TemplateError::Syntax { .. } => FindingCode::InvalidTemplate,
+        };
+        findings.push(ValidationFinding::error(
+            code,
+            "global_settings.voice_template".to_string(),
+            e.to_string(),
+        ));
+    }
+
+    for (i, rule) in config.global_settings.schedules.iter().enumerate() {
+        for (field, message) in rule.errors() {
+            findings.push(ValidationFinding::error(
+                FindingCode::InvalidSchedule,
+                format!("global_settings.schedules[{}].{}", i, field),
+                message,
+            ));
+        }
+    }
+
+    findings
+}
+
+pub fn has_errors(findings: &[ValidationFinding]) -> bool {
+    findings.iter().any(|f| f.severity == Severity::Error)
+}
+
+/// Whether `field` is `key`, inside it, or contains it; `projects[1]`
+/// touches `projects[1].path` and `global_settings` but not `projects[10]`.
+pub fn touches(field: &str, key: &str) -> bool {
+    let within = |inner: &str, outer: &str| {
+        inner.strip_prefix(outer).is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[']))
+    };
+    within(field, key) || within(key, field)
+}
+
+/// Check the sounds of enabled events; disabled events never reach the hook scripts.
+fn check_event_sounds(
+    sounds: &EventSounds,
+    enabled: &EventEnabled,
+    prefix: &str,
+    voice_dirs: &[PathBuf],
+    home: &Path,
+    findings: &mut Vec<ValidationFinding>,
+) {
+    for (event, sound) in sounds.iter() {
+        if !enabled.is_enabled(event) {
+            continue;
+        }
+        let event = event.key();
+        let field = format!("{}.{}", prefix, event);
+
+        if sound.starts_with("voice:") {
+            if !voice_dirs.iter().any(|dir| audio_format::find_clip(dir, event).is_some()) {
+                findings.push(ValidationFinding::warning(
+                    FindingCode::VoiceFileMissing,
+                    field,
+                    format!("No generated voice file for {} yet; save to generate it", event),
+                ));
+            }
+            continue;
+        }
+
+        if let Some(finding) = check_sound_file(sound, field, home) {
+            findings.push(finding);
+        }
+    }
+}
+
+fn check_sound_file(sound: &str, field: String, home: &Path) -> Option<ValidationFinding> {
+    if sound.is_empty() {
+        return None;
+    }
+
+    // The hook scripts expand ~/ the same way
+    let path = match sound.strip_prefix("~/") {
+        Some(rest) => home.join(rest),
+        None => PathBuf::from(sound),
+    };
+    if !path.is_file() {
+        return Some(ValidationFinding::error(
+            FindingCode::SoundNotFound,
+            field,
+            format!("Sound file {} does not exist", sound),
+        ));
+    }
+
+    if audio_format::sniff_file(&path).is_none() {
+        return Some(ValidationFinding::error(
+            FindingCode::SoundNotAudio,
+            field,
+            format!("{} is not a recognised audio file", sound),
+        ));
+    }
+
+    if let Err(e) = loudness::probe(&path) {
+        return Some(ValidationFinding::error(FindingCode::SoundNotDecodable, field, e));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write_tone(path: &Path, seconds: f32) {
+        let spec = hound::WavSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for i in 0..(8000.0 * seconds) as usize {
+            let sample = (i as f32 * 440.0 * std::f32::consts::TAU / 8000.0).sin() * 8000.0;
+            writer.write_sample(sample as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    fn code(sound: &Path) -> Option<FindingCode> {
+        check_sound_file(&sound.to_string_lossy(), "sound".to_string(), Path::new("/nonexistent-home")).map(|finding| finding.code)
+    }
+
+    #[test]
+    fn sound_files_must_decode() {
+        let dir = tempfile::tempdir().unwrap();
+        let good = dir.path().join("good.wav");
+        write_tone(&good, 0.5);
+        assert_eq!(code(&good), None);
+
+        assert_eq!(code(&dir.path().join("missing.wav")), Some(FindingCode::SoundNotFound));
+
+        let text = dir.path().join("notes.mp3");
+        fs::write(&text, "not audio").unwrap();
+        assert_eq!(code(&text), Some(FindingCode::SoundNotAudio));
+
+        // Header intact, half the samples gone
+        let truncated = dir.path().join("truncated.wav");
+        let bytes = fs::read(&good).unwrap();
+        fs::write(&truncated, &bytes[..bytes.len() / 2]).unwrap();
+        assert_eq!(code(&truncated), Some(FindingCode::SoundNotDecodable));
+
+        let corrupt = dir.path().join("corrupt.wav");
+        let mut bytes = bytes.clone();
+        bytes[12..44].fill(0xAB);
+        fs::write(&corrupt, &bytes).unwrap();
+        assert_eq!(code(&corrupt), Some(FindingCode::SoundNotDecodable));
+
+
+        // Read up to the last packet without decoding it all
+        assert_eq!(code(&Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/voices/stop.mp3")), None);
+    }
+
+    #[test]
+    fn touched_fields_match_on_whole_segments() {
+        assert!(touches("projects[1].path", "projects[1]"));
+        assert!(touches("global_settings.speak_summary", "global_settings.speak_summary.max_words"));
+        assert!(touches("global_settings.voice_provider", "global_settings.voice_provider"));
+        assert!(!touches("projects[10].path", "projects[1]"));
+        assert!(!touches("global_settings.voice_template", "global_settings.voice"));
+    }
+
+    #[test]
+    fn sound_paths_expand_home() {
+        let home = tempfile::tempdir().unwrap();
+        fs::create_dir(home.path().join("sounds")).unwrap();
+        write_tone(&home.path().join("sounds/tone.wav"), 0.2);
+
+        let finding = check_sound_file("~/sounds/tone.wav", "sound".to_string(), home.path());
+        assert!(finding.is_none(), "{:?}", finding);
+        let finding = check_sound_file("~/sounds/tone.wav", "sound".to_string(), Path::new("/nonexistent-home"));
+        assert_eq!(finding.map(|finding| finding.code), Some(FindingCode::SoundNotFound));
+    }
+
+    #[test]
+    fn fish_audio_without_a_key_warns_when_voices_are_generated() {
+        let missing_key = |config: &Config| {
+            validate(config).iter().any(|finding| finding.code == FindingCode::FishAudioKeyMissing)
+        };
+        let mut config = Config::default();
+        assert!(!missing_key(&config));
+
+        config.global_settings.event_sounds.set(crate::events::HookEvent::Stop, "voice:project".to_string());
+        assert!(missing_key(&config));
+
+        config.global_settings.fish_audio_api_key = Some(crate::secrets::SecretRef::Env { var: "FISH_KEY".to_string() });
+        assert!(!missing_key(&config));
+    }
+}
@@ -0,0 +1,417 @@
+// Content-addressed cache of generated speech (~/.claude/voice-cache/)
+//
+// Every clip is stored under a key hashing everything that changes the audio:
+// provider, voice, output format, the provider's speaking options (model,
+// server, engine and rate) and the text. Switching voice or provider therefore
+// misses the cache instead of replaying the old clip, and switching back is
+// free.
+//
+// index.json records each clip's size and when it was last played or
+// generated. Once the cache is larger than global_settings.voice_cache_max_mb
+// the least recently used clips are deleted. Clips and the index are only
+// written under the lock from atomic_write, so concurrent generation is safe.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::atomic_write;
+use crate::audio_format::{self, AudioFormat};
+use crate::voice_provider::{ProviderError, VoiceProvider};
+use crate::{get_speech_cache_dir, hash_string, GlobalSettings};
+
+const INDEX_FILE: &str = "index.json";
+
+pub fn default_max_mb() -> u32 {
+    100
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CacheEntry {
+    /// File name inside the cache directory
+    pub file: String,
+    pub provider: String,
+    pub voice: Option<String>,
+    pub format: AudioFormat,
+    pub text: String,
+    pub bytes: u64,
+    /// Unix seconds
+    pub created_at: i64,
+    pub last_used: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct Index {
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct ProviderStats {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct CacheStats {
+    pub dir: PathBuf,
+    pub entries: usize,
+    pub bytes: u64,
+    pub max_bytes: u64,
+    pub by_provider: BTreeMap<String, ProviderStats>,
+    /// Unix seconds of the least recently used clip
+    pub oldest_use: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct GcReport {
+    /// Clips evicted to get under the size cap, or cleared
+    pub removed: usize,
+    /// Files in the directory the index didn't know about, and index entries
+    /// whose file was gone
+    pub orphans: usize,
+    pub freed_bytes: u64,
+}
+
+/// Where a clip came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedVoice {
+    pub path: PathBuf,
+    pub format: AudioFormat,
+    /// False when it was just generated
+    pub hit: bool,
+}
+
+pub struct VoiceCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl VoiceCache {
+    pub fn new(dir: PathBuf, max_mb: u32) -> Self {
+        VoiceCache { dir, max_bytes: u64::from(max_mb) * 1024 * 1024 }
+    }
+
+    /// The cache in ~/.claude/voice-cache with the configured size cap.
+    pub fn for_settings(settings: &GlobalSettings) -> Self {
+        VoiceCache::new(get_speech_cache_dir(), settings.voice_cache_max_mb)
+    }
+
+    /// Key for `text` spoken by `provider` with `voice`.
+    pub fn key(provider: &dyn VoiceProvider, text: &str, voice: Option<&str>) -> String {
+        hash_string(&format!(
+            "{}\0{}\0{}\0{}\0{}",
+            provider.id(),
+            voice.unwrap_or(""),
+            provider.output_format().extension(),
+            provider.cache_options(),
+            text
+        ))
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.dir.join(INDEX_FILE)
+    }
+
+    fn read_index(&self) -> Index {
+        fs::read_to_string(self.index_path())
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Change the index under its lock.
+    fn update<T>(&self, change: impl FnOnce(&mut Index) -> T) -> Result<T, String> {
+        let path = self.index_path();
+        let _lock = atomic_write::lock(&path).map_err(|e| format!("Failed to lock voice cache: {}", e))?;
+        let mut index = self.read_index();
+        let result = change(&mut index);
+        let json = serde_json::to_vec_pretty(&index).map_err(|e| format!("Failed to serialize voice cache index: {}", e))?;
+        atomic_write::write_atomic(&path, &json).map_err(|e| format!("Failed to write voice cache index: {}", e))?;
+        Ok(result)
+    }
+
+    /// The cached clip for `key`, marking it as used.
+    pub fn get(&self, key: &str) -> Result<Option<CachedVoice>, String> {
+        if !self.read_index().entries.contains_key(key) {
+            return Ok(None);
+        }
+        let dir = self.dir.clone();
+        self.update(|index| {
+            let entry = index.entries.get_mut(key)?;
+            let path = dir.join(&entry.file);
+            if !path.is_file() {
+                index.entries.remove(key);
+                return None;
+            }
+            entry.last_used = chrono::Utc::now().timestamp();
+            Some(CachedVoice { path, format: entry.format, hit: true })
+        })
+    }
+
+    /// Store a clip under `key`, evicting old clips if the cache is over its cap.
+    pub fn insert(
+        &self,
+        key: &str,
+        provider: &dyn VoiceProvider,
+        text: &str,
+        voice: Option<&str>,
+        bytes: &[u8],
+    ) -> Result<CachedVoice, String> {
+        // Stored as what the bytes are, not what the provider was asked for
+        let format = audio_format::sniff(bytes).unwrap_or_else(|| provider.output_format());
+        let file = format!("{}.{}", key, format.extension());
+        let path = self.dir.join(&file);
+
+        let now = chrono::Utc::now().timestamp();
+        let entry = CacheEntry {
+            file,
+            provider: provider.id().to_string(),
+            voice: voice.map(str::to_string),
+            format,
+            text: text.to_string(),
+            bytes: bytes.len() as u64,
+            created_at: now,
+            last_used: now,
+        };
+        let max_bytes = self.max_bytes;
+        let dir = self.dir.clone();
+        // Under the index lock, so readers never see a half-written clip and
+        // an eviction running elsewhere can't race the new entry
+        self.update(|index| {
+            atomic_write::write_atomic(&path, bytes).map_err(|e| format!("Failed to write voice cache file: {}", e))?;
+            index.entries.insert(key.to_string(), entry);
+            evict(index, &dir, max_bytes, Some(key));
+            Ok::<_, String>(())
+        })??;
+        Ok(CachedVoice { path, format, hit: false })
+    }
+
+    /// The cached clip, or a new one from `provider`.
+    pub async fn synthesize(
+        &self,
+        provider: &dyn VoiceProvider,
+        text: &str,
+        voice: Option<&str>,
+    ) -> Result<CachedVoice, ProviderError> {
+        let key = VoiceCache::key(provider, text, voice);
+        if let Some(cached) = self.get(&key)? {
+            return Ok(cached);
+        }
+        let bytes = provider.synthesize(text, voice).await?;
+        Ok(self.insert(&key, provider, text, voice, &bytes)?)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let index = self.read_index();
+        let mut by_provider: BTreeMap<String, ProviderStats> = BTreeMap::new();
+        for entry in index.entries.values() {
+            let stats = by_provider.entry(entry.provider.clone()).or_default();
+            stats.entries += 1;
+            stats.bytes += entry.bytes;
+        }
+        CacheStats {
+            dir: self.dir.clone(),
+            entries: index.entries.len(),
+            bytes: index.entries.values().map(|e| e.bytes).sum(),
+            max_bytes: self.max_bytes,
+            by_provider,
+            oldest_use: index.entries.values().map(|e| e.last_used).min(),
+        }
+    }
+
+    /// Evict down to the size cap and reconcile the index with the directory.
+    pub fn gc(&self) -> Result<GcReport, String> {
+        let dir = self.dir.clone();
+        let max_bytes = self.max_bytes;
+        self.update(|index| {
+            let mut report = GcReport::default();
+            index.entries.retain(|_, entry| {
+                let exists = dir.join(&entry.file).is_file();
+                if !exists {
+                    report.orphans += 1;
+                }
+                exists
+            });
+
+            let known: Vec<&str> = index.entries.values().map(|e| e.file.as_str()).collect();
+            for path in cache_files(&dir) {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                if !known.contains(&name.as_ref()) {
+                    report.freed_bytes += fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                    if fs::remove_file(&path).is_ok() {
+                        report.orphans += 1;
+                    }
+                }
+            }
+
+            let (removed, freed) = evict(index, &dir, max_bytes, None);
+            report.removed = removed;
+            report.freed_bytes += freed;
+            report
+        })
+    }
+
+    /// Delete every clip.
+    pub fn clear(&self) -> Result<GcReport, String> {
+        let dir = self.dir.clone();
+        self.update(|index| {
+            let mut report = GcReport::default();
+            for path in cache_files(&dir) {
+                let bytes = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                if fs::remove_file(&path).is_ok() {
+                    report.removed += 1;
+                    report.freed_bytes += bytes;
+                }
+            }
+            index.entries.clear();
+            report
+        })
+    }
+}
+
+/// Audio files in the cache directory (everything but the index and its lock).
+fn cache_files(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.is_file())
+                .filter(|path| {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    !name.starts_with(INDEX_FILE) && !name.starts_with('.')
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Delete least recently used clips until the cache fits in `max_bytes`,
+/// never the one being added. Returns how many clips and bytes went.
+fn evict(index: &mut Index, dir: &Path, max_bytes: u64, keep: Option<&str>) -> (usize, u64) {
+    let mut total: u64 = index.entries.values().map(|e| e.bytes).sum();
+    let mut by_age: Vec<(i64, String)> = index
+        .entries
+        .iter()
+        .filter(|(key, _)| Some(key.as_str()) != keep)
+        .map(|(key, entry)| (entry.last_used, key.clone()))
+        .collect();
+    by_age.sort();
+
+    let (mut removed, mut freed) = (0, 0);
+    for (_, key) in by_age {
+        if total <= max_bytes {
+            break;
+        }
+        if let Some(entry) = index.entries.remove(&key) {
+            let _ = fs::remove_file(dir.join(&entry.file));
+            total -= entry.bytes;
+            freed += entry.bytes;
+            removed += 1;
+        }
+    }
+    (removed, freed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice_provider::fake::FakeProvider;
+    use std::sync::atomic::Ordering;
+
+    fn provider(options: &str) -> FakeProvider {
+        FakeProvider { options: options.to_string(), ..Default::default() }
+    }
+
+    /// Pretend `key` was last used at `when`.
+    fn touch(cache: &VoiceCache, key: &str, when: i64) {
+        cache.update(|index| index.entries.get_mut(key).unwrap().last_used = when).unwrap();
+    }
+
+    #[test]
+    fn keys_cover_everything_that_changes_the_audio() {
+        let fast = provider("rate=1.2");
+        let key = VoiceCache::key(&fast, "Build finished", Some("nova"));
+
+        assert_eq!(key, VoiceCache::key(&provider("rate=1.2"), "Build finished", Some("nova")));
+        assert_ne!(key, VoiceCache::key(&fast, "Build finished", Some("onyx")));
+        assert_ne!(key, VoiceCache::key(&fast, "Build finished", None));
+        assert_ne!(key, VoiceCache::key(&fast, "Build finished.", Some("nova")));
+        assert_ne!(key, VoiceCache::key(&provider("rate=1.0"), "Build finished", Some("nova")));
+        // Fields can't run into each other
+        assert_ne!(
+            VoiceCache::key(&fast, "b", Some("a")),
+            VoiceCache::key(&fast, "", Some("a\0b"))
+        );
+    }
+
+    #[tokio::test]
+    async fn repeated_text_is_served_from_the_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = VoiceCache::new(dir.path().to_path_buf(), 1);
+        let fake = provider("");
+
+        let first = cache.synthesize(&fake, "hello", None).await.unwrap();
+        let second = cache.synthesize(&fake, "hello", None).await.unwrap();
+
+        assert_eq!((first.hit, second.hit), (false, true));
+        assert_eq!(first.path, second.path);
+        assert_eq!(fake.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(fs::read(&second.path).unwrap(), b"hello");
+
+        // A clip deleted behind the cache's back is generated again
+        fs::remove_file(&first.path).unwrap();
+        assert!(!cache.synthesize(&fake, "hello", None).await.unwrap().hit);
+        assert_eq!(fake.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn least_recently_used_clips_go_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = VoiceCache::new(dir.path().to_path_buf(), 1);
+        cache.max_bytes = 10;
+        let fake = provider("");
+        let clip = |text: &str| (VoiceCache::key(&fake, text, None), text.to_string());
+        let (a, b, c) = (clip("aaaa"), clip("bbbb"), clip("cccc"));
+
+        cache.insert(&a.0, &fake, &a.1, None, a.1.as_bytes()).unwrap();
+        cache.insert(&b.0, &fake, &b.1, None, b.1.as_bytes()).unwrap();
+        touch(&cache, &a.0, 200);
+        touch(&cache, &b.0, 100);
+
+        // Over the cap: b was used longest ago, and the new clip always stays
+        cache.insert(&c.0, &fake, &c.1, None, c.1.as_bytes()).unwrap();
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.bytes), (2, 8));
+        assert!(cache.get(&b.0).unwrap().is_none());
+        assert!(!dir.path().join(format!("{}.wav", b.0)).exists());
+        assert!(cache.get(&a.0).unwrap().is_some());
+
+        // A clip bigger than the cap on its own is still kept
+        let big = clip("a clip longer than the cap");
+        cache.insert(&big.0, &fake, &big.1, None, big.1.as_bytes()).unwrap();
+        assert_eq!(cache.stats().entries, 1);
+        assert!(cache.get(&big.0).unwrap().is_some());
+    }
+
+    #[test]
+    fn gc_reconciles_the_index_with_the_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = VoiceCache::new(dir.path().to_path_buf(), 1);
+        let fake = provider("");
+        let (kept, lost) = (VoiceCache::key(&fake, "kept", None), VoiceCache::key(&fake, "lost", None));
+        cache.insert(&kept, &fake, "kept", None, b"kept").unwrap();
+        let lost_clip = cache.insert(&lost, &fake, "lost", None, b"lost").unwrap();
+        fs::remove_file(lost_clip.path).unwrap();
+        fs::write(dir.path().join("stray.mp3"), b"stray").unwrap();
+
+        let report = cache.gc().unwrap();
+        assert_eq!((report.removed, report.orphans, report.freed_bytes), (0, 2, 5));
+        assert_eq!(cache.stats().entries, 1);
+
+        let report = cache.clear().unwrap();
+        assert_eq!((report.removed, report.freed_bytes), (1, 4));
+        assert_eq!(cache.stats().entries, 0);
+        assert!(dir.path().join(INDEX_FILE).exists());
+    }
+}
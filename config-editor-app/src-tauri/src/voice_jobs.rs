@@ -0,0 +1,345 @@
+// Voice generation runs (generate_voice_notifications)
+//
+// A run is a queue of clips to write. Up to `concurrency` clips are generated
+// at once, through the voice cache, and a failure only fails that clip. The
+// queue is saved to ~/.claude/voice-generation-queue.json after every clip,
+// so a run that failed or was cancelled can be resumed with only the clips
+// that didn't finish. The file is removed once every clip is done.
+//
+// Cancelling stops new clips from starting; the ones already being generated
+// finish, so no half-written files are left behind.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::task::JoinSet;
+
+use crate::atomic_write;
+use crate::audio_format;
+use crate::loudness;
+use crate::voice_cache::VoiceCache;
+use crate::voice_provider::{ProviderError, ProviderErrorCode, VoiceProvider};
+
+/// Clips generated at once unless the caller asks for another number.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum JobStatus {
+    #[default]
+    Pending,
+    Done {
+        /// Copied from the voice cache rather than generated
+        cached: bool,
+    },
+    Failed {
+        error: String,
+        /// Missing from queues saved before failures had codes
+        #[serde(default)]
+        code: ProviderErrorCode,
+    },
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct VoiceJob {
+    /// Where the clip is written, e.g. ~/.claude/voices/global/stop; the
+    /// extension is set to the clip's actual format once it's done
+    pub file: PathBuf,
+    pub text: String,
+    #[serde(default)]
+    pub status: JobStatus,
+}
+
+impl VoiceJob {
+    pub fn new(file: PathBuf, text: String) -> Self {
+        VoiceJob { file, text, status: JobStatus::Pending }
+    }
+
+    fn is_done(&self) -> bool {
+        matches!(self.status, JobStatus::Done { .. })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct VoiceQueue {
+    pub jobs: Vec<VoiceJob>,
+}
+
+impl VoiceQueue {
+    pub fn new(jobs: Vec<VoiceJob>) -> Self {
+        VoiceQueue { jobs }
+    }
+
+    /// The queue left by an unfinished run, if any.
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map(Some)
+                .map_err(|e| format!("Failed to read voice generation queue: {}", e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read voice generation queue: {}", e)),
+        }
+    }
+
+    /// Write the queue, or remove the file once nothing is left to do.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if self.jobs.iter().all(VoiceJob::is_done) {
+            return match fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    Err(format!("Failed to remove voice generation queue: {}", e))
+                }
+                _ => Ok(()),
+            };
+        }
+        let json = serde_json::to_vec_pretty(self).map_err(|e| format!("Failed to serialize voice generation queue: {}", e))?;
+        let _lock = atomic_write::lock(path).map_err(|e| format!("Failed to lock voice generation queue: {}", e))?;
+        atomic_write::write_atomic(path, &json).map_err(|e| format!("Failed to write voice generation queue: {}", e))
+    }
+}
+
+/// Sent after each clip finishes.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Progress {
+    pub total: usize,
+    /// Clips done or failed so far, including ones finished by an earlier run
+    /// of a resumed queue
+    pub finished: usize,
+    pub failed: usize,
+    pub job: VoiceJob,
+}
+
+/// Totals over the whole queue, including clips finished by an earlier run.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct RunReport {
+    pub total: usize,
+    pub generated: usize,
+    pub cached: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    pub jobs: Vec<VoiceJob>,
+}
+
+impl RunReport {
+    fn new(jobs: &[VoiceJob]) -> Self {
+        let mut report = RunReport { total: jobs.len(), jobs: jobs.to_vec(), ..Default::default() };
+        for job in jobs {
+            match job.status {
+                JobStatus::Done { cached: false } => report.generated += 1,
+                JobStatus::Done { cached: true } => report.cached += 1,
+                JobStatus::Failed { .. } => report.failed += 1,
+                JobStatus::Cancelled | JobStatus::Pending => report.cancelled += 1,
+            }
+        }
+        report
+    }
+}
+
+pub struct VoiceRunner {
+    pub provider: Arc<dyn VoiceProvider>,
+    pub voice: Option<String>,
+    pub cache: Arc<VoiceCache>,
+    pub concurrency: usize,
+    /// Set to stop starting new clips
+    pub cancel: Arc<AtomicBool>,
+    /// Where the queue is saved after every clip; None keeps it in memory
+    pub queue_path: Option<PathBuf>,
+    /// LUFS to normalize clips to; None leaves them as generated
+    pub loudness_target: Option<f64>,
+}
+
+impl VoiceRunner {
+    /// Generate every clip in `queue` that isn't done yet.
+    pub async fn run(&self, queue: &mut VoiceQueue, on_progress: impl Fn(&Progress)) -> RunReport {
+        let mut waiting: VecDeque<usize> = (0..queue.jobs.len()).filter(|&i| !queue.jobs[i].is_done()).collect();
+        for &i in &waiting {
+            queue.jobs[i].status = JobStatus::Pending;
+        }
+        let total = queue.jobs.len();
+        let mut finished = total - waiting.len();
+        let mut failed = 0;
+        let mut running = JoinSet::new();
+        let mut spawned = HashMap::new();
+
+        loop {
+            while running.len() < self.concurrency.max(1) && !self.cancel.load(Ordering::SeqCst) {
+                let Some(i) = waiting.pop_front() else { break };
+                let job = queue.jobs[i].clone();
+                let (provider, cache, voice) = (self.provider.clone(), self.cache.clone(), self.voice.clone());
+                let loudness_target = self.loudness_target;
+                let task = running.spawn(async move {
+                    generate(&job, provider.as_ref(), &cache, voice.as_deref(), loudness_target).await
+                });
+                spawned.insert(task.id(), i);
+            }
+
+            let Some(joined) = running.join_next_with_id().await else { break };
+            // A panicking clip fails like any other, so progress still reaches
+            // the total and a resumed run retries it
+            let (i, result) = match joined {
+                Ok((id, result)) => (spawned[&id], result),
+                Err(e) => (spawned[&e.id()], Err(format!("Voice generation crashed: {}", e).into())),
+            };
+            queue.jobs[i].status = match result {
+                Ok((file, cached)) => {
+                    queue.jobs[i].file = file;
+                    JobStatus::Done { cached }
+                }
+                Err(error) => {
+                    failed += 1;
+                    JobStatus::Failed { error: error.message, code: error.code }
+                }
+            };
+            finished += 1;
+            self.save(queue);
+            on_progress(&Progress { total, finished, failed, job: queue.jobs[i].clone() });
+        }
+
+        for job in &mut queue.jobs {
+            if job.status == JobStatus::Pending {
+                job.status = JobStatus::Cancelled;
+            }
+        }
+        self.save(queue);
+        RunReport::new(&queue.jobs)
+    }
+
+    fn save(&self, queue: &VoiceQueue) {
+        if let Some(path) = &self.queue_path {
+            if let Err(e) = queue.save(path) {
+                println!("{}", e);
+            }
+        }
+    }
+}
+
+/// Write one clip, returning where it went and whether it came from the cache.
+/// Also used for clips rendered when a hook fires.
+pub async fn generate(
+    job: &VoiceJob,
+    provider: &dyn VoiceProvider,
+    cache: &VoiceCache,
+    voice: Option<&str>,
+    loudness_target: Option<f64>,
+) -> Result<(PathBuf, bool), ProviderError> {
+    let cached = cache.synthesize(provider, &job.text, voice).await?;
+    let mut file = audio_format::install_clip(&cached.path, &job.file)?;
+    if let Some(target) = loudness_target {
+        // An undecodable clip still plays as generated
+        let path = file.clone();
+        match tokio::task::spawn_blocking(move || loudness::normalize_file(&path, target)).await {
+            Ok(Ok(normalized)) => file = normalized.path,
+            Ok(Err(e)) => println!("{}", e),
+            Err(e) => println!("Failed to normalize {}: {}", file.display(), e),
+        }
+    }
+    Ok((file, cached.hit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice_provider::fake::FakeProvider;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    struct Setup {
+        dir: tempfile::TempDir,
+        provider: Arc<FakeProvider>,
+        runner: VoiceRunner,
+    }
+
+    fn setup(concurrency: usize) -> Setup {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = Arc::new(FakeProvider { delay: Duration::from_millis(20), ..Default::default() });
+        provider.fail.store(true, Ordering::SeqCst);
+        let runner = VoiceRunner {
+            provider: provider.clone(),
+            voice: None,
+            cache: Arc::new(VoiceCache::new(dir.path().join("cache"), 10)),
+            concurrency,
+            cancel: Arc::new(AtomicBool::new(false)),
+            queue_path: Some(dir.path().join("queue.json")),
+            loudness_target: None,
+        };
+        Setup { dir, provider, runner }
+    }
+
+    fn queue(dir: &Path, texts: &[&str]) -> VoiceQueue {
+        VoiceQueue::new(
+            texts
+                .iter()
+                .enumerate()
+                .map(|(i, text)| VoiceJob::new(dir.join("voices").join(format!("clip{}", i)), text.to_string()))
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn failures_and_panics_only_fail_their_clip() {
+        let Setup { dir, provider, runner } = setup(2);
+        let mut queue = queue(dir.path(), &["one", "fail two", "panic three", "four", "five"]);
+        let progress = Mutex::new(Vec::new());
+
+        let report = runner.run(&mut queue, |p| progress.lock().unwrap().push((p.finished, p.failed))).await;
+
+        assert_eq!((report.total, report.generated, report.failed, report.cancelled), (5, 3, 2, 0));
+        assert_eq!(provider.peak.load(Ordering::SeqCst), 2);
+        let progress = progress.into_inner().unwrap();
+        assert_eq!(progress.len(), 5);
+        assert_eq!(progress.last(), Some(&(5, 2)));
+        assert!(matches!(&queue.jobs[2].status, JobStatus::Failed { error, .. } if error.contains("crashed")));
+        assert_eq!(queue.jobs[0].file, dir.path().join("voices/clip0.wav"));
+        assert_eq!(fs::read(&queue.jobs[3].file).unwrap(), b"four");
+    }
+
+    #[tokio::test]
+    async fn resuming_only_runs_unfinished_clips() {
+        let Setup { dir, provider, runner } = setup(4);
+        let queue_path = runner.queue_path.clone().unwrap();
+        let mut first = queue(dir.path(), &["one", "fail two", "three"]);
+        runner.run(&mut first, |_| {}).await;
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 3);
+
+        let mut saved = VoiceQueue::load(&queue_path).unwrap().unwrap();
+        assert_eq!(saved, first);
+        provider.fail.store(false, Ordering::SeqCst);
+        let finished = Mutex::new(Vec::new());
+        let report = runner.run(&mut saved, |p| finished.lock().unwrap().push(p.finished)).await;
+
+        // Done clips count towards progress but aren't generated again
+        assert_eq!(finished.into_inner().unwrap(), [3]);
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 4);
+        assert_eq!((report.generated, report.failed), (3, 0));
+        assert!(VoiceQueue::load(&queue_path).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn cancelling_lets_running_clips_finish() {
+        let Setup { dir, provider, runner } = setup(2);
+        let queue_path = runner.queue_path.clone().unwrap();
+        let mut queue = queue(dir.path(), &["one", "two", "three", "four", "five"]);
+        let cancel = runner.cancel.clone();
+
+        let report = runner.run(&mut queue, |_| cancel.store(true, Ordering::SeqCst)).await;
+
+        // The first clip cancels the run while the second is still generating
+        assert_eq!((report.generated, report.cancelled), (2, 3));
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+        assert!(queue.jobs[2..].iter().all(|job| job.status == JobStatus::Cancelled));
+        assert_eq!(VoiceQueue::load(&queue_path).unwrap(), Some(queue));
+    }
+
+    #[test]
+    fn failures_keep_their_code_and_old_queues_still_load() {
+        let status = JobStatus::Failed { error: "bad key".to_string(), code: ProviderErrorCode::Auth };
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json, serde_json::json!({ "state": "failed", "error": "bad key", "code": "auth" }));
+
+        let old: JobStatus = serde_json::from_str(r#"{ "state": "failed", "error": "bad key" }"#).unwrap();
+        assert_eq!(old, JobStatus::Failed { error: "bad key".to_string(), code: ProviderErrorCode::Other });
+    }
+}
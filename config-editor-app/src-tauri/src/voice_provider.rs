@@ -0,0 +1,597 @@
+// Text-to-speech backends for generated voices (global_settings.voice_provider)
+//
+//   fish_audio         Fish Audio cloud API; needs fish_audio_api_key; see
+//                      fish_audio.rs
+//   openai_compatible  any server implementing OpenAI's POST /v1/audio/speech:
+//                      OpenAI itself, or a self-hosted server (Kokoro-FastAPI,
+//                      openedai-speech, ...) at global_settings.openai_tts.base_url
+//   system             an offline speech engine: `say` on macOS, Piper, espeak-ng
+//                      or pico2wave elsewhere; see system_tts.rs
+//
+// `from_settings` picks the provider for a config; Fish Audio without an API
+// key is an error, so generated voices never silently change. Previews go
+// through `preview_provider`, which speaks with the system voice instead, as
+// they always have.
+
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::audio_format::AudioFormat;
+use crate::fish_audio::{FishAudio, FishAudioError};
+use crate::secrets::SecretRef;
+use crate::validation::normalize_voice_provider;
+use crate::system_tts::SystemTts;
+use crate::GlobalSettings;
+
+pub const OPENAI_DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+const OPENAI_DEFAULT_VOICE: &str = "alloy";
+/// Voices every OpenAI-compatible server is expected to accept, for servers
+/// that can't list their own
+const OPENAI_VOICES: &[&str] = &["alloy", "ash", "coral", "echo", "fable", "nova", "onyx", "sage", "shimmer"];
+pub const FISH_AUDIO_KEY_MISSING: &str = "Fish Audio API key required; add one or choose another voice provider";
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct VoiceInfo {
+    /// Value for voice_id
+    pub id: String,
+    pub name: String,
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct CostEstimate {
+    pub characters: usize,
+    /// US dollars; None when the provider's price isn't known (e.g. self-hosted)
+    pub usd: Option<f64>,
+}
+
+/// Why a provider call failed. `code` doesn't change with the wording of
+/// `message`, so the editor can act on it (e.g. ask for a new key on auth).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProviderError {
+    pub code: ProviderErrorCode,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderErrorCode {
+    /// Missing, wrong or revoked API key
+    Auth,
+    /// The account has no credit left
+    QuotaExhausted,
+    /// voice_id doesn't name a voice
+    InvalidVoice,
+    /// Still rate limited after retrying
+    RateLimited,
+    /// Still a server error after retrying
+    Server,
+    /// The server couldn't be reached
+    Network,
+    /// Anything else, including local failures (cache, files)
+    #[default]
+    Other,
+}
+
+impl ProviderError {
+    pub fn new(code: ProviderErrorCode, message: impl Into<String>) -> Self {
+        ProviderError { code, message: message.into() }
+    }
+
+    /// Code for an HTTP status a provider answered with.
+    fn from_status(status: reqwest::StatusCode, message: String) -> Self {
+        let code = match status.as_u16() {
+            401 | 403 => ProviderErrorCode::Auth,
+            402 => ProviderErrorCode::QuotaExhausted,
+            429 => ProviderErrorCode::RateLimited,
+            500..=599 => ProviderErrorCode::Server,
+            _ => ProviderErrorCode::Other,
+        };
+        ProviderError::new(code, message)
+    }
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<String> for ProviderError {
+    fn from(message: String) -> Self {
+        ProviderError::new(ProviderErrorCode::Other, message)
+    }
+}
+
+impl From<ProviderError> for String {
+    fn from(error: ProviderError) -> Self {
+        error.message
+    }
+}
+
+impl From<FishAudioError> for ProviderError {
+    fn from(error: FishAudioError) -> Self {
+        let code = match &error {
+            FishAudioError::Auth { .. } => ProviderErrorCode::Auth,
+            FishAudioError::QuotaExhausted { .. } => ProviderErrorCode::QuotaExhausted,
+            FishAudioError::InvalidVoice { .. } => ProviderErrorCode::InvalidVoice,
+            FishAudioError::RateLimited { .. } => ProviderErrorCode::RateLimited,
+            FishAudioError::Server { .. } => ProviderErrorCode::Server,
+            FishAudioError::Network { .. } => ProviderErrorCode::Network,
+            FishAudioError::Unexpected { .. } => ProviderErrorCode::Other,
+        };
+        ProviderError::new(code, error.to_string())
+    }
+}
+
+#[async_trait]
+pub trait VoiceProvider: Send + Sync {
+    /// voice_provider value this backend is chosen by
+    fn id(&self) -> &'static str;
+
+    /// Format of the bytes `synthesize` returns
+    fn output_format(&self) -> AudioFormat;
+
+    /// Speak `text` with `voice` (a voice_id), or the provider's default voice.
+    async fn synthesize(&self, text: &str, voice: Option<&str>) -> Result<Vec<u8>, ProviderError>;
+
+    async fn list_voices(&self) -> Result<Vec<VoiceInfo>, ProviderError>;
+
+    /// What generating all of `texts` would cost.
+    fn estimate_cost(&self, texts: &[String]) -> CostEstimate;
+
+    /// Settings besides the voice that change the audio (model, server,
+    /// speaking rate), for voice_cache keys.
+    fn cache_options(&self) -> String {
+        String::new()
+    }
+}
+
+/// Settings for the openai_compatible provider.
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema, PartialEq)]
+pub struct OpenAiTtsSettings {
+    /// API root including /v1, e.g. http://localhost:8880/v1 for a local server
+    #[serde(default = "default_openai_base_url")]
+    pub base_url: String,
+    /// tts-1, tts-1-hd, or whatever model the server expects
+    #[serde(default = "default_openai_model")]
+    pub model: String,
+}
+
+impl Default for OpenAiTtsSettings {
+    fn default() -> Self {
+        OpenAiTtsSettings { base_url: default_openai_base_url(), model: default_openai_model() }
+    }
+}
+
+fn default_openai_base_url() -> String {
+    OPENAI_DEFAULT_BASE_URL.to_string()
+}
+
+fn default_openai_model() -> String {
+    "tts-1".to_string()
+}
+
+/// The provider `settings` select. `api_key` replaces the one from the
+/// secret store, e.g. a key typed into the editor but not saved yet.
+pub fn from_settings(settings: &GlobalSettings, api_key: Option<String>) -> Result<Box<dyn VoiceProvider>, String> {
+    match normalize_voice_provider(&settings.voice_provider) {
+        Some("fish_audio") => match api_key_or(api_key, settings.fish_audio_api_key.as_ref())? {
+            Some(key) => Ok(Box::new(FishAudio::new(key, &settings.fish_audio_base_url))),
+            None => Err(FISH_AUDIO_KEY_MISSING.to_string()),
+        },
+        Some("openai_compatible") => {
+            let key = api_key_or(api_key, settings.openai_api_key.as_ref())?;
+            Ok(Box::new(OpenAiCompatible::new(&settings.openai_tts, key)?))
+        }
+        Some("system") => Ok(Box::new(SystemTts::new(&settings.system_tts))),
+        _ => Err(format!("Unknown voice provider \"{}\"", settings.voice_provider)),
+    }
+}
+
+/// The provider voice previews use: like `from_settings`, but Fish Audio
+/// without an API key previews with the system voice.
+pub fn preview_provider(settings: &GlobalSettings, api_key: Option<String>) -> Result<Box<dyn VoiceProvider>, String> {
+    let fish_audio = normalize_voice_provider(&settings.voice_provider) == Some("fish_audio");
+    match api_key_or(api_key, settings.fish_audio_api_key.as_ref().filter(|_| fish_audio))? {
+        None if fish_audio => Ok(Box::new(SystemTts::new(&settings.system_tts))),
+        key => from_settings(settings, key),
+    }
+}
+
+/// voice_id when it belongs to `provider`; a Fish Audio voice means nothing
+/// to the system voice previews fall back to.
+pub fn configured_voice<'a>(settings: &'a GlobalSettings, provider: &dyn VoiceProvider) -> Option<&'a str> {
+    if normalize_voice_provider(&settings.voice_provider) == Some(provider.id()) {
+        settings.voice_id.as_deref()
+    } else {
+        None
+    }
+}
+
+/// The key a SecretRef points at; None when there is no reference or the
+/// store has no such key.
+pub fn resolve_api_key(reference: Option<&SecretRef>) -> Result<Option<String>, String> {
+    match reference {
+        Some(reference) => reference.resolve().map_err(|e| format!("Failed to read API key: {}", e)),
+        None => Ok(None),
+    }
+}
+
+/// `api_key` if given, else the stored key.
+fn api_key_or(api_key: Option<String>, reference: Option<&SecretRef>) -> Result<Option<String>, String> {
+    match api_key {
+        Some(key) => Ok(Some(key)),
+        None => resolve_api_key(reference),
+    }
+}
+
+/// HTTP client shared by the cloud providers, so connections are reused
+/// across clips.
+pub fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(60))
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+/// `what` failed with the status and body of `response`.
+async fn error_body(what: &str, response: reqwest::Response) -> ProviderError {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    ProviderError::from_status(status, format!("{} {}: {}", what, status, body.trim()))
+}
+
+fn send_error(url: &str, e: reqwest::Error) -> ProviderError {
+    ProviderError::new(ProviderErrorCode::Network, format!("Failed to call {}: {}", url, e))
+}
+
+// ===== OpenAI-compatible =====
+
+pub struct OpenAiCompatible {
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl OpenAiCompatible {
+    pub fn new(settings: &OpenAiTtsSettings, api_key: Option<String>) -> Result<Self, String> {
+        let base_url = normalize_base_url(&settings.base_url)?;
+        Ok(OpenAiCompatible { base_url, model: settings.model.clone(), api_key, client: http_client().clone() })
+    }
+
+    fn request(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        // Local servers usually run without authentication
+        match &self.api_key {
+            Some(key) => builder.bearer_auth(key),
+            None => builder,
+        }
+    }
+
+    /// Price per million characters, for OpenAI's own models.
+    fn price(&self) -> Option<f64> {
+        if !self.base_url.starts_with(OPENAI_DEFAULT_BASE_URL) {
+            return None;
+        }
+        match self.model.as_str() {
+            "tts-1" => Some(15.0),
+            "tts-1-hd" => Some(30.0),
+            _ => None,
+        }
+    }
+}
+
+/// Accept the base URL with or without the trailing /v1.
+pub fn normalize_base_url(base_url: &str) -> Result<String, String> {
+    let trimmed = base_url.trim().trim_end_matches('/');
+    let url = reqwest::Url::parse(trimmed).map_err(|e| format!("\"{}\" is not a URL: {}", base_url, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("\"{}\" must start with http:// or https://", base_url));
+    }
+    Ok(if trimmed.ends_with("/v1") { trimmed.to_string() } else { format!("{}/v1", trimmed) })
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum VoiceList {
+    Names { voices: Vec<String> },
+    Objects { voices: Vec<VoiceObject> },
+}
+
+#[derive(Deserialize)]
+struct VoiceObject {
+    id: String,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+#[async_trait]
+impl VoiceProvider for OpenAiCompatible {
+    fn id(&self) -> &'static str {
+        "openai_compatible"
+    }
+
+    fn output_format(&self) -> AudioFormat {
+        AudioFormat::Mp3
+    }
+
+    async fn synthesize(&self, text: &str, voice: Option<&str>) -> Result<Vec<u8>, ProviderError> {
+        let url = format!("{}/audio/speech", self.base_url);
+        let response = self
+            .request(self.client.post(&url))
+            .json(&serde_json::json!({
+                "model": self.model,
+                "input": text,
+                "voice": voice.unwrap_or(OPENAI_DEFAULT_VOICE),
+                "response_format": "mp3"
+            }))
+            .send()
+            .await
+            .map_err(|e| send_error(&url, e))?;
+
+        if !response.status().is_success() {
+            return Err(error_body("Speech API error", response).await);
+        }
+        response
+            .bytes()
+            .await
+            .map(|b| b.to_vec())
+            .map_err(|e| ProviderError::new(ProviderErrorCode::Network, format!("Failed to read response: {}", e)))
+    }
+
+    /// Servers that list voices at GET /audio/voices report their own; ones
+    /// without that endpoint (OpenAI itself) get the standard OpenAI voices.
+    async fn list_voices(&self) -> Result<Vec<VoiceInfo>, ProviderError> {
+        let url = format!("{}/audio/voices", self.base_url);
+        let response = self
+            .request(self.client.get(&url))
+            .send()
+            .await
+            .map_err(|e| send_error(&url, e))?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+            return Ok(OPENAI_VOICES
+                .iter()
+                .map(|id| VoiceInfo { id: id.to_string(), name: id.to_string(), language: None })
+                .collect());
+        }
+        if !status.is_success() {
+            return Err(error_body("Voice list error", response).await);
+        }
+        let voices = match response.json::<VoiceList>().await {
+            Ok(VoiceList::Names { voices }) => {
+                voices.into_iter().map(|id| VoiceInfo { name: id.clone(), id, language: None }).collect()
+            }
+            Ok(VoiceList::Objects { voices }) => voices
+                .into_iter()
+                .map(|v| VoiceInfo { name: v.name.unwrap_or_else(|| v.id.clone()), id: v.id, language: None })
+                .collect(),
+            Err(e) => return Err(format!("Unexpected voice list from {}: {}", url, e).into()),
+        };
+        Ok(voices)
+    }
+
+    fn cache_options(&self) -> String {
+        format!("{} {}", self.base_url, self.model)
+    }
+
+    fn estimate_cost(&self, texts: &[String]) -> CostEstimate {
+        let characters = texts.iter().map(|t| t.chars().count()).sum();
+        CostEstimate { characters, usd: self.price().map(|price| characters as f64 * price / 1_000_000.0) }
+    }
+}
+
+/// Provider for tests: speaks a text as its own bytes, after `delay`.
+/// "fail" in the text fails while `fail` is set, and "panic" panics.
+#[cfg(test)]
+pub(crate) mod fake {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    pub struct FakeProvider {
+        pub options: String,
+        pub delay: Duration,
+        /// Wait on a blocking thread, as the system voice does
+        pub blocking: bool,
+        pub fail: AtomicBool,
+        pub calls: AtomicUsize,
+        pub running: AtomicUsize,
+        /// Most calls in progress at once
+        pub peak: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl VoiceProvider for FakeProvider {
+        fn id(&self) -> &'static str {
+            "fake"
+        }
+
+        fn output_format(&self) -> AudioFormat {
+            AudioFormat::Wav
+        }
+
+        async fn synthesize(&self, text: &str, _voice: Option<&str>) -> Result<Vec<u8>, ProviderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+            if self.blocking {
+                let delay = self.delay;
+                let _ = tokio::task::spawn_blocking(move || std::thread::sleep(delay)).await;
+            } else {
+                tokio::time::sleep(self.delay).await;
+            }
+            self.running.fetch_sub(1, Ordering::SeqCst);
+
+            if text.contains("panic") {
+                panic!("provider panicked on {}", text);
+            }
+            if text.contains("fail") && self.fail.load(Ordering::SeqCst) {
+                return Err(format!("could not say {}", text).into());
+            }
+            Ok(text.as_bytes().to_vec())
+        }
+
+        async fn list_voices(&self) -> Result<Vec<VoiceInfo>, ProviderError> {
+            Ok(Vec::new())
+        }
+
+        fn estimate_cost(&self, texts: &[String]) -> CostEstimate {
+            CostEstimate { characters: texts.iter().map(|t| t.chars().count()).sum(), usd: None }
+        }
+
+        fn cache_options(&self) -> String {
+            self.options.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn openai(server: &MockServer, api_key: Option<&str>) -> OpenAiCompatible {
+        let settings = OpenAiTtsSettings { base_url: server.uri(), model: "tts-1-hd".to_string() };
+        OpenAiCompatible::new(&settings, api_key.map(str::to_string)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn speech_requests_carry_the_model_voice_and_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/audio/speech"))
+            .and(header("authorization", "Bearer sk-test"))
+            .and(body_json(serde_json::json!({
+                "model": "tts-1-hd",
+                "input": "Build finished",
+                "voice": "nova",
+                "response_format": "mp3"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"ID3 audio".to_vec()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let audio = openai(&server, Some("sk-test")).synthesize("Build finished", Some("nova")).await.unwrap();
+        assert_eq!(audio, b"ID3 audio");
+    }
+
+    #[tokio::test]
+    async fn speech_errors_include_the_status_and_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/audio/speech"))
+            .respond_with(ResponseTemplate::new(400).set_body_string("unknown voice"))
+            .mount(&server)
+            .await;
+
+        let error = openai(&server, None).synthesize("hi", None).await.unwrap_err();
+        assert!(error.message.contains("400") && error.message.contains("unknown voice"), "{}", error);
+        assert_eq!(error.code, ProviderErrorCode::Other);
+        // Local servers are called without authentication
+        let requests = server.received_requests().await.unwrap();
+        assert!(!requests[0].headers.contains_key("authorization"));
+        assert_eq!(requests[0].body_json::<serde_json::Value>().unwrap()["voice"], OPENAI_DEFAULT_VOICE);
+    }
+
+    #[tokio::test]
+    async fn lists_the_servers_voices() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/audio/voices"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "voices": [{ "id": "af_sky", "name": "Sky" }, { "id": "am_adam" }]
+            })))
+            .mount(&server)
+            .await;
+
+        let voices = openai(&server, None).list_voices().await.unwrap();
+        let ids: Vec<(&str, &str)> = voices.iter().map(|v| (v.id.as_str(), v.name.as_str())).collect();
+        assert_eq!(ids, [("af_sky", "Sky"), ("am_adam", "am_adam")]);
+    }
+
+    #[tokio::test]
+    async fn servers_without_a_voice_list_get_the_standard_voices() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/audio/voices"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let voices = openai(&server, None).list_voices().await.unwrap();
+        assert_eq!(voices.len(), OPENAI_VOICES.len());
+        assert_eq!(voices[0].id, "alloy");
+    }
+
+    #[tokio::test]
+    async fn voice_list_failures_are_reported() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/audio/voices"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("loading model"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/audio/voices"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html>"))
+            .mount(&server)
+            .await;
+        let provider = openai(&server, None);
+
+        let error = provider.list_voices().await.unwrap_err();
+        assert!(error.message.contains("503") && error.message.contains("loading model"), "{}", error);
+        assert_eq!(error.code, ProviderErrorCode::Server);
+        assert!(provider.list_voices().await.unwrap_err().message.starts_with("Unexpected voice list"));
+    }
+
+    #[test]
+    fn fish_audio_needs_a_key_except_for_previews() {
+        let mut settings = Config::default().global_settings;
+        settings.voice_provider = "fish_audio".to_string();
+        settings.voice_id = Some("fish-voice".to_string());
+
+        assert_eq!(from_settings(&settings, None).err().as_deref(), Some(FISH_AUDIO_KEY_MISSING));
+        let preview = preview_provider(&settings, None).unwrap();
+        assert_eq!(preview.id(), "system");
+        assert_eq!(configured_voice(&settings, preview.as_ref()), None);
+
+        let fish = preview_provider(&settings, Some("key".to_string())).unwrap();
+        assert_eq!(fish.id(), "fish_audio");
+        assert_eq!(configured_voice(&settings, fish.as_ref()), Some("fish-voice"));
+    }
+
+    #[test]
+    fn resolves_keys_from_the_referenced_store() {
+        let var = "AUDIO_NOTIFIER_TEST_OPENAI_KEY";
+        let reference = SecretRef::Env { var: var.to_string() };
+        assert_eq!(resolve_api_key(None), Ok(None));
+        assert_eq!(resolve_api_key(Some(&reference)), Ok(None));
+
+        std::env::set_var(var, "sk-env");
+        assert_eq!(resolve_api_key(Some(&reference)), Ok(Some("sk-env".to_string())));
+        std::env::remove_var(var);
+    }
+
+    #[test]
+    fn base_urls_gain_the_version_prefix() {
+        assert_eq!(normalize_base_url("http://localhost:8880").unwrap(), "http://localhost:8880/v1");
+        assert_eq!(normalize_base_url(" http://localhost:8880/v1/ ").unwrap(), "http://localhost:8880/v1");
+        assert!(normalize_base_url("ftp://example.com").is_err());
+        assert!(normalize_base_url("localhost").is_err());
+    }
+}
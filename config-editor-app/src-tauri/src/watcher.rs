@@ -0,0 +1,149 @@
+// Filesystem watcher that pushes external edits to the editor
+//
+// Watches ~/.claude (for audio-notifier.yaml and .sounds-enabled) and
+// ~/.claude/sounds, and emits:
+//   config-changed         { config, error }  when the YAML is edited outside the editor
+//   sounds-toggled         bool               when .sounds-enabled is created or removed
+//   sound-library-changed  [path]             when custom sounds are added or removed
+//
+// ~/.claude is watched non-recursively because atomic saves (ours and most
+// editors') replace the file, which would drop a watch on the file itself.
+
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, DebouncedEvent, Debouncer};
+use serde::Serialize;
+use std::fs;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::migration::{self, MigrationContext, MovedSecret};
+use crate::{
+    atomic_write, custom_sound_paths, get_config_path, get_custom_sounds_dir, get_home_dir,
+    get_sounds_enabled_path, Config, ConfigFileState,
+};
+
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Keeps the watcher alive for as long as it is managed by the app.
+pub struct ConfigWatcher {
+    _debouncer: Mutex<Debouncer<RecommendedWatcher>>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct ConfigChangedPayload {
+    /// The re-parsed config, or None if the file was removed or can't be parsed
+    config: Option<Config>,
+    error: Option<String>,
+}
+
+pub fn start(app: AppHandle) -> Result<ConfigWatcher, String> {
+    let config_path = get_config_path();
+    let claude_dir = config_path
+        .parent()
+        .ok_or("Config path has no parent directory")?
+        .to_path_buf();
+    let sounds_dir = get_custom_sounds_dir();
+
+    fs::create_dir_all(&sounds_dir)
+        .map_err(|e| format!("Failed to create sounds directory: {}", e))?;
+
+    let mut debouncer = new_debouncer(DEBOUNCE, move |result: DebounceEventResult| match result {
+        Ok(events) => handle_events(&app, &events),
+        Err(e) => println!("Config watcher error: {}", e),
+    })
+    .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+
+    debouncer
+        .watcher()
+        .watch(&claude_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch {:?}: {}", claude_dir, e))?;
+    debouncer
+        .watcher()
+        .watch(&sounds_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch {:?}: {}", sounds_dir, e))?;
+
+    Ok(ConfigWatcher {
+        _debouncer: Mutex::new(debouncer),
+    })
+}
+
+fn handle_events(app: &AppHandle, events: &[DebouncedEvent]) {
+    let config_path = get_config_path();
+    let sounds_enabled_path = get_sounds_enabled_path();
+    let sounds_dir = get_custom_sounds_dir();
+
+    let config_changed = events.iter().any(|e| e.path == config_path);
+    let sounds_toggled = events.iter().any(|e| e.path == sounds_enabled_path);
+    let library_changed = events.iter().any(|e| e.path.parent() == Some(sounds_dir.as_path()));
+
+    if config_changed {
+        emit_config_changed(app);
+    }
+
+    if sounds_toggled {
+        let _ = app.emit("sounds-toggled", sounds_enabled_path.exists());
+    }
+
+    if library_changed {
+        match custom_sound_paths() {
+            Ok(sounds) => {
+                let _ = app.emit("sound-library-changed", sounds);
+            }
+            Err(e) => println!("Config watcher: {}", e),
+        }
+    }
+}
+
+fn emit_config_changed(app: &AppHandle) {
+    let config_path = get_config_path();
+
+    // Our own saves update the loaded hash, so they don't echo back to the editor
+    let current_hash = atomic_write::current_hash(&config_path).ok();
+    let loaded_hash = app
+        .state::<ConfigFileState>()
+        .loaded_hash
+        .lock()
+        .unwrap()
+        .clone();
+    if current_hash.is_some() && current_hash == loaded_hash {
+        return;
+    }
+
+    let payload = match parse_config_file() {
+        Ok((config, moved_secrets)) => {
+            // A plaintext key pasted into the file becomes a reference in the
+            // migrated config; the next save stores it so it isn't lost
+            *app.state::<ConfigFileState>().pending_secrets.lock().unwrap() = moved_secrets;
+            ConfigChangedPayload { config, error: None }
+        }
+        Err(error) => ConfigChangedPayload { config: None, error: Some(error) },
+    };
+    let _ = app.emit("config-changed", payload);
+}
+
+/// Parse and migrate the config in memory, along with any plaintext keys the
+/// migration took out. Unlike read_config this never writes, backs up or
+/// stores anything, since the file may be mid-edit.
+fn parse_config_file() -> Result<(Option<Config>, Vec<MovedSecret>), String> {
+    let config_path = get_config_path();
+    if !config_path.exists() {
+        return Ok((None, Vec::new()));
+    }
+
+    let contents = fs::read_to_string(&config_path)
+        .map_err(|e| format!("Failed to read config file: {}", e))?;
+    let doc: serde_yaml::Value = serde_yaml::from_str(&contents)
+        .map_err(|e| format!("Config file is not valid YAML: {}", e))?;
+
+    let ctx = MigrationContext {
+        project_sounds_conf: None,
+        home: get_home_dir().unwrap_or_default(),
+        ..Default::default()
+    };
+    let (doc, _) = migration::migrate(doc, &ctx)
+        .map_err(|e| format!("Config file can't be migrated: {}", e))?;
+
+    let config = serde_yaml::from_value(doc).map_err(|e| format!("Failed to parse config: {}", e))?;
+    Ok((Some(config), ctx.moved_secrets.take()))
+}
@@ -0,0 +1,36 @@
+// The one-time move of a plaintext API key out of audio-notifier.yaml. Runs
+// read_config against a temporary HOME, so it lives in its own test binary.
+
+use audio_notifier_config_editor::secrets::{SecretRef, FISH_AUDIO_API_KEY};
+use audio_notifier_config_editor::{get_backup_dir, get_config_path, get_credentials_path, read_config};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+#[test]
+fn plaintext_key_moves_to_the_file_store_once() {
+    let home = tempfile::tempdir().unwrap();
+    std::env::set_var("HOME", home.path());
+
+    let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/config/v2.yaml");
+    fs::create_dir_all(get_config_path().parent().unwrap()).unwrap();
+    fs::copy(fixture, get_config_path()).unwrap();
+
+    let (config, hash) = read_config().unwrap();
+
+    assert_eq!(config.global_settings.fish_audio_api_key, Some(SecretRef::default_for(FISH_AUDIO_API_KEY)));
+    assert!(!fs::read_to_string(get_config_path()).unwrap().contains("v2-test-key"));
+
+    let credentials = get_credentials_path();
+    assert_eq!(fs::metadata(&credentials).unwrap().permissions().mode() & 0o777, 0o600);
+    assert_eq!(
+        config.global_settings.fish_audio_api_key.unwrap().resolve().unwrap().as_deref(),
+        Some("v2-test-key")
+    );
+
+    // The original file is kept as a backup, and nothing runs a second time
+    let backups = || fs::read_dir(get_backup_dir()).unwrap().count();
+    assert_eq!(backups(), 1);
+    let (_, second_hash) = read_config().unwrap();
+    assert_eq!(second_hash, hash);
+    assert_eq!(backups(), 1);
+}
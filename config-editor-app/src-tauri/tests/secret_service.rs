@@ -0,0 +1,179 @@
+// SecretServiceStore against a stand-in org.freedesktop.secrets on a private
+// session bus. Skipped when dbus-daemon isn't installed.
+#![cfg(target_os = "linux")]
+
+use audio_notifier_config_editor::secrets::{SecretServiceStore, SecretStore, FISH_AUDIO_API_KEY};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use zbus::object_server::ObjectServer;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+const COLLECTION_PATH: &str = "/org/freedesktop/secrets/collection/login";
+const SESSION_PATH: &str = "/org/freedesktop/secrets/session/1";
+
+type Secret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+
+fn path(p: &str) -> OwnedObjectPath {
+    OwnedObjectPath::try_from(p).unwrap()
+}
+
+/// Items by object path: (attributes, secret)
+type Items = Arc<Mutex<HashMap<String, (HashMap<String, String>, Vec<u8>)>>>;
+
+struct Service(Items);
+struct Collection(Items, Arc<Mutex<u32>>);
+struct Item(Items, String);
+struct Session;
+
+#[zbus::interface(name = "org.freedesktop.Secret.Service")]
+impl Service {
+    fn open_session(&self, algorithm: &str, _input: Value<'_>) -> zbus::fdo::Result<(OwnedValue, OwnedObjectPath)> {
+        if algorithm != "plain" {
+            return Err(zbus::fdo::Error::NotSupported(algorithm.to_string()));
+        }
+        Ok((OwnedValue::from(0u32), path(SESSION_PATH)))
+    }
+
+    fn search_items(&self, attributes: HashMap<String, String>) -> (Vec<OwnedObjectPath>, Vec<OwnedObjectPath>) {
+        let items = self.0.lock().unwrap();
+        let unlocked = items
+            .iter()
+            .filter(|(_, (attrs, _))| attributes.iter().all(|(k, v)| attrs.get(k) == Some(v)))
+            .map(|(p, _)| path(p))
+            .collect();
+        (unlocked, vec![])
+    }
+
+    fn unlock(&self, objects: Vec<OwnedObjectPath>) -> (Vec<OwnedObjectPath>, OwnedObjectPath) {
+        (objects, path("/"))
+    }
+
+    fn read_alias(&self, name: &str) -> OwnedObjectPath {
+        if name == "default" {
+            path(COLLECTION_PATH)
+        } else {
+            path("/")
+        }
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.Secret.Collection")]
+impl Collection {
+    async fn create_item(
+        &self,
+        properties: HashMap<String, OwnedValue>,
+        secret: Secret,
+        replace: bool,
+        #[zbus(object_server)] server: &ObjectServer,
+    ) -> zbus::fdo::Result<(OwnedObjectPath, OwnedObjectPath)> {
+        let attrs: HashMap<String, String> = properties
+            .get("org.freedesktop.Secret.Item.Attributes")
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs("missing attributes".to_string()))?
+            .try_clone()
+            .ok()
+            .and_then(|v| v.try_into().ok())
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs("attributes must be a string map".to_string()))?;
+        let item_path = {
+            let mut items = self.0.lock().unwrap();
+            let existing = items.iter().find(|(_, (a, _))| *a == attrs).map(|(p, _)| p.clone());
+            let item_path = match existing {
+                Some(p) if replace => p,
+                _ => {
+                    let mut next = self.1.lock().unwrap();
+                    *next += 1;
+                    format!("{}/{}", COLLECTION_PATH, next)
+                }
+            };
+            items.insert(item_path.clone(), (attrs, secret.2));
+            item_path
+        };
+        server.at(item_path.as_str(), Item(self.0.clone(), item_path.clone())).await?;
+        Ok((path(&item_path), path("/")))
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.Secret.Item")]
+impl Item {
+    fn get_secret(&self, session: OwnedObjectPath) -> zbus::fdo::Result<Secret> {
+        let items = self.0.lock().unwrap();
+        let (_, value) = items.get(&self.1).ok_or_else(|| zbus::fdo::Error::UnknownObject(self.1.clone()))?;
+        Ok((session, vec![], value.clone(), "text/plain".to_string()))
+    }
+
+    fn delete(&self) -> OwnedObjectPath {
+        self.0.lock().unwrap().remove(&self.1);
+        path("/")
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.Secret.Session")]
+impl Session {
+    fn close(&self) {}
+}
+
+struct Bus(Child);
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Start a private session bus, returning None when dbus-daemon is missing.
+fn private_bus() -> Option<(Bus, String)> {
+    let mut child = Command::new("dbus-daemon")
+        .args(["--session", "--nofork", "--print-address"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut address = String::new();
+    BufReader::new(child.stdout.take()?).read_line(&mut address).ok()?;
+    Some((Bus(child), address.trim().to_string()))
+}
+
+#[test]
+fn stores_reads_replaces_and_deletes_items() {
+    let Some((_bus, address)) = private_bus() else {
+        eprintln!("dbus-daemon not found; skipping");
+        return;
+    };
+
+    let items: Items = Default::default();
+    let _service = zbus::blocking::connection::Builder::address(address.as_str())
+        .unwrap()
+        .name("org.freedesktop.secrets")
+        .unwrap()
+        .serve_at("/org/freedesktop/secrets", Service(items.clone()))
+        .unwrap()
+        .serve_at(COLLECTION_PATH, Collection(items.clone(), Default::default()))
+        .unwrap()
+        .serve_at(SESSION_PATH, Session)
+        .unwrap()
+        .build()
+        .unwrap();
+
+    std::env::set_var("DBUS_SESSION_BUS_ADDRESS", &address);
+    let store = SecretServiceStore::connect().unwrap();
+
+    assert_eq!(store.get(FISH_AUDIO_API_KEY).unwrap(), None);
+    store.set(FISH_AUDIO_API_KEY, "first-key").unwrap();
+    assert_eq!(store.get(FISH_AUDIO_API_KEY).unwrap().as_deref(), Some("first-key"));
+
+    store.set(FISH_AUDIO_API_KEY, "second-key").unwrap();
+    store.set("openai_api_key", "openai-key").unwrap();
+    {
+        let items = items.lock().unwrap();
+        assert_eq!(items.len(), 2);
+        let attrs = items.values().map(|(attrs, _)| attrs).find(|a| a["name"] == FISH_AUDIO_API_KEY).unwrap();
+        assert_eq!(attrs["application"], "audio-notifier");
+    }
+    assert_eq!(store.get(FISH_AUDIO_API_KEY).unwrap().as_deref(), Some("second-key"));
+
+    store.delete(FISH_AUDIO_API_KEY).unwrap();
+    assert_eq!(store.get(FISH_AUDIO_API_KEY).unwrap(), None);
+    assert_eq!(store.get("openai_api_key").unwrap().as_deref(), Some("openai-key"));
+}